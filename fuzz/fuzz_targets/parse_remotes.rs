@@ -0,0 +1,53 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+/// stands in for src/git.rs, which can't be built without the rest of marge. remotes are the same
+/// if their names are, like there
+mod git {
+    use std::hash::{Hash, Hasher};
+
+    #[derive(Clone, Debug)]
+    pub struct Remote {
+        pub name: String,
+        pub host: String,
+        pub owner: String,
+        pub repo: String,
+    }
+
+    impl Eq for Remote {}
+
+    impl PartialEq<Remote> for Remote {
+        fn eq(&self, other: &Remote) -> bool {
+            self.name == other.name
+        }
+    }
+
+    impl Hash for Remote {
+        fn hash<H>(&self, hasher: &mut H)
+        where
+            H: Hasher,
+        {
+            self.name.hash(hasher);
+        }
+    }
+}
+
+#[path = "../../src/remote.rs"]
+mod remote;
+
+// whatever git remote -v prints, marge gets at most one remote per name, in order, and none of
+// their parts run into the whitespace around them
+fuzz_target!(|out: &str| {
+    let Ok(remotes) = remote::parse_remotes(out) else {
+        return;
+    };
+    assert!(!remotes.is_empty());
+    assert!(remotes.windows(2).all(|w| w[0].name < w[1].name));
+    for r in &remotes {
+        assert!(!r.name.is_empty() && !r.host.is_empty());
+        for part in [&r.name, &r.host, &r.owner, &r.repo] {
+            assert!(!part.contains(char::is_whitespace), "{r:?}");
+        }
+    }
+});
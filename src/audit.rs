@@ -0,0 +1,154 @@
+use std::{collections::HashMap, io::Write, path::PathBuf, sync::Mutex, sync::OnceLock};
+
+use anyhow::{anyhow, Context};
+use log::{info, warn};
+use regex::Regex;
+
+/// every change marge makes to the remote, for tracing who force-pushed what in a
+/// shared repo. set up once at startup
+static AUDIT: OnceLock<Audit> = OnceLock::new();
+
+struct Audit {
+    run_id: String,
+    /// owner/repo, one file is shared by all repos
+    repo: String,
+    /// appended to by every run, never truncated
+    file: PathBuf,
+    /// what this run changed, to show at the end
+    trail: Mutex<Vec<String>>,
+}
+
+/** the audit file in the state dir */
+pub fn file() -> anyhow::Result<PathBuf> {
+    Ok(crate::git::state_dir()?.join("audit.log"))
+}
+
+/** start recording changes to repo, with an id that tells this run's entries apart from the others' */
+pub fn init(repo: String) -> anyhow::Result<()> {
+    let file = file()?;
+    let run_id = format!(
+        "{}-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    );
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    info!(
+        "this is run {run_id}, its changes to the remote go to {}",
+        file.display()
+    );
+    let _ = AUDIT.set(Audit {
+        run_id,
+        repo,
+        file,
+        trail: Mutex::new(vec![]),
+    });
+    Ok(())
+}
+
+pub fn run_id() -> Option<&'static str> {
+    AUDIT.get().map(|a| a.run_id.as_str())
+}
+
+/** note a change to the remote, in the audit file and in the trail */
+pub fn record(action: &str) {
+    let Some(audit) = AUDIT.get() else {
+        return;
+    };
+    let timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%z");
+    let entry = format!("{timestamp} {} {} {action}", audit.run_id, audit.repo);
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&audit.file)
+        .and_then(|mut f| writeln!(f, "{entry}"));
+    if let Err(e) = written {
+        warn!(
+            "could not write to the audit file {}: {e}",
+            audit.file.display()
+        );
+    }
+    info!("{action}");
+    if let Ok(mut trail) = audit.trail.lock() {
+        trail.push(format!("{timestamp} {action}"));
+    }
+}
+
+/** what this run changed on the remote so far */
+pub fn trail() -> Vec<String> {
+    AUDIT
+        .get()
+        .and_then(|a| a.trail.lock().ok().map(|t| t.clone()))
+        .unwrap_or_default()
+}
+
+/** what the run changed on the remote, as it was written to the audit file */
+pub fn entries(run_id: &str) -> anyhow::Result<Vec<String>> {
+    let file = file()?;
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+    let contents = std::fs::read_to_string(&file)
+        .context(format!("could not read the audit file {}", file.display()))?;
+    // <timestamp> <run id> <repo> <action>, see record
+    Ok(contents
+        .lines()
+        .filter_map(|l| {
+            let mut fields = l.splitn(4, ' ');
+            let timestamp = fields.next()?;
+            if fields.next()? != run_id {
+                return None;
+            }
+            Some(format!("{timestamp} {}", fields.nth(1)?))
+        })
+        .collect())
+}
+
+/** the bases the pulls of repo had before a run retargeted them, leaving out the ones it merged.
+the last run that retargeted any if there's no run id */
+pub fn retargets(repo: &str, run_id: Option<&str>) -> anyhow::Result<(String, Vec<(u64, String)>)> {
+    let file = file()?;
+    let contents = std::fs::read_to_string(&file)
+        .context(format!("could not read the audit file {}", file.display()))?;
+    // <timestamp> <run id> <repo> <action>, see record
+    let entry_re = Regex::new(r"^\S+ (\S+) (\S+) (retargeted|merged) #(\d+)(?: from (\S+) onto)?")
+        .expect("valid regex");
+    let entries = contents
+        .lines()
+        .filter_map(|l| entry_re.captures(l))
+        .filter(|caps| &caps[2] == repo)
+        .collect::<Vec<_>>();
+
+    let run_id = match run_id {
+        Some(run_id) => run_id.to_owned(),
+        None => entries
+            .iter()
+            .rev()
+            .find(|caps| caps.get(5).is_some())
+            .map(|caps| caps[1].to_owned())
+            .ok_or_else(|| anyhow!("no run retargeted any pulls of {repo}"))?,
+    };
+
+    let mut bases: HashMap<u64, String> = HashMap::new();
+    let mut order = vec![];
+    for caps in entries.iter().filter(|caps| caps[1] == run_id) {
+        let number = caps[4].parse::<u64>()?;
+        match (&caps[3], caps.get(5)) {
+            // only the first retarget knows the base the pull had before the run
+            ("retargeted", Some(base)) if !bases.contains_key(&number) => {
+                bases.insert(number, base.as_str().to_owned());
+                order.push(number);
+            }
+            ("merged", _) => {
+                bases.remove(&number);
+            }
+            _ => (),
+        }
+    }
+    let retargets = order
+        .into_iter()
+        .filter_map(|number| bases.remove(&number).map(|base| (number, base)))
+        .collect();
+    Ok((run_id, retargets))
+}
@@ -0,0 +1,427 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+
+use crate::{
+    forge::{Checks, Forge, MergeMethod, Pull, Ref},
+    git::Remote,
+    network,
+    settings::Network,
+};
+
+const API_VERSION: &str = "api-version=7.1";
+
+/// how often to look whether a completed pull got merged, azure does that in the background
+const MERGE_POLLS: u32 = 30;
+
+/// azure repos on dev.azure.com, through the azure devops REST API. the remote's owner is
+/// `organization/project`
+pub struct Azure {
+    client: reqwest::Client,
+    token: String,
+    timeout: Option<Duration>,
+}
+
+/// azure wraps every list in one of these
+#[derive(Deserialize)]
+struct List<T> {
+    value: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzurePull {
+    pull_request_id: u64,
+    title: Option<String>,
+    description: Option<String>,
+    created_by: Option<AzureIdentity>,
+    creation_date: Option<DateTime<Utc>>,
+    source_ref_name: String,
+    target_ref_name: String,
+    last_merge_source_commit: Option<AzureCommit>,
+    last_merge_target_commit: Option<AzureCommit>,
+    last_merge_commit: Option<AzureCommit>,
+    #[serde(default)]
+    labels: Vec<AzureLabel>,
+    status: String,
+    merge_status: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureRepo {
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureIdentity {
+    unique_name: String,
+}
+
+/// the profile of whoever the token belongs to
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureProfile {
+    email_address: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureCommit {
+    commit_id: String,
+}
+
+#[derive(Deserialize)]
+struct AzureLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct AzureStatus {
+    state: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureCommitDiffs {
+    behind_count: u64,
+}
+
+impl AzurePull {
+    fn into_pull(self, remote: &Remote) -> Pull {
+        let sha = |c: Option<AzureCommit>| c.map(|c| c.commit_id).unwrap_or_default();
+        Pull {
+            number: self.pull_request_id,
+            html_url: format!(
+                "https://dev.azure.com/{}/_git/{}/pullrequest/{}",
+                remote.owner, remote.repo, self.pull_request_id
+            )
+            .parse()
+            .ok(),
+            title: self.title,
+            body: self.description,
+            author: self.created_by.map(|c| c.unique_name),
+            labels: self.labels.into_iter().map(|l| l.name).collect(),
+            created_at: self.creation_date,
+            // azure only has the creation date
+            updated_at: self.creation_date,
+            head: Ref {
+                branch: branch_name(&self.source_ref_name).to_owned(),
+                sha: sha(self.last_merge_source_commit),
+            },
+            base: Ref {
+                branch: branch_name(&self.target_ref_name).to_owned(),
+                sha: sha(self.last_merge_target_commit),
+            },
+            commits: None,
+            additions: None,
+            deletions: None,
+            merged: self.status == "completed",
+            mergeable: match self.merge_status.as_deref() {
+                Some("succeeded") => Some(true),
+                Some("conflicts" | "failure" | "rejectedByPolicy") => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+/** azure names the branches of a pull by their full ref */
+fn branch_name(full_ref: &str) -> &str {
+    full_ref.strip_prefix("refs/heads/").unwrap_or(full_ref)
+}
+
+impl Azure {
+    pub fn new(
+        token: String,
+        timeout: Option<Duration>,
+        network: &Network,
+    ) -> anyhow::Result<Azure> {
+        Ok(Azure {
+            client: network::http_client(network)?,
+            token,
+            timeout,
+        })
+    }
+
+    fn repo_url(&self, remote: &Remote, path: &str) -> String {
+        let query = if path.contains('?') { '&' } else { '?' };
+        format!(
+            "https://dev.azure.com/{}/_apis/git/repositories/{}{path}{query}{API_VERSION}",
+            remote.owner, remote.repo
+        )
+    }
+
+    /** send the request with the token as basic auth, turning azure's error messages into errors */
+    async fn send(&self, request: reqwest::RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        let mut request = request
+            .basic_auth("", Some(&self.token))
+            .header("Accept", "application/json");
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        // a token azure doesn't like gets the html of the sign-in page, with a 203
+        if status.is_success() && status.as_u16() != 203 {
+            Ok(response)
+        } else {
+            let message = response.text().await.unwrap_or_default();
+            let message = if message.starts_with('{') {
+                message
+            } else {
+                "the token may be wrong or expired".to_owned()
+            };
+            Err(anyhow!("azure devops answered {status}: {message}"))
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: String) -> anyhow::Result<T> {
+        Ok(self.send(self.client.get(url)).await?.json().await?)
+    }
+
+    async fn get_pull(&self, remote: &Remote, number: u64) -> anyhow::Result<AzurePull> {
+        self.get(self.repo_url(remote, &format!("/pullrequests/{number}")))
+            .await
+    }
+}
+
+impl Forge for Azure {
+    fn pulls<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<Vec<Pull>>> {
+        async move {
+            let url = self.repo_url(
+                remote,
+                "/pullrequests?searchCriteria.status=active&$top=100",
+            );
+            let pulls: List<AzurePull> = self.get(url).await.context(format!(
+                "could not get pulls for repo {}/{}",
+                remote.owner, remote.repo
+            ))?;
+            Ok(pulls
+                .value
+                .into_iter()
+                .map(|p| p.into_pull(remote))
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn pull<'a>(&'a self, remote: &'a Remote, number: u64) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        async move {
+            let pull = self.get_pull(remote, number).await?;
+            let commits: List<serde_json::Value> = self
+                .get(self.repo_url(remote, &format!("/pullrequests/{number}/commits")))
+                .await?;
+            Ok(Pull {
+                commits: Some(commits.value.len() as u64),
+                ..pull.into_pull(remote)
+            })
+        }
+        .boxed()
+    }
+
+    fn default_branch<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            let found: AzureRepo = self.get(self.repo_url(remote, "")).await?;
+            let full_ref = found
+                .default_branch
+                .context("azure devops did not say which branch is the default")?;
+            Ok(branch_name(&full_ref).to_owned())
+        }
+        .boxed()
+    }
+
+    fn check_access<'a>(
+        &'a self,
+        remote: &'a Remote,
+        _read_only: bool,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        // azure has no cheap way to ask for push permissions, so this only makes sure
+        // the token gets at the repo at all
+        async move {
+            self.send(self.client.get(self.repo_url(remote, "")))
+                .await
+                .context(format!(
+                    "could not get {}/{}, the token may not have access to it",
+                    remote.owner, remote.repo
+                ))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn login<'a>(&'a self, _remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            let profile: AzureProfile = self
+                .get(format!(
+                    "https://app.vssps.visualstudio.com/_apis/profile/profiles/me?{API_VERSION}"
+                ))
+                .await?;
+            Ok(profile.email_address)
+        }
+        .boxed()
+    }
+
+    fn retarget<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let url = self.repo_url(remote, &format!("/pullrequests/{number}"));
+            let body = json!({ "targetRefName": format!("refs/heads/{base}") });
+            self.send(self.client.patch(url).json(&body)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn open_pull<'a>(
+        &'a self,
+        remote: &'a Remote,
+        title: String,
+        body: String,
+        head: &'a str,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        async move {
+            let request = self
+                .client
+                .post(self.repo_url(remote, "/pullrequests"))
+                .json(&json!({
+                    "sourceRefName": format!("refs/heads/{head}"),
+                    "targetRefName": format!("refs/heads/{base}"),
+                    "title": title,
+                    "description": body,
+                }));
+            let pull: AzurePull = self.send(request).await?.json().await?;
+            Ok(pull.into_pull(remote))
+        }
+        .boxed()
+    }
+
+    fn merge<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        method: MergeMethod,
+    ) -> BoxFuture<'a, anyhow::Result<Option<String>>> {
+        let method = match method {
+            MergeMethod::Rebase => "rebase",
+            MergeMethod::Squash => "squash",
+            MergeMethod::Merge => "noFastForward",
+        };
+        async move {
+            // completing needs the commit the pull is at, so nothing pushed since gets merged
+            let pull = self.get_pull(remote, number).await?;
+            let head = pull
+                .last_merge_source_commit
+                .context(format!("azure doesn't know the head of #{number} yet"))?;
+            let url = self.repo_url(remote, &format!("/pullrequests/{number}"));
+            let body = json!({
+                "status": "completed",
+                "lastMergeSourceCommit": { "commitId": head.commit_id },
+                "completionOptions": { "mergeStrategy": method },
+            });
+            self.send(self.client.patch(url).json(&body)).await?;
+
+            for _ in 0..MERGE_POLLS {
+                let pull = self.get_pull(remote, number).await?;
+                if pull.status == "completed" {
+                    return Ok(pull.last_merge_commit.map(|c| c.commit_id));
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(anyhow!(
+                "azure didn't complete #{number} in time, it may still be merging it or a policy blocks it"
+            ))
+        }
+        .boxed()
+    }
+
+    fn update_branch<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        number: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move { Err(anyhow!("azure devops can't update #{number} with its base")) }.boxed()
+    }
+
+    fn dispatch_workflow<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        workflow: &'a str,
+        _branch: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            Err(anyhow!(
+                "azure devops can't dispatch the workflow {workflow}"
+            ))
+        }
+        .boxed()
+    }
+
+    fn workflow_run<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        workflow: &'a str,
+        _sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        async move {
+            Err(anyhow!(
+                "azure devops can't look up runs of the workflow {workflow}"
+            ))
+        }
+        .boxed()
+    }
+
+    fn checks<'a>(
+        &'a self,
+        remote: &'a Remote,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        async move {
+            let statuses: List<AzureStatus> = self
+                .get(self.repo_url(remote, &format!("/commits/{sha}/statuses")))
+                .await?;
+            let states = statuses
+                .value
+                .iter()
+                .map(|s| s.state.as_str())
+                .filter(|s| !matches!(*s, "notApplicable" | "notSet"))
+                .collect::<Vec<&str>>();
+            Ok(if states.is_empty() {
+                Checks::Missing
+            } else if states.iter().any(|s| matches!(*s, "failed" | "error")) {
+                Checks::Failing
+            } else if states.contains(&"pending") {
+                Checks::Pending
+            } else {
+                Checks::Passing
+            })
+        }
+        .boxed()
+    }
+
+    fn behind_by<'a>(
+        &'a self,
+        remote: &'a Remote,
+        base: &'a str,
+        head: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        async move {
+            let path = format!(
+                "/diffs/commits?baseVersion={base}&baseVersionType=branch&targetVersion={head}&targetVersionType=commit&$top=0"
+            );
+            let diffs: AzureCommitDiffs = self.get(self.repo_url(remote, &path)).await?;
+            Ok(diffs.behind_count)
+        }
+        .boxed()
+    }
+}
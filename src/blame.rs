@@ -0,0 +1,104 @@
+use regex::Regex;
+use tokio::process::Command;
+
+use crate::command_log::Logged;
+
+/// one line of `git blame --line-porcelain`
+struct Blamed {
+    sha: String,
+    author: String,
+    summary: String,
+    content: String,
+}
+
+/** who last touched the lines a conflicted file conflicts on, as `author (#pull)` or `author (sha)`.
+ * while rebasing, those are the lines on the base's side, the ones that came in since the pull was opened */
+pub async fn owners(path: &str) -> Vec<String> {
+    let Ok(file) = tokio::fs::read_to_string(path).await else {
+        return vec![];
+    };
+    let output = Command::new("git")
+        .args(["blame", "--line-porcelain", "HEAD", "--", path])
+        .logged_output()
+        .await;
+    let blamed = match output {
+        Ok(o) if o.status.success() => parse(&String::from_utf8_lossy(&o.stdout)),
+        // the file may not be there on the base's side at all
+        _ => return vec![],
+    };
+
+    let pull = Regex::new(r"\(#(\d+)\)$|^Merge pull request #(\d+)").expect("the regex is valid");
+    let mut owners = vec![];
+    // the hunks come in the same order as the lines in HEAD
+    let mut cursor = 0;
+    for hunk in base_hunks(&file) {
+        let Some(start) = (cursor..blamed.len().saturating_sub(hunk.len() - 1)).find(|i| {
+            hunk.iter()
+                .zip(&blamed[*i..])
+                .all(|(line, b)| *line == b.content)
+        }) else {
+            continue;
+        };
+        for b in &blamed[start..start + hunk.len()] {
+            let owner = format!("{} ({})", b.author, pull_of(&pull, b));
+            if !owners.contains(&owner) {
+                owners.push(owner);
+            }
+        }
+        cursor = start + hunk.len();
+    }
+    owners
+}
+
+/** the lines between `<<<<<<<` and the next marker of each conflict, skipping empty sides */
+fn base_hunks(file: &str) -> Vec<Vec<&str>> {
+    let mut hunks = vec![];
+    let mut hunk: Option<Vec<&str>> = None;
+    for line in file.lines() {
+        if line.starts_with("<<<<<<<") {
+            hunk = Some(vec![]);
+        } else if line.starts_with("|||||||") || line.starts_with("=======") {
+            hunks.extend(hunk.take().filter(|h| !h.is_empty()));
+        } else if let Some(hunk) = &mut hunk {
+            hunk.push(line);
+        }
+    }
+    hunks
+}
+
+fn parse(porcelain: &str) -> Vec<Blamed> {
+    let mut blamed = vec![];
+    let (mut sha, mut author, mut summary) = (String::new(), String::new(), String::new());
+    for line in porcelain.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            blamed.push(Blamed {
+                sha: sha.clone(),
+                author: author.clone(),
+                summary: summary.clone(),
+                content: content.to_owned(),
+            });
+        } else if let Some(a) = line.strip_prefix("author ") {
+            a.clone_into(&mut author);
+        } else if let Some(s) = line.strip_prefix("summary ") {
+            s.clone_into(&mut summary);
+        } else if let Some(first) = line
+            .split(' ')
+            .next()
+            .filter(|s| s.len() >= 40 && s.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            // the header of each line starts with the sha of its commit
+            first.clone_into(&mut sha);
+        }
+    }
+    blamed
+}
+
+/** the pull a commit came from if its subject says, like squash and merge commits do, its short sha otherwise */
+fn pull_of(pull: &Regex, b: &Blamed) -> String {
+    pull.captures(&b.summary)
+        .and_then(|c| c.get(1).or(c.get(2)))
+        .map_or_else(
+            || b.sha.chars().take(7).collect(),
+            |n| format!("#{}", n.as_str()),
+        )
+}
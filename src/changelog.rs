@@ -0,0 +1,32 @@
+use std::collections::BTreeMap;
+
+use crate::merge_candidate::MergeCandidate;
+
+/// where pulls without labels end up
+const UNLABELED: &str = "other";
+
+/** release notes for the merged pulls, grouped by their first label */
+pub fn generate(merged: &[MergeCandidate]) -> String {
+    let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for c in merged {
+        let label = c.pull.labels.first().map_or(UNLABELED, String::as_str);
+        let title = c.pull.title.as_deref().unwrap_or("<no title>");
+        let author = match &c.pull.author {
+            Some(author) => format!(" by @{author}"),
+            None => String::new(),
+        };
+        groups
+            .entry(label)
+            .or_default()
+            .push(format!("- {title} (#{}){author}", c.pull.number));
+    }
+
+    // the unlabeled ones go last
+    let other = groups.remove(UNLABELED);
+    groups
+        .into_iter()
+        .chain(other.map(|lines| (UNLABELED, lines)))
+        .map(|(label, lines)| format!("### {label}\n\n{}\n", lines.join("\n")))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
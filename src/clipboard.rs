@@ -0,0 +1,28 @@
+use log::{info, warn};
+
+/// the system clipboard, opened the first time something gets copied.
+///
+/// on x11 the copied text is only there as long as we hold on to the clipboard,
+/// so it lives as long as marge does.
+#[derive(Default)]
+pub struct Clipboard(Option<arboard::Clipboard>);
+
+impl Clipboard {
+    /** put text into the clipboard, what describes it for the log */
+    pub fn copy(&mut self, what: &str, text: String) {
+        let clipboard = match &mut self.0 {
+            Some(clipboard) => clipboard,
+            None => match arboard::Clipboard::new() {
+                Ok(clipboard) => self.0.insert(clipboard),
+                Err(e) => {
+                    warn!("could not open the clipboard: {e}");
+                    return;
+                }
+            },
+        };
+        match clipboard.set_text(text) {
+            Ok(()) => info!("copied {what}"),
+            Err(e) => warn!("could not copy {what}: {e}"),
+        }
+    }
+}
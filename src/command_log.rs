@@ -0,0 +1,99 @@
+use std::{
+    future::Future,
+    process::{ExitStatus, Output},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::process::Command;
+
+use crate::observer;
+
+/// a command marge ran, to reproduce it by hand
+#[derive(Clone, Debug)]
+pub struct Ran {
+    /// the command as it would be typed into a shell
+    pub line: String,
+    pub cwd: String,
+    pub took: Duration,
+    /// none if it couldn't be started or was killed by a signal
+    pub code: Option<i32>,
+}
+
+/// every command marge ran, oldest first
+static LOG: Mutex<Vec<Ran>> = Mutex::new(vec![]);
+
+/** the commands run so far, oldest first */
+pub fn ran() -> Vec<Ran> {
+    LOG.lock().map(|log| log.clone()).unwrap_or_default()
+}
+
+/** note that command ran since started and exited with status, if it ran at all, and tell the
+observers with its output if there is one */
+pub fn record(
+    command: &Command,
+    started: Instant,
+    status: Option<ExitStatus>,
+    output: Option<&Output>,
+) {
+    let command = command.as_std();
+    let line = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let cwd = command
+        .get_current_dir()
+        .map(|dir| dir.to_owned())
+        .or_else(|| std::env::current_dir().ok())
+        .map_or("?".to_owned(), |dir| dir.display().to_string());
+    let ran = Ran {
+        line,
+        cwd,
+        took: started.elapsed(),
+        code: status.and_then(|s| s.code()),
+    };
+    observer::command_ran(&ran, output);
+    if let Ok(mut log) = LOG.lock() {
+        log.push(ran);
+    }
+}
+
+/** the arg in single quotes if a shell would split it up or expand it */
+pub fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// running a command and noting it in the log
+pub trait Logged {
+    /** like `output`, noting the command in the log */
+    fn logged_output(&mut self) -> impl Future<Output = std::io::Result<Output>> + Send + '_;
+
+    /** like `status`, noting the command in the log */
+    fn logged_status(&mut self) -> impl Future<Output = std::io::Result<ExitStatus>> + Send + '_;
+}
+
+impl Logged for Command {
+    async fn logged_output(&mut self) -> std::io::Result<Output> {
+        let started = Instant::now();
+        let output = self.output().await;
+        let ran = output.as_ref().ok();
+        record(self, started, ran.map(|o| o.status), ran);
+        output
+    }
+
+    async fn logged_status(&mut self) -> std::io::Result<ExitStatus> {
+        let started = Instant::now();
+        let status = self.status().await;
+        record(self, started, status.as_ref().ok().copied(), None);
+        status
+    }
+}
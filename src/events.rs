@@ -5,43 +5,86 @@ use crossterm::event::{ Event, EventStream, KeyCode, KeyEvent, KeyModifiers,
 };
 use futures::{
     future::{self, FutureExt},
-    select, StreamExt,
+    select,
+    stream::{self, BoxStream},
+    Stream, StreamExt,
 };
 
 use futures_timer::Delay;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::signal::unix;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::{oneshot, watch};
 use tokio::time::Duration;
 use tokio_stream::wrappers::SignalStream;
 
 #[derive(Debug)]
 pub enum AppEvent {
     Input(KeyEvent),
+    /// text pasted into the terminal, all at once instead of key by key
+    Paste(String),
     Signal,
     Error(anyhow::Error),
     Tick,
+    /// a background task needs the terminal for an interactive command
+    Foreground(Foreground),
+    /// a background git task finished. what it found waits in the receiver of the state that started it
+    GitDone(&'static str, Result<(), String>),
+}
+
+/// handshake for handing the terminal to a background task.
+/// the task waits for `granted`, runs its command and signals `done` when it's finished.
+#[derive(Debug)]
+pub struct Foreground {
+    pub granted: oneshot::Sender<()>,
+    pub done: oneshot::Receiver<()>,
 }
 
 pub struct EventPump {
     rx: Receiver<AppEvent>,
     // Need to be kept around to prevent disposing the sender side.
-    _tx: Sender<AppEvent>,
+    tx: Sender<AppEvent>,
+    paused: watch::Sender<bool>,
 }
 
 impl EventPump {
-    #[must_use] pub fn new(tick_rate: Duration) -> EventPump {
-        let (tx, rx) = channel(10);
+    #[must_use] pub fn new(tick_rate: Duration, capacity: usize) -> EventPump {
+        EventPump::start(tick_rate, capacity, || key_events().boxed())
+    }
+
+    /// reads lines from stdin instead of keys from the terminal, for --plain
+    #[must_use] pub fn plain(tick_rate: Duration, capacity: usize) -> EventPump {
+        EventPump::start(tick_rate, capacity, line_events)
+    }
+
+    fn start(
+        tick_rate: Duration,
+        capacity: usize,
+        input: fn() -> BoxStream<'static, std::io::Result<AppEvent>>,
+    ) -> EventPump {
+        let (tx, rx) = channel(capacity.max(1));
+        let (paused, paused_rx) = watch::channel(false);
         let sent_tx = tx.clone();
         tokio::spawn(async move {
-            let result = poll_events(tick_rate, &sent_tx).await;
-            let _ = if let Err(err) = result {
-                sent_tx.send(AppEvent::Error(err)).await
-            } else {
-                let err = anyhow!("event ended without err?"); 
-                sent_tx.send(AppEvent::Error(err)).await
-            };
+            let result = poll_events(tick_rate, input, &sent_tx, paused_rx).await;
+            let Err(err) = result;
+            let _ = sent_tx.send(AppEvent::Error(err)).await;
         });
-        EventPump { rx, _tx: tx }
+        EventPump { rx, tx, paused }
+    }
+
+    /// a sender for background tasks to push their own events into the pump
+    #[must_use] pub fn sender(&self) -> Sender<AppEvent> {
+        self.tx.clone()
+    }
+
+    /// stop reading from the terminal until `resume` is called.
+    pub fn pause(&self) {
+        let _ = self.paused.send(true);
+    }
+
+    pub fn resume(&self) {
+        let _ = self.paused.send(false);
     }
 
     /// Attempts to read an event.
@@ -51,15 +94,63 @@ impl EventPump {
     }
 }
 
-async fn poll_events(tick_rate: Duration, tx: &Sender<AppEvent>) -> anyhow::Result<Infallible> {
-    let millis = u64::try_from(tick_rate.as_millis())?;
-    let mut reader = EventStream::new().filter_map(|e| {
+fn key_events() -> impl Stream<Item = std::io::Result<AppEvent>> {
+    EventStream::new().filter_map(|e| {
         future::ready(match e {
-            Ok(Event::Key(key_event)) => Some(Ok(key_event)),
+            Ok(Event::Key(key_event)) => Some(Ok(map_event(key_event))),
+            Ok(Event::Paste(text)) => Some(Ok(AppEvent::Paste(text))),
             Err(e) => Some(Err(e)),
             _ => None,
         })
-    });
+    })
+}
+
+/** the keys typed on each line of stdin, pressed once the line is entered. an empty line is
+enter, the names of the keys marge uses stand for them. once stdin is closed marge quits */
+fn line_events() -> BoxStream<'static, std::io::Result<AppEvent>> {
+    let lines = BufReader::new(tokio::io::stdin()).lines();
+    stream::unfold(lines, |mut lines| async move {
+        match lines.next_line().await {
+            Ok(Some(line)) => Some((
+                stream::iter(map_line(&line).into_iter().map(Ok)).boxed(),
+                lines,
+            )),
+            Ok(None) => None,
+            Err(e) => Some((stream::once(future::ready(Err(e))).boxed(), lines)),
+        }
+    })
+    .flatten()
+    .chain(stream::once(future::ready(Ok(AppEvent::Signal))))
+    .chain(stream::pending())
+    .boxed()
+}
+
+fn map_line(line: &str) -> Vec<AppEvent> {
+    let key = |code| AppEvent::Input(KeyEvent::new(code, KeyModifiers::NONE));
+    let code = match line.trim() {
+        "" | "enter" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "backspace" => KeyCode::Backspace,
+        typed => return typed.chars().map(|c| key(KeyCode::Char(c))).collect(),
+    };
+    vec![key(code)]
+}
+
+async fn poll_events(
+    tick_rate: Duration,
+    input: fn() -> BoxStream<'static, std::io::Result<AppEvent>>,
+    tx: &Sender<AppEvent>,
+    mut paused: watch::Receiver<bool>,
+) -> anyhow::Result<Infallible> {
+    let millis = u64::try_from(tick_rate.as_millis())?;
+    // dropped while paused, the event stream would otherwise keep reading from the terminal
+    let mut reader = Some(input());
     let mut signal_int = SignalStream::new(unix::signal(unix::SignalKind::interrupt())?);
     let mut signal_quit = SignalStream::new(unix::signal(unix::SignalKind::quit())?);
     let mut signal_term = SignalStream::new(unix::signal(unix::SignalKind::terminate())?);
@@ -67,6 +158,13 @@ async fn poll_events(tick_rate: Duration, tx: &Sender<AppEvent>) -> anyhow::Resu
     let mut since_last_tick = 0;
 
     let last_e = loop {
+        if *paused.borrow_and_update() {
+            drop(reader.take());
+            while *paused.borrow_and_update() {
+                paused.changed().await?;
+            }
+            reader = Some(input());
+        }
         while since_last_tick > millis {
             since_last_tick = since_last_tick .saturating_sub(millis);
         }
@@ -76,15 +174,20 @@ async fn poll_events(tick_rate: Duration, tx: &Sender<AppEvent>) -> anyhow::Resu
         let mut sigint = signal_int.next().fuse();
         let mut sigquit = signal_quit.next().fuse();
         let mut sigterm = signal_term.next().fuse();
-        let mut event = reader.next().fuse();
+        let mut event = reader.as_mut().expect("only taken while paused").next().fuse();
+        let mut pause = Box::pin(paused.changed()).fuse();
 
         let e: AppEvent = select! {
             () = delay => {
                 AppEvent::Tick
             },
+            changed = pause => {
+                changed?;
+                continue;
+            },
             maybe_event = event => {
                 match maybe_event {
-                    Some(Ok(key_event)) => map_event(key_event),
+                    Some(Ok(e)) => e,
                     Some(Err(e)) => break Err(anyhow!(e)),
                     None => break Err(anyhow!("none in event stream!")),
                 }
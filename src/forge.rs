@@ -0,0 +1,234 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    azure::Azure,
+    git::{get_token, Remote},
+    gitea::Gitea,
+    github::GitHub,
+    settings::{ForgeKind, Settings},
+};
+
+/// a pull request as marge sees it, whichever forge it's on
+#[derive(Clone, Debug)]
+pub struct Pull {
+    pub number: u64,
+    pub title: Option<String>,
+    pub body: Option<String>,
+    /// the login of whoever opened it
+    pub author: Option<String>,
+    pub labels: Vec<String>,
+    pub html_url: Option<Url>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub head: Ref,
+    pub base: Ref,
+    /// only there when the pull was fetched by itself, listing leaves them out
+    pub commits: Option<u64>,
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+    /// whether it landed on its base
+    pub merged: bool,
+    /// whether it merges cleanly, none while the forge is still working that out
+    pub mergeable: Option<bool>,
+}
+
+/// the branch on either end of a pull
+#[derive(Clone, Debug)]
+pub struct Ref {
+    pub branch: String,
+    pub sha: String,
+}
+
+/// what the checks on a commit add up to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checks {
+    Failing,
+    Pending,
+    Passing,
+    Missing,
+}
+
+impl Checks {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Checks::Failing => "checks failing",
+            Checks::Pending => "checks pending",
+            Checks::Passing => "checks passing",
+            Checks::Missing => "no checks",
+        }
+    }
+}
+
+/// how the forge puts a pull onto its base
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMethod {
+    #[default]
+    Rebase,
+    Squash,
+    Merge,
+}
+
+impl MergeMethod {
+    pub fn next(self) -> MergeMethod {
+        match self {
+            MergeMethod::Rebase => MergeMethod::Squash,
+            MergeMethod::Squash => MergeMethod::Merge,
+            MergeMethod::Merge => MergeMethod::Rebase,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MergeMethod::Rebase => "rebase",
+            MergeMethod::Squash => "squash",
+            MergeMethod::Merge => "merge commit",
+        }
+    }
+}
+
+/** whether the forge turned a merge away because the pull isn't ready yet, like when its base
+moved right before, rather than because it can't be merged at all */
+pub fn merge_blocked(error: &anyhow::Error) -> bool {
+    let message = format!("{error:#}").to_lowercase();
+    [
+        "base branch was modified",
+        "head branch was modified",
+        "not mergeable",
+        "try again",
+    ]
+    .iter()
+    .any(|m| message.contains(m))
+}
+
+/// the service hosting the repo and its pulls. everything marge changes on the remote
+/// besides pushing goes through here
+pub trait Forge: Send + Sync {
+    /** the open pulls of the repo */
+    fn pulls<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<Vec<Pull>>>;
+
+    /** the branch the repo's pulls go into unless they say otherwise */
+    fn default_branch<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>>;
+
+    /** one pull, with its commit and line counts */
+    fn pull<'a>(&'a self, remote: &'a Remote, number: u64) -> BoxFuture<'a, anyhow::Result<Pull>>;
+
+    /** make sure the token can push to the repo and retarget and merge its pulls, instead of failing halfway through a run */
+    fn check_access<'a>(
+        &'a self,
+        remote: &'a Remote,
+        read_only: bool,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /** the account the token belongs to, as the forge names it */
+    fn login<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>>;
+
+    /** point the pull at another base branch */
+    fn retarget<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /** open a pull from head into base */
+    fn open_pull<'a>(
+        &'a self,
+        remote: &'a Remote,
+        title: String,
+        body: String,
+        head: &'a str,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Pull>>;
+
+    /** merge the pull, returning the commit it ended up as if the forge says */
+    fn merge<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        method: MergeMethod,
+    ) -> BoxFuture<'a, anyhow::Result<Option<String>>>;
+
+    /** bring the pull's head up to date with its base on the forge's side */
+    fn update_branch<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /** run the workflow on branch through a workflow_dispatch event */
+    fn dispatch_workflow<'a>(
+        &'a self,
+        remote: &'a Remote,
+        workflow: &'a str,
+        branch: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /** how the latest dispatched run of the workflow on a commit went, missing if it didn't
+    start yet */
+    fn workflow_run<'a>(
+        &'a self,
+        remote: &'a Remote,
+        workflow: &'a str,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>>;
+
+    /** the worst of the checks on a commit */
+    fn checks<'a>(
+        &'a self,
+        remote: &'a Remote,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>>;
+
+    /** how many commits of base aren't in head */
+    fn behind_by<'a>(
+        &'a self,
+        remote: &'a Remote,
+        base: &'a str,
+        head: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<u64>>;
+}
+
+/** the client for the forge the remote is on: github for github.com, azure devops for dev.azure.com, whatever the `[hosts]` table of the config says for other hosts */
+pub async fn connect(
+    remote: &Remote,
+    token_file: &str,
+    settings: &Settings,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Arc<dyn Forge>> {
+    let host = settings.hosts.get(&remote.host);
+    let token_file = host
+        .and_then(|h| h.token_file.as_deref())
+        .unwrap_or(token_file);
+    let kind = match host {
+        Some(host) => host.forge,
+        None if remote.host == "github.com" => ForgeKind::GitHub,
+        None if remote.host == "dev.azure.com" => ForgeKind::Azure,
+        None => {
+            return Err(anyhow!(
+                "don't know which forge {} is, add it to the [hosts] table of the config",
+                remote.host
+            ))
+        }
+    };
+    let token = get_token(token_file)
+        .await
+        .context(format!("could not read the token for {}", remote.host))?;
+    Ok(match kind {
+        ForgeKind::GitHub => Arc::new(GitHub::new(token, timeout, &settings.network)?),
+        ForgeKind::Gitea | ForgeKind::Forgejo => {
+            let api = match host.and_then(|h| h.api.clone()) {
+                Some(api) => api,
+                None => format!("https://{}/api/v1/", remote.host).parse()?,
+            };
+            Arc::new(Gitea::new(api, token, timeout, &settings.network)?)
+        }
+        ForgeKind::Azure => Arc::new(Azure::new(token, timeout, &settings.network)?),
+    })
+}
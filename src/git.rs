@@ -1,20 +1,48 @@
 use anyhow::{anyhow, Context};
-use clap::Parser;
-use crossterm::event::{KeyCode, KeyEvent};
-use futures::FutureExt;
-use log::info;
-use octocrab::{models::pulls::PullRequest, params, Octocrab, Page};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use log::{debug, error, info, warn};
 use regex::Regex;
-use std::{collections::HashSet, hash::Hash, hash::Hasher};
-use tokio::sync::mpsc::Receiver;
+use std::process::{Output, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{Receiver, Sender};
 use tui_logger::TuiWidgetState;
 
-use crate::{events::AppEvent, merge_candidate::MergeCandidate, AppArgs, AppConfig};
+use crate::{
+    audit, blame, changelog,
+    clipboard::Clipboard,
+    command_log::{self, Logged},
+    events::{AppEvent, Foreground},
+    forge::{self, Checks, Forge, Pull},
+    hooks::{self, Hook},
+    input::{Edit, Input},
+    local::Local,
+    merge_candidate::{CandidateOption, MergeCandidate, PullStats, Status},
+    network,
+    notify::Notifier,
+    observer::{self, DebugLog, Observed},
+    plan::Plan,
+    remote,
+    session::Session,
+    settings::{Merging, Settings},
+    task, theme, validation_log,
+    viewer::{Highlight, Viewer},
+    worktree, AppArgs, AppConfig, RunArgs,
+};
 use tokio::process::Command;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Remote {
     pub name: String,
+    /// github.com or wherever else the repo lives, which decides the forge
+    pub host: String,
     pub owner: String,
     pub repo: String,
 }
@@ -37,140 +65,455 @@ impl Hash for Remote {
 }
 
 /** get the remotes of the git repository in the current wd */
-async fn get_remotes() -> anyhow::Result<Vec<Remote>> {
-    let remote_re = Regex::new(
-        r"(?xm)           # verbose syntax / multiline
-        ^([[:alpha:]]*)                          # remote name at line start
-        \s*                                      # eat whitespace
-        (?:git@github\.com:|https://github.com/) # eat start of URL
-        ([[:alpha:]-_\d]*)                       # remote owner
-        /                                        # eat /
-        ([[:alpha:]-_\d]*)                       # remote repo
-        \.git                                    # eat .git
-        \s*                                      # eat whitespace
-        \((?:fetch|push)\)$                      # eat (fetch) or (push) at line end
-    ",
-    )
-    .unwrap();
+pub async fn get_remotes() -> anyhow::Result<Vec<Remote>> {
     let output = Command::new("git")
         .args(["remote", "-v"])
-        .output()
+        .logged_output()
         .await
         .context("could not run git remote")?;
 
     // check if we got 128 -> no git remote
     let out = String::from_utf8(output.stdout).context("output not valid utf-8")?;
-    let mut set: HashSet<Remote> = HashSet::new();
-    let remotes = remote_re.captures_iter(&out).map(|caps| {
-        let (_, [name, owner, repo]) = caps.extract();
-        Remote {
-            name: name.to_owned(),
-            owner: owner.to_owned(),
-            repo: repo.to_owned(),
-        }
-    });
-    set.extend(remotes);
+    Ok(remote::parse_remotes(&out)?)
+}
 
-    if set.is_empty() {
-        Err(anyhow!("not enough remotes!"))
-    } else {
-        Ok(set.into_iter().collect())
+/** run a command like `Command::output`, but log its stdout and stderr line by line as they come in */
+async fn output_streaming(command: &mut Command) -> std::io::Result<Output> {
+    let started = Instant::now();
+    let output = stream_output(command).await;
+    let ran = output.as_ref().ok();
+    command_log::record(command, started, ran.map(|o| o.status), ran);
+    output
+}
+
+async fn stream_output(command: &mut Command) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (stdout, stderr, status) = tokio::try_join!(
+        log_lines(stdout, "stdout"),
+        log_lines(stderr, "stderr"),
+        child.wait()
+    )?;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/** log every line read from reader and return everything that was read */
+async fn log_lines(reader: impl AsyncRead + Unpin, label: &str) -> std::io::Result<Vec<u8>> {
+    let mut lines = BufReader::new(reader).split(b'\n');
+    let mut all = vec![];
+    while let Some(line) = lines.next_segment().await? {
+        // progress output redraws its line with carriage returns, only keep the last state
+        let line = String::from_utf8_lossy(&line);
+        let line = line
+            .rsplit('\r')
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("");
+        debug!("{label}: {line}");
+        all.extend_from_slice(line.as_bytes());
+        all.push(b'\n');
     }
+    Ok(all)
+}
+
+/** how long a command talking to the remote may take before marge gives up on it, from the
+seconds in the settings. 0 turns the timeout off */
+fn remote_timeout(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
 }
 
-async fn get_pulls(remote: &Remote, instance: &Octocrab) -> anyhow::Result<Vec<PullRequest>> {
-    let owner = &remote.owner;
-    let repo = &remote.repo;
-    instance
-        .pulls(owner, repo)
-        .list()
-        .state(params::State::Open)
-        .per_page(100)
-        .page(1u8)
-        .send()
+/** run a command that talks to the remote like `output_streaming`, killing it when it takes
+longer than the timeout */
+async fn output_remote(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> std::io::Result<Output> {
+    let Some(timeout) = timeout else {
+        return output_streaming(command).await;
+    };
+    command.kill_on_drop(true);
+    tokio::time::timeout(timeout, output_streaming(command))
         .await
-        .context(format!("could not get pulls for repo {owner}/{repo}"))
-        .map(|p: Page<PullRequest>| p.items)
+        .unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("gave up after {}s", timeout.as_secs()),
+            ))
+        })
+}
+
+/** compile the stack regex, which needs both groups to be of any use */
+fn stack_title(pattern: &str) -> anyhow::Result<Regex> {
+    let re = Regex::new(pattern).context(format!("invalid stack regex {pattern}"))?;
+    let names = re.capture_names().flatten().collect::<Vec<&str>>();
+    if !names.contains(&"stack") || !names.contains(&"index") {
+        return Err(anyhow!(
+            "the stack regex {pattern} needs a stack and an index group, like (?<stack>...)"
+        ));
+    }
+    Ok(re)
+}
+
+/** take the pulls whose titles name a stack out of the candidates, in the order the titles
+give. the stacks follow each other by their lowest pull number */
+fn title_stacks(re: &Regex, candidates: &mut Vec<MergeCandidate>) -> Vec<MergeCandidate> {
+    let mut stacks: Vec<(String, Vec<(u64, MergeCandidate)>)> = vec![];
+    let mut rest = vec![];
+    for c in candidates.drain(..) {
+        let found = c.pull.title.as_deref().and_then(|title| {
+            let caps = re.captures(title)?;
+            // either group may be optional in the pattern and not take part in the match
+            let stack = caps.name("stack")?.as_str().to_owned();
+            Some((stack, caps.name("index")?.as_str().parse::<u64>().ok()?))
+        });
+        let Some((stack, index)) = found else {
+            rest.push(c);
+            continue;
+        };
+        match stacks.iter_mut().find(|(name, _)| *name == stack) {
+            Some((_, pulls)) => pulls.push((index, c)),
+            None => stacks.push((stack, vec![(index, c)])),
+        }
+    }
+    *candidates = rest;
+    stacks.sort_by_key(|(_, pulls)| pulls.iter().map(|(_, c)| c.pull.number).min());
+    let mut chain = vec![];
+    for (stack, mut pulls) in stacks {
+        pulls.sort_by_key(|(index, _)| *index);
+        info!(
+            "chained the stack {stack} from the pull titles: {} pulls",
+            pulls.len()
+        );
+        chain.extend(pulls.into_iter().map(|(_, c)| c));
+    }
+    chain
+}
+
+/// branch patterns marge must never delete, reset, rebase or force-push. they're compiled once
+/// at startup and kept for the whole run, so the options carrying them stay Copy
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Protected(&'static [(String, Regex)]);
+
+impl Protected {
+    /** compile the protected branch patterns, where * matches anything */
+    fn new(patterns: &[String]) -> anyhow::Result<Protected> {
+        let compiled = patterns
+            .iter()
+            .map(|p| {
+                let re = Regex::new(&format!("^{}$", regex::escape(p).replace(r"\*", ".*")))
+                    .context(format!("invalid protected branch pattern {p}"))?;
+                Ok((p.clone(), re))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Protected(compiled.leak()))
+    }
+
+    /** the protected pattern matching branch, if any */
+    fn pattern(self, branch: &str) -> Option<&'static str> {
+        self.0
+            .iter()
+            .find(|(_, re)| re.is_match(branch))
+            .map(|(p, _)| p.as_str())
+    }
+
+    /** refuse to go on if doing `action` to branch would touch a protected branch */
+    fn guard(self, branch: &str, action: &str) -> anyhow::Result<()> {
+        match self.pattern(branch) {
+            Some(pattern) => Err(anyhow!(
+                "refusing to {action} {branch}, it matches the protected pattern {pattern}"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /** like guard, for whatever branch is checked out right now */
+    async fn guard_head(self, action: &str) -> anyhow::Result<()> {
+        let output = Command::new("git")
+            .args(["symbolic-ref", "--short", "HEAD"])
+            .logged_output()
+            .await?;
+        self.guard(std::str::from_utf8(&output.stdout)?.trim(), action)
+    }
+}
+
+async fn head_sha() -> anyhow::Result<String> {
+    rev_parse("HEAD").await
+}
+
+async fn rev_parse(rev: &str) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", rev])
+        .logged_output()
+        .await?;
+    Ok(std::str::from_utf8(&output.stdout)?.trim().to_owned())
+}
+
+/** check out the target branch. it's pulled right afterwards, so a protected one is checked out
+as it is instead of being recreated */
+fn checkout_target(branchname: &str, options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let recreate = match options.protected.pattern(branchname) {
+        Some(_) => {
+            info!("{branchname} is protected, checking it out without recreating it");
+            false
+        }
+        None => true,
+    };
+    checkout(branchname, recreate, options)
+}
+
+/** recreate a candidate branch from the remote and check it out, refusing to if it's protected */
+fn checkout_branch(branchname: &str, options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    if let Err(e) = options
+        .protected
+        .guard(branchname, "delete and check out again")
+    {
+        let (tx, rx) = task::channel("checkout");
+        tokio::spawn(async move { tx.send(Err(e)).await });
+        return rx;
+    }
+    checkout(branchname, true, options)
 }
 
-fn checkout_branch(branchname: &str) -> Receiver<anyhow::Result<()>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
+fn checkout(branchname: &str, recreate: bool, options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("checkout");
     log::info!("running git checkout");
     let b = branchname.to_owned();
     tokio::spawn(async move {
-        let o = Command::new("git")
-            .args(["branch", "-D", &b])
-            .output()
-            .await;
-        info!("{:?}", o);
-        let result = Command::new("git").args(["checkout", &b]).output().await;
+        if options.local {
+            // local branches may be the only copy there is
+            debug!("checking out {b} as it is");
+        } else if recreate {
+            let o = Command::new("git")
+                .args(["branch", "-D", &b])
+                .logged_output()
+                .await;
+            debug!("{:?}", o);
+        }
+        let result = output_streaming(checkout_command(options).args(["checkout", &b]))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|o| check_status(&format!("git checkout {b}"), o));
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let Ok(output) = result else {
-            let _ = tx.send(Err(anyhow!("could not checkout branch"))).await;
-            return;
-        };
-
-        info!(
-            "stdout: {}",
-            std::str::from_utf8(&output.stdout).unwrap_or("<invalid utf8 output>")
-        );
-        let _ = tx.send(Ok(())).await;
+        tx.send(result.map(|_| ()).context("could not checkout branch"))
+            .await;
     });
 
     rx
 }
 
+/// how marge lets commit signing prompt for a passphrase
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SigningMode {
+    /// hand over the terminal if commit.gpgsign is set
+    Auto,
+    /// always hand over the terminal to commands that create commits
+    Terminal,
+    /// never hand over the terminal, the signing agent doesn't need to prompt
+    Agent,
+}
+
+/** check whether git is configured to sign commits */
+async fn commit_signing_enabled() -> bool {
+    git_config_bool("commit.gpgsign").await
+}
+
+/// runs git commands that create commits. with signing, they get the terminal while
+/// the TUI is suspended so pinentry & co. can prompt for the passphrase
+#[derive(Clone, Debug)]
+pub struct Signer(Option<Sender<AppEvent>>);
+
+impl Signer {
+    async fn try_new(mode: SigningMode, events: Sender<AppEvent>) -> Signer {
+        let foreground = match mode {
+            SigningMode::Auto => commit_signing_enabled().await,
+            SigningMode::Terminal => true,
+            SigningMode::Agent => false,
+        };
+        if foreground {
+            info!("commit signing: git will get the terminal when creating commits");
+            Signer(Some(events))
+        } else {
+            Signer(None)
+        }
+    }
+
+    /** run a command creating commits and return whether it succeeded */
+    async fn run(&self, command: &mut Command) -> anyhow::Result<bool> {
+        let Some(events) = &self.0 else {
+            let output = output_streaming(command).await?;
+            return Ok(output.status.success());
+        };
+
+        let (granted, granted_rx) = tokio::sync::oneshot::channel();
+        let (done_tx, done) = tokio::sync::oneshot::channel();
+        events
+            .send(AppEvent::Foreground(Foreground { granted, done }))
+            .await?;
+        granted_rx.await?;
+        let status = command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .logged_status()
+            .await;
+        let _ = done_tx.send(());
+        Ok(status?.success())
+    }
+}
+
+/// tweaks to the git commands marge runs on each candidate
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GitOptions {
+    /// fold fixup!/squash! commits in non-interactively while rebasing
+    pub autosquash: bool,
+    /// skip the repository's commit and push hooks
+    pub no_verify: bool,
+    /// bring the submodules up to date before validating
+    pub submodules: bool,
+    /// don't download LFS objects on checkout, only pull them before validating
+    pub no_lfs: bool,
+    /// ask before retargeting, force-pushing and merging
+    pub confirm: bool,
+    /// let git record conflict resolutions and replay them in later rebases
+    pub rerere: bool,
+    /// merge each candidate once its checks pass, before rebasing the next one onto the target
+    pub rolling: bool,
+    /// validate the rebased candidates in worktrees while the next ones are rebased
+    pub parallel: bool,
+    /// how often a failed validation is run again before waiting for a fix
+    pub validate_retries: u32,
+    /// show the range-diff of each validated candidate and wait for a go-ahead before pushing it
+    pub review_rebase: bool,
+    /// turn everything marge would change on the remote into a log line
+    pub read_only: bool,
+    /// how long a command talking to the remote may take, none means waiting forever
+    pub remote_timeout: Option<Duration>,
+    /// work on local branches with --local, pushing them to the remote but never fetching it
+    pub local: bool,
+    /// how merges are retried and spaced out, from the `[merging]` settings
+    pub merging: Merging,
+    /// the pushed candidates are validated by the --workflow run on the forge instead of the command
+    pub workflow: bool,
+    /// the branches marge refuses to touch, from the `[branches]` settings
+    pub protected: Protected,
+}
+
+impl GitOptions {
+    /** read the defaults for this repo from git config, cli flags are added on top */
+    async fn from_git_config(args: &RunArgs, settings: &Settings) -> anyhow::Result<GitOptions> {
+        Ok(GitOptions {
+            autosquash: args.autosquash,
+            no_verify: args.no_verify || git_config_bool("marge.noVerify").await,
+            submodules: args.submodules || git_config_bool("marge.submodules").await,
+            no_lfs: args.no_lfs,
+            confirm: args.confirm,
+            rerere: args.rerere || git_config_bool("marge.rerere").await,
+            rolling: args.rolling,
+            parallel: args.parallel,
+            validate_retries: args.validate_retries,
+            review_rebase: args.review_rebase,
+            read_only: args.read_only,
+            remote_timeout: remote_timeout(settings.timeouts.remote),
+            local: !args.local.is_empty(),
+            merging: settings.merging,
+            workflow: args.workflow.is_some(),
+            protected: Protected::new(&settings.branches.protected)?,
+        })
+    }
+}
+
+/** get a boolean from the git config of the repo in the current wd */
+async fn git_config_bool(key: &str) -> bool {
+    Command::new("git")
+        .args(["config", "--bool", "--get", key])
+        .logged_output()
+        .await
+        .map(|o| o.stdout.trim_ascii() == b"true")
+        .unwrap_or(false)
+}
+
+/** git for a command that writes files into the checkout, leaving the LFS objects out of them
+with --no-lfs */
+fn checkout_command(options: GitOptions) -> Command {
+    let mut command = Command::new("git");
+    if options.no_lfs {
+        command.env("GIT_LFS_SKIP_SMUDGE", "1");
+    }
+    command
+}
+
+/** a git rebase command, with rerere turned on if asked for */
+fn rebase_command(options: GitOptions) -> Command {
+    let mut command = checkout_command(options);
+    if options.rerere {
+        // autoupdate stages what rerere resolved, so continuing the rebase picks it up
+        command.args(["-c", "rerere.enabled=true", "-c", "rerere.autoUpdate=true"]);
+    }
+    command.arg("rebase");
+    command
+}
+
 /** return true if done */
-fn rebase_branch(onto: &str) -> Receiver<anyhow::Result<bool>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
+fn rebase_branch(
+    onto: &str,
+    options: GitOptions,
+    signer: &Signer,
+    env: hooks::Env,
+) -> Receiver<anyhow::Result<bool>> {
+    let (tx, rx) = task::channel("rebase");
     info!("running git rebase onto {onto}");
     let b = onto.to_owned();
+    let signer = signer.clone();
     tokio::spawn(async move {
-        let result = Command::new("git").args(["rebase", &b]).output().await;
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let _ = match result {
-            Ok(output) => {
-                let o = std::str::from_utf8(&output.stdout).unwrap_or("<invalid utf8 output>");
-                info!("stdout: {o}",);
-                tx.send(Ok(output.status.success()))
-            }
-            Err(e) => tx.send(Err(e).context("could not rebase current branch")),
+        if let Err(e) = hooks::run(Hook::PreRebase, &env).await {
+            tx.send(Err(e)).await;
+            return;
         }
-        .await;
+        let mut command = rebase_command(options);
+        if options.no_verify {
+            command.arg("--no-verify");
+        }
+        if options.autosquash {
+            command
+                .args(["-i", "--autosquash"])
+                .env("GIT_SEQUENCE_EDITOR", "true");
+        }
+        command.arg(&b);
+        let result = match options.protected.guard_head("rebase").await {
+            Ok(()) => signer.run(&mut command).await,
+            Err(e) => Err(e),
+        };
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        tx.send(result.context("could not rebase current branch"))
+            .await;
     });
 
     rx
 }
 
-fn has_no_conflicts() -> Receiver<anyhow::Result<bool>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
+fn has_no_conflicts(options: GitOptions, signer: &Signer) -> Receiver<anyhow::Result<bool>> {
+    let (tx, rx) = task::channel("conflict check");
     info!("running git rebase --continue");
+    let signer = signer.clone();
     tokio::spawn(async move {
-        let result = Command::new("git")
-            .args(["rebase", "--continue"])
-            .env("GIT_EDITOR", "true")
-            .output()
+        let result = signer
+            .run(
+                rebase_command(options)
+                    .arg("--continue")
+                    .env("GIT_EDITOR", "true"),
+            )
             .await;
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let _ = match result {
-            Ok(output) => {
-                info!(
-                    "stdout: {}",
-                    std::str::from_utf8(&output.stdout).unwrap_or("<invalid utf8 output>")
-                );
-                info!(
-                    "stderr: {}",
-                    std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8 stderr>")
-                );
-                if let Some(0) = output.status.code() {
-                    tx.send(Ok(true))
-                } else {
-                    tx.send(Ok(false))
-                }
-            }
-            Err(e) => tx.send(Err(e).context("could not rebase current branch")),
-        }
-        .await;
+        tx.send(result.context("could not rebase current branch"))
+            .await;
     });
 
     rx
@@ -178,37 +521,59 @@ fn has_no_conflicts() -> Receiver<anyhow::Result<bool>> {
 
 async fn retarget_candidate(
     remote: &Remote,
-    instance: &Octocrab,
+    forge: &dyn Forge,
     merge_candidate: &MergeCandidate,
     onto: &str,
+    read_only: bool,
 ) -> anyhow::Result<()> {
-    let Remote { owner, repo, .. } = remote;
+    if read_only {
+        info!(
+            "read-only: would retarget #{} onto {onto}",
+            merge_candidate.pull.number
+        );
+        return Ok(());
+    }
 
-    instance
-        .pulls(owner, repo)
-        .update(merge_candidate.pull.number)
-        .base(onto)
-        .send()
+    forge
+        .retarget(remote, merge_candidate.pull.number, onto)
         .await?;
+    audit::record(&format!(
+        "retargeted #{} from {} onto {onto}",
+        merge_candidate.pull.number, merge_candidate.pull.base.branch
+    ));
 
     Ok(())
 }
 
-fn pull_remote() -> Receiver<anyhow::Result<()>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
-    log::info!("running git pull");
+/** list the branch names on the given remote */
+fn list_remote_branches(
+    remote: &str,
+    options: GitOptions,
+) -> Receiver<anyhow::Result<Vec<String>>> {
+    let (tx, rx) = task::channel("branch listing");
+    log::info!("running git ls-remote --heads {remote}");
+    let r = remote.to_owned();
     tokio::spawn(async move {
-        let result = Command::new("git").args(["pull"]).output().await;
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let _ = match result {
-            Ok(output) => {
-                info!(
-                    "stdout: {}",
-                    std::str::from_utf8(&output.stdout).unwrap_or("<invalid utf8 output>")
-                );
-                tx.send(Ok(()))
+        let result = output_remote(
+            Command::new("git").args(["ls-remote", "--heads", &r]),
+            options.remote_timeout,
+        )
+        .await;
+        match result {
+            Ok(output) if output.status.success() => {
+                let out = std::str::from_utf8(&output.stdout).unwrap_or_default();
+                let branches = out
+                    .lines()
+                    .filter_map(|l| l.split_once("refs/heads/"))
+                    .map(|(_, name)| name.trim().to_owned())
+                    .collect();
+                tx.send(Ok(branches))
             }
-            Err(e) => tx.send(Err(e).context("could not check repo")),
+            Ok(output) => tx.send(Err(anyhow!(
+                "git ls-remote failed: {}",
+                std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8 stderr>")
+            ))),
+            Err(e) => tx.send(Err(e).context("could not list remote branches")),
         }
         .await;
     });
@@ -216,24 +581,23 @@ fn pull_remote() -> Receiver<anyhow::Result<()>> {
     rx
 }
 
-fn push_candidate() -> Receiver<anyhow::Result<()>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
-    log::info!("running git push --force-with-lease");
+fn update_submodules(options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("submodule update");
+    info!("running git submodule update --init --recursive");
     tokio::spawn(async move {
-        let result = Command::new("git")
-            .args(["push", "--force-with-lease"])
-            .output()
-            .await;
+        let result = output_remote(
+            Command::new("git").args(["submodule", "update", "--init", "--recursive"]),
+            options.remote_timeout,
+        )
+        .await;
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let _ = match result {
-            Ok(output) => {
-                info!(
-                    "stdout: {}",
-                    std::str::from_utf8(&output.stdout).unwrap_or("<invalid utf8 output>")
-                );
-                tx.send(Ok(()))
-            }
-            Err(e) => tx.send(Err(e).context("could not force push")),
+        match result {
+            Ok(output) => tx.send(
+                check_status("git submodule update --init --recursive", output)
+                    .map(|_| ())
+                    .context("could not update submodules"),
+            ),
+            Err(e) => tx.send(Err(e).context("could not update submodules")),
         }
         .await;
     });
@@ -241,55 +605,66 @@ fn push_candidate() -> Receiver<anyhow::Result<()>> {
     rx
 }
 
-fn validate(cmd: &str) -> Receiver<anyhow::Result<bool>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
-    let cmd = cmd.to_owned();
-    log::info!("validating: {}", cmd);
+/** abort any rebase or cherry-pick a failed step left behind */
+fn abort_in_progress(options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("abort");
+    info!("aborting rebases and cherry-picks in progress");
     tokio::spawn(async move {
-        let result = Command::new("sh").args(["-c", &cmd]).output().await;
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let _ = match result {
-            Ok(output) => {
-                info!(
-                    "stdout: {}",
-                    std::str::from_utf8(&output.stdout).unwrap_or("<invalid utf8 output>")
-                );
-                info!(
-                    "stderr: {}",
-                    std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8 stderr>")
-                );
-                if let Some(0) = output.status.code() {
-                    tx.send(Ok(true))
-                } else {
-                    tx.send(Ok(false))
-                }
-            }
-            Err(e) => tx.send(Err(e).context("could not validate current branch")),
+        let result = async {
+            // these fail harmlessly if there's nothing to abort
+            checkout_command(options)
+                .args(["rebase", "--abort"])
+                .logged_output()
+                .await?;
+            checkout_command(options)
+                .args(["cherry-pick", "--abort"])
+                .logged_output()
+                .await?;
+            Ok::<_, anyhow::Error>(())
         }
         .await;
+        tx.send(result.context("could not abort")).await;
     });
 
     rx
 }
 
-fn is_repo_clean() -> Receiver<anyhow::Result<bool>> {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
-    log::info!("running git status");
+fn pull_lfs(options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("lfs pull");
+    info!("running git lfs pull");
+    tokio::spawn(async move {
+        let result = output_remote(
+            Command::new("git").args(["lfs", "pull"]),
+            options.remote_timeout,
+        )
+        .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        match result {
+            Ok(output) => tx.send(
+                check_status("git lfs pull", output)
+                    .map(|_| ())
+                    .context("could not pull lfs objects"),
+            ),
+            Err(e) => tx.send(Err(e).context("could not pull lfs objects")),
+        }
+        .await;
+    });
+
+    rx
+}
 
+fn pull_remote(options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("pull");
+    log::info!("running git pull");
     tokio::spawn(async move {
-        let result = Command::new("git")
-            .args(["status", "--porcelain"])
-            .output()
-            .await;
+        let result = output_remote(
+            checkout_command(options).args(["pull"]),
+            options.remote_timeout,
+        )
+        .await;
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-        let _ = match result {
-            Ok(output) => {
-                if output.stdout.is_empty() {
-                    tx.send(Ok(true))
-                } else {
-                    tx.send(Ok(false))
-                }
-            }
+        match result {
+            Ok(output) => tx.send(check_status("git pull", output).map(|_| ())),
             Err(e) => tx.send(Err(e).context("could not check repo")),
         }
         .await;
@@ -298,635 +673,5453 @@ fn is_repo_clean() -> Receiver<anyhow::Result<bool>> {
     rx
 }
 
-#[derive(PartialEq)]
-pub enum ActivePane {
-    List,
-    Log,
+/** fetch both ends of the pull and show what it changes since it forked off its base (d) or its commits (c) */
+fn show_pull(
+    remote: &str,
+    pull: &Pull,
+    key: char,
+    options: GitOptions,
+) -> Receiver<anyhow::Result<String>> {
+    let (tx, rx) = task::channel("show pull");
+    let (remote, base, head) = (
+        remote.to_owned(),
+        pull.base.branch.clone(),
+        pull.head.branch.clone(),
+    );
+    tokio::spawn(async move {
+        let result = async {
+            // local branches are shown as they are
+            let (base, head) = if options.local {
+                (base, head)
+            } else {
+                let fetch = output_remote(
+                    Command::new("git").args(["fetch", &remote, &base, &head]),
+                    options.remote_timeout,
+                )
+                .await?;
+                check_status(&format!("git fetch {remote} {base} {head}"), fetch)?;
+                (format!("{remote}/{base}"), format!("{remote}/{head}"))
+            };
+            let args = match key {
+                'c' => vec![
+                    "log".to_owned(),
+                    "--reverse".to_owned(),
+                    "--format=%h %an: %s".to_owned(),
+                    format!("{base}..{head}"),
+                ],
+                _ => vec![
+                    "diff".to_owned(),
+                    "--no-color".to_owned(),
+                    format!("{base}...{head}"),
+                ],
+            };
+            let output = Command::new("git").args(&args).logged_output().await?;
+            let output = check_status(&format!("git {}", args.join(" ")), output)?;
+            Ok::<_, anyhow::Error>(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        .await;
+        tx.send(result.context("could not look at the pull")).await;
+    });
+
+    rx
 }
 
-#[derive(Debug)]
-pub struct SortingState {
-    pub unsorted: Vec<MergeCandidate>,
-    pub current_index: usize,
-    pub merge_chain: Vec<MergeCandidate>,
+/** the files that still have conflicts */
+async fn conflicted_files() -> anyhow::Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=U"])
+        .logged_output()
+        .await?;
+    let output = check_status("git diff --name-only --diff-filter=U", output)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_owned)
+        .collect())
 }
 
-#[derive(Debug)]
-pub struct WorkingState {
-    pub current_checkout: MergeCandidate,
-    pub next: Vec<MergeCandidate>,
-    pub done: Vec<MergeCandidate>,
+/** resolve the conflicts in path by taking one side (ours or theirs) as a whole, if given. then list the files that are still conflicted */
+fn resolve_file(side: Option<(&'static str, String)>) -> Receiver<anyhow::Result<Vec<Conflict>>> {
+    let (tx, rx) = task::channel("conflict listing");
+    tokio::spawn(async move {
+        let result = async {
+            if let Some((side, path)) = side {
+                info!("taking {side} for {path}");
+                let side = format!("--{side}");
+                let checkout = Command::new("git")
+                    .args(["checkout", &side, "--", &path])
+                    .logged_output()
+                    .await?;
+                check_status(&format!("git checkout {side} -- {path}"), checkout)?;
+                let add = Command::new("git")
+                    .args(["add", "--", &path])
+                    .logged_output()
+                    .await?;
+                check_status(&format!("git add -- {path}"), add)?;
+            }
+            let mut conflicts = vec![];
+            for path in conflicted_files().await? {
+                let owners = blame::owners(&path).await;
+                conflicts.push(Conflict { path, owners });
+            }
+            Ok::<_, anyhow::Error>(conflicts)
+        }
+        .await;
+        tx.send(result.context("could not resolve the conflict"))
+            .await;
+    });
+
+    rx
 }
 
-#[derive(Debug)]
-pub struct MergingState {
-    pub to_merge: Vec<MergeCandidate>,
+/** the conflicted files one after the other, each under a `==> path <==` line */
+fn show_conflicts() -> Receiver<anyhow::Result<String>> {
+    let (tx, rx) = task::channel("show conflicts");
+    tokio::spawn(async move {
+        let result = async {
+            let mut text = String::new();
+            for path in conflicted_files().await? {
+                // deleted on one side, there's nothing to show but the path
+                let content = tokio::fs::read(&path).await.unwrap_or_default();
+                text += &format!("==> {path} <==\n{}\n", String::from_utf8_lossy(&content));
+            }
+            Ok::<_, anyhow::Error>(text)
+        }
+        .await;
+        tx.send(result.context("could not get the conflicts")).await;
+    });
+
+    rx
 }
 
-#[derive(Debug)]
-pub enum AppState {
-    /// make sure that the current state of the repo is clean
-    CheckingRepo(Receiver<anyhow::Result<bool>>),
-    /// waiting for the user to tell us to check again...
-    WaitingForCleanRepo,
-    /// check out our target branch
-    CheckingOutTargetBranch(Receiver<anyhow::Result<()>>),
-    /// pull the latest state from the remote
-    PullingRemote(Receiver<anyhow::Result<()>>),
-    /// get the list of open pull requests
-    GettingPulls,
-    /// wait for the user to select the pulls to be merged
-    WaitingForSort(SortingState),
-    /// change the base of the current pull request to the previous one (or target)
-    UpdatingCandidate(WorkingState),
-    /// check out the branch belonging to the current pull request
-    CheckingOutCandidate(Receiver<anyhow::Result<()>>, WorkingState),
-    /// run rebase on the current branch
-    RebaseCandidate(Receiver<anyhow::Result<bool>>, WorkingState),
-    /// check if the rebase resulted in conflicts
-    CheckingForConflicts(Receiver<anyhow::Result<bool>>, WorkingState),
-    /// wait for the user to manually fix the results and then signal
-    WaitingForResolution(WorkingState),
-    /// check that the rebased branch passes the validation statement
-    Validating(Receiver<anyhow::Result<bool>>, WorkingState),
-    /// wait for the user to fix any errors and signal us
-    WaitingForFix(WorkingState),
-    /// force-push the branch to the remote
-    PushingCandidate(Receiver<anyhow::Result<()>>, WorkingState),
-    /// merge all the pulls that were rebased
-    Merging(MergingState),
-    Done,
-    Failed,
+/** create new_branch at start and cherry-pick the commits in range onto it. return true if done */
+fn cherry_pick_onto(
+    remote: &str,
+    new_branch: &str,
+    start: &str,
+    range: &str,
+    options: GitOptions,
+) -> Receiver<anyhow::Result<bool>> {
+    let (tx, rx) = task::channel("cherry-pick");
+    info!("cherry-picking {range} onto {new_branch}");
+    let (remote, new_branch, start, range) = (
+        remote.to_owned(),
+        new_branch.to_owned(),
+        start.to_owned(),
+        range.to_owned(),
+    );
+    tokio::spawn(async move {
+        let result = async {
+            options.protected.guard(&new_branch, "reset")?;
+            let fetch = output_remote(
+                Command::new("git").args(["fetch", &remote]),
+                options.remote_timeout,
+            )
+            .await?;
+            check_status(&format!("git fetch {remote}"), fetch)?;
+            let count = Command::new("git")
+                .args(["rev-list", "--count", &range])
+                .logged_output()
+                .await?;
+            let count = check_status(&format!("git rev-list --count {range}"), count)?;
+            if std::str::from_utf8(&count.stdout)?.trim() == "0" {
+                return Err(anyhow!("there are no commits in {range} to cherry-pick"));
+            }
+            let checkout = checkout_command(options)
+                .args(["checkout", "-B", &new_branch, &start])
+                .logged_output()
+                .await?;
+            check_status(&format!("git checkout -B {new_branch} {start}"), checkout)?;
+            let pick =
+                output_streaming(checkout_command(options).args(["cherry-pick", "-x", &range]))
+                    .await?;
+            Ok::<_, anyhow::Error>(pick.status.success())
+        }
+        .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        tx.send(result.context("could not cherry-pick")).await;
+    });
+
+    rx
 }
 
-/// the main app struct
-pub struct Marge {
-    pub app_state: Box<AppState>,
-    pub instance: Octocrab,
-    pub remote: Remote,
-    pub cmd: String,
-    pub branch: String,
-    pub active_pane: ActivePane,
-    pub last_event: AppEvent,
-    pub log_state: TuiWidgetState,
+/** write the default squash message for a candidate to the git dir and return its path */
+fn prepare_squash_message(candidate: &MergeCandidate) -> Receiver<anyhow::Result<PathBuf>> {
+    let (tx, rx) = task::channel("squash message");
+    let message = format!(
+        "{} (#{})\n\n{}\n",
+        candidate.pull.title.as_deref().unwrap_or("<untitled>"),
+        candidate.pull.number,
+        candidate.pull.body.as_deref().unwrap_or_default()
+    );
+    tokio::spawn(async move {
+        let result = async {
+            let output = Command::new("git")
+                .args(["rev-parse", "--git-path", "MARGE_SQUASH_MSG"])
+                .logged_output()
+                .await?;
+            let path = PathBuf::from(std::str::from_utf8(&output.stdout)?.trim());
+            tokio::fs::write(&path, message).await?;
+            Ok::<_, anyhow::Error>(path)
+        }
+        .await;
+        tx.send(result.context("could not write squash message"))
+            .await;
+    });
+
+    rx
 }
 
-impl Marge {
-    pub async fn try_transition(&mut self) -> anyhow::Result<()> {
-        let old_state = std::mem::replace(self.app_state.as_mut(), AppState::Failed);
+/** squash everything since base into one commit with the message from msg_file */
+fn squash_onto(
+    base: &str,
+    msg_file: &Path,
+    options: GitOptions,
+    signer: &Signer,
+) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("squash");
+    info!("squashing onto {base}");
+    let (b, f) = (base.to_owned(), msg_file.to_owned());
+    let signer = signer.clone();
+    tokio::spawn(async move {
+        let result = async {
+            options.protected.guard_head("squash").await?;
+            let reset = Command::new("git")
+                .args(["reset", "--soft", &b])
+                .logged_output()
+                .await?;
+            if !reset.status.success() {
+                return Err(anyhow!("git reset --soft {b} failed"));
+            }
+            let mut commit = Command::new("git");
+            commit.arg("commit").arg("-F").arg(&f);
+            if options.no_verify {
+                commit.arg("--no-verify");
+            }
+            let committed = signer.run(&mut commit).await?;
+            if committed {
+                Ok(())
+            } else {
+                Err(anyhow!("git commit failed"))
+            }
+        }
+        .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        tx.send(result.context("could not squash")).await;
+    });
 
-        let _ = std::mem::replace(
-            self.app_state.as_mut(),
+    rx
+}
+
+fn continue_cherry_pick(options: GitOptions) -> Receiver<anyhow::Result<bool>> {
+    let (tx, rx) = task::channel("cherry-pick continue");
+    info!("running git cherry-pick --continue");
+    tokio::spawn(async move {
+        let result = output_streaming(
+            checkout_command(options)
+                .args(["cherry-pick", "--continue"])
+                .env("GIT_EDITOR", "true"),
+        )
+        .await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        match result {
+            Ok(output) => tx.send(Ok(output.status.success())),
+            Err(e) => tx.send(Err(e).context("could not continue cherry-pick")),
+        }
+        .await;
+    });
+
+    rx
+}
+
+/** push a branch that does not exist on the remote yet */
+fn push_new_branch(
+    remote: &str,
+    branch: &str,
+    options: GitOptions,
+) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("push");
+    info!("running git push -u {remote} {branch}");
+    let (r, b) = (remote.to_owned(), branch.to_owned());
+    tokio::spawn(async move {
+        if let Err(e) = options.protected.guard(&b, "force-push") {
+            tx.send(Err(e)).await;
+            return;
+        }
+        let mut command = Command::new("git");
+        command.args(["push", "--porcelain", "--force-with-lease", "-u", &r, &b]);
+        if options.no_verify {
+            command.arg("--no-verify");
+        }
+        if options.read_only {
+            command.arg("--dry-run");
+        }
+        let result = output_remote(&mut command, options.remote_timeout).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        match result {
+            Ok(output) => tx.send(
+                check_status(&format!("git push -u {r} {b}"), output)
+                    .map(|output| audit_push(&output, options.read_only))
+                    .context(format!("could not push {b}")),
+            ),
+            Err(e) => tx.send(Err(e).context("could not push")),
+        }
+        .await;
+    });
+
+    rx
+}
+
+async fn open_backport_pull(
+    remote: &Remote,
+    forge: &dyn Forge,
+    source: &MergeCandidate,
+    head: &str,
+    target: &str,
+) -> anyhow::Result<Pull> {
+    let title = format!(
+        "[{target}] {}",
+        source.pull.title.as_deref().unwrap_or("<untitled>")
+    );
+    let body = format!("Backport of #{} onto `{target}`.", source.pull.number);
+
+    forge
+        .open_pull(remote, title, body, head, target)
+        .await
+        .context(format!("could not open backport pull for {head}"))
+}
+
+/** record the refs a `git push --porcelain` changed, with their old and new commits */
+fn audit_push(output: &Output, read_only: bool) {
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        // <flag>\t<from>:<to>\t<summary>, = means the ref was up to date already
+        let mut fields = line.split('\t');
+        if let (Some(flag), Some(refs), Some(summary)) =
+            (fields.next(), fields.next(), fields.next())
+        {
+            if flag != "=" {
+                let to = refs.rsplit(':').next().unwrap_or(refs);
+                if read_only {
+                    info!("read-only: would push {to}: {summary}");
+                } else {
+                    audit::record(&format!("pushed {to}: {summary}"));
+                }
+            }
+        }
+    }
+}
+
+/** force-push the current branch. sends the head that was pushed */
+fn push_candidate(
+    remote: &str,
+    options: GitOptions,
+    env: hooks::Env,
+) -> Receiver<anyhow::Result<String>> {
+    force_push(remote.to_owned(), None, options, env)
+}
+
+/** force-push head to remote without checking it out, for the candidates validated in worktrees.
+sends the head that was pushed */
+fn push_validated(
+    remote: &str,
+    head: &str,
+    options: GitOptions,
+    env: hooks::Env,
+) -> Receiver<anyhow::Result<String>> {
+    force_push(remote.to_owned(), Some(head.to_owned()), options, env)
+}
+
+/** force-push head to remote, or the current branch to where it tracks. with --local the branches
+may not track anything yet, so they're pushed to the remote and set to track it */
+fn force_push(
+    remote: String,
+    head: Option<String>,
+    options: GitOptions,
+    env: hooks::Env,
+) -> Receiver<anyhow::Result<String>> {
+    let (tx, rx) = task::channel("force-push");
+    log::info!("running git push --force-with-lease");
+    tokio::spawn(async move {
+        let guarded = match &head {
+            Some(head) => options.protected.guard(head, "force-push"),
+            None => options.protected.guard_head("force-push").await,
+        };
+        if let Err(e) = guarded {
+            tx.send(Err(e)).await;
+            return;
+        }
+        if options.read_only {
+            if let Some(cmd) = Hook::PrePush.command() {
+                info!("read-only: would run the pre_push hook: {cmd}");
+            }
+        } else if let Err(e) = hooks::run(Hook::PrePush, &env).await {
+            tx.send(Err(e)).await;
+            return;
+        }
+        let mut command = Command::new("git");
+        command.args(["push", "--porcelain", "--force-with-lease"]);
+        if let Some(head) = &head {
+            command.args([&remote, head]);
+        } else if options.local {
+            command.args(["-u", &remote, "HEAD"]);
+        }
+        if options.no_verify {
+            command.arg("--no-verify");
+        }
+        if options.read_only {
+            command.arg("--dry-run");
+        }
+        // hooks write to stderr as well, so their output shows up in the log
+        let result = output_remote(&mut command, options.remote_timeout).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        let result = match result {
+            Ok(output) => check_status("git push --force-with-lease", output)
+                .map(|output| audit_push(&output, options.read_only))
+                .context("push was rejected"),
+            Err(e) => Err(e).context("could not force push"),
+        };
+        let result = match (result, &head) {
+            (Ok(()), Some(head)) => rev_parse(head).await,
+            (Ok(()), None) => head_sha().await,
+            (Err(e), _) => Err(e),
+        };
+        tx.send(result).await;
+    });
+
+    rx
+}
+
+/** the environment the validation command sees, so it can tell the candidates apart */
+fn validation_env(branch: &str, s: &WorkingState) -> hooks::Env {
+    let mut env = hooks::env(&s.current_checkout, &s.base(branch));
+    let position = s.done.len() + 1;
+    env.push(("MARGE_CHAIN_POSITION", position.to_string()));
+    env.push(("MARGE_CHAIN_LENGTH", (position + s.next.len()).to_string()));
+    env.push(("MARGE_TARGET_REF", branch.to_owned()));
+    env
+}
+
+/** the paths the candidate checked out in dir changes since it left base, quoted for the shell */
+async fn changed_files(base: &str, dir: Option<&Path>) -> anyhow::Result<String> {
+    let mut command = Command::new("git");
+    command.args(["diff", "--name-only", &format!("{base}...HEAD")]);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = check_status("git diff --name-only", command.logged_output().await?)?;
+    Ok(std::str::from_utf8(&output.stdout)?
+        .lines()
+        .map(command_log::quote)
+        .collect::<Vec<String>>()
+        .join(" "))
+}
+
+/** the validation command for the candidate checked out in dir, or the working directory. a
+`{files}` in it is replaced with the paths the candidate changes, to only run the tests that
+matter for them */
+async fn validation_command(
+    cmd: &str,
+    base: &str,
+    env: &hooks::Env,
+    dir: Option<&Path>,
+) -> anyhow::Result<Command> {
+    let cmd = if cmd.contains("{files}") {
+        let files = changed_files(base, dir)
+            .await
+            .context("could not list the changed files")?;
+        cmd.replace("{files}", &files)
+    } else {
+        cmd.to_owned()
+    };
+    let mut command = Command::new("sh");
+    command
+        .args(["-c", &cmd])
+        .envs(env.iter().map(|(k, v)| (k, v)));
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    Ok(command)
+}
+
+/** run the validation command on the pull and tell whether it exited with 0. the output is kept
+for the report */
+async fn run_validation(
+    cmd: &str,
+    number: u64,
+    base: &str,
+    env: &hooks::Env,
+    dir: Option<&Path>,
+) -> anyhow::Result<bool> {
+    let mut command = validation_command(cmd, base, env, dir).await?;
+    let output = output_streaming(&mut command)
+        .await
+        .context("could not validate current branch")?;
+    validation_log::write(number, cmd, &output);
+    Ok(output.status.code() == Some(0))
+}
+
+/** run the validation command on the current candidate */
+fn validate(cmd: &str, branch: &str, s: &WorkingState) -> Receiver<anyhow::Result<bool>> {
+    let (tx, rx) = task::channel("validation");
+    let cmd = cmd.to_owned();
+    let base = s.base_ref(branch);
+    let env = validation_env(branch, s);
+    let number = s.current_checkout.pull.number;
+    log::info!("validating: {}", cmd);
+    tokio::spawn(async move {
+        let result = run_validation(&cmd, number, &base, &env, None).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        tx.send(result).await;
+    });
+
+    rx
+}
+
+/** validate the current candidate in a worktree of its own, so the next one can be rebased in the
+meantime */
+fn validate_in_worktree(
+    cmd: &str,
+    branch: &str,
+    options: GitOptions,
+    s: &WorkingState,
+) -> Receiver<anyhow::Result<bool>> {
+    let (tx, rx) = task::channel("worktree validation");
+    let cmd = cmd.to_owned();
+    let base = s.base_ref(branch);
+    let env = validation_env(branch, s);
+    let head = s.current_checkout.pull.head.branch.clone();
+    let number = s.current_checkout.pull.number;
+    log::info!("validating #{number} in a worktree: {cmd}");
+    tokio::spawn(async move {
+        let dir = match worktree::add(&head, number, options.no_lfs).await {
+            Ok(dir) => dir,
+            Err(e) => {
+                tx.send(Err(e)).await;
+                return;
+            }
+        };
+        let result = async {
+            if options.submodules {
+                let mut command = Command::new("git");
+                command
+                    .args(["submodule", "update", "--init", "--recursive"])
+                    .current_dir(&dir);
+                check_status(
+                    "git submodule update --init --recursive",
+                    output_remote(&mut command, options.remote_timeout).await?,
+                )
+                .context("could not update submodules")?;
+            }
+            if options.no_lfs {
+                let mut command = Command::new("git");
+                command.args(["lfs", "pull"]).current_dir(&dir);
+                check_status(
+                    "git lfs pull",
+                    output_remote(&mut command, options.remote_timeout).await?,
+                )
+                .context("could not pull lfs objects")?;
+            }
+            run_validation(&cmd, number, &base, &env, Some(&dir)).await
+        }
+        .await;
+        worktree::remove(&dir).await;
+        tx.send(result.with_context(|| format!("could not validate #{number}")))
+            .await;
+    });
+
+    rx
+}
+
+/// an operation someone started in the repo and didn't finish, e.g. a run that was killed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Rebase,
+    Merge,
+    CherryPick,
+    Revert,
+}
+
+impl Operation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Rebase => "rebase",
+            Operation::Merge => "merge",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+        }
+    }
+}
+
+/// what keeps the repo from being worked in
+#[derive(Clone, Debug, Default)]
+pub struct RepoStatus {
+    pub in_progress: Option<Operation>,
+    /// the changed and untracked files, as git status --porcelain lists them
+    pub dirty: Vec<String>,
+}
+
+impl RepoStatus {
+    pub fn is_clean(&self) -> bool {
+        self.in_progress.is_none() && self.dirty.is_empty()
+    }
+}
+
+/// the repo that isn't clean, looked at again now and then so the user sees what's left to do
+#[derive(Debug)]
+pub struct CleanState {
+    pub status: RepoStatus,
+    pub refresh: Option<Receiver<anyhow::Result<RepoStatus>>>,
+    /// when to look again
+    pub at: Instant,
+}
+
+/// how often the files keeping the repo from being clean are looked at again
+const CLEAN_REFRESH_DELAY: Duration = Duration::from_secs(3);
+
+impl CleanState {
+    pub fn new(status: RepoStatus) -> CleanState {
+        CleanState {
+            status,
+            refresh: None,
+            at: Instant::now() + CLEAN_REFRESH_DELAY,
+        }
+    }
+}
+
+/** the operation in progress, going by the files git keeps in its dir while one is */
+async fn in_progress(logged: bool) -> anyhow::Result<Option<Operation>> {
+    const MARKERS: [(&str, Operation); 5] = [
+        ("rebase-merge", Operation::Rebase),
+        ("rebase-apply", Operation::Rebase),
+        ("MERGE_HEAD", Operation::Merge),
+        ("CHERRY_PICK_HEAD", Operation::CherryPick),
+        ("REVERT_HEAD", Operation::Revert),
+    ];
+    let mut command = Command::new("git");
+    command.arg("rev-parse");
+    for (marker, _) in MARKERS {
+        command.args(["--git-path", marker]);
+    }
+    let output = if logged {
+        command.logged_output().await?
+    } else {
+        command.output().await?
+    };
+    let output = check_status("git rev-parse --git-path", output)?;
+    let paths = std::str::from_utf8(&output.stdout)?;
+    Ok(paths
+        .lines()
+        .zip(MARKERS)
+        .find(|(path, _)| Path::new(path).exists())
+        .map(|(_, (_, operation))| operation))
+}
+
+/** what keeps the repo from being clean. the refreshes while waiting for it aren't logged, they'd
+drown out the commands that matter */
+async fn repo_status(logged: bool) -> anyhow::Result<RepoStatus> {
+    let in_progress = in_progress(logged).await?;
+    let mut command = Command::new("git");
+    command.args(["status", "--porcelain"]);
+    let output = if logged {
+        command.logged_output().await?
+    } else {
+        command.output().await?
+    };
+    let output = check_status("git status --porcelain", output)?;
+    Ok(RepoStatus {
+        in_progress,
+        dirty: std::str::from_utf8(&output.stdout)?
+            .lines()
+            .map(str::to_owned)
+            .collect(),
+    })
+}
+
+/** look at the repo again without logging it, while waiting for it to be clean */
+fn refresh_repo_status() -> Receiver<anyhow::Result<RepoStatus>> {
+    let (tx, rx) = task::channel("clean refresh");
+    tokio::spawn(async move {
+        tx.send(repo_status(false).await).await;
+    });
+
+    rx
+}
+
+fn is_repo_clean() -> Receiver<anyhow::Result<RepoStatus>> {
+    let (tx, rx) = task::channel("clean check");
+    log::info!("running git status");
+
+    tokio::spawn(async move {
+        let result = repo_status(true).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        tx.send(result.context("could not check repo")).await;
+    });
+
+    rx
+}
+
+/** abort the operation someone left unfinished in the repo and check it again */
+fn abort_operation(operation: Operation) -> Receiver<anyhow::Result<RepoStatus>> {
+    let (tx, rx) = task::channel("abort");
+    info!("running git {} --abort", operation.name());
+    tokio::spawn(async move {
+        let result = async {
+            let output = Command::new("git")
+                .args([operation.name(), "--abort"])
+                .logged_output()
+                .await?;
+            check_status(&format!("git {} --abort", operation.name()), output)?;
+            repo_status(true).await
+        }
+        .await;
+        tx.send(result.with_context(|| format!("could not abort the {}", operation.name())))
+            .await;
+    });
+
+    rx
+}
+
+/// how each candidate gets onto its predecessor
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    /// rebase each pull's branch onto its predecessor and force-push it
+    Rebase,
+    /// cherry-pick each pull's commits onto a new integration branch and merge that instead,
+    /// leaving the pull branches untouched
+    CherryPick,
+}
+
+/** the branch the cherry-pick strategy collects the chain on */
+pub fn integration_branch(target: &str) -> String {
+    format!("marge-integration/{target}")
+}
+
+#[derive(PartialEq)]
+pub enum ActivePane {
+    List,
+    Log,
+}
+
+/// the narrowest either pane gets, in percent
+pub const MIN_WIDTH: u16 = 20;
+
+/// how far the app pane is scrolled down, for content that doesn't fit
+#[derive(Default)]
+pub struct PaneScroll {
+    /// the first line shown
+    pub offset: usize,
+    /// how many lines fit, as of the last draw
+    pub height: usize,
+    /// the line of the selected pull, as of the last draw, so it's only followed when it moves
+    pub selected: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SortingState {
+    pub unsorted: Vec<MergeCandidate>,
+    pub current_index: usize,
+    pub merge_chain: Vec<MergeCandidate>,
+    pub order: SortOrder,
+    /// the order the last keypress chained all the pulls in and how many it chained,
+    /// so pressing a again can try the next order instead
+    pub auto: Option<(AutoOrder, usize)>,
+    /// the unsorted pulls marked with x, in the order they were marked
+    pub marked: Vec<u64>,
+    /// the selected row of the options popup of the selected pull, while it's open
+    pub options: Option<usize>,
+    /// the chained pull shift and the arrows move through the chain, while they're pressed
+    pub moving: Option<usize>,
+    /// the number typed after # to jump to a pull, while it's being typed
+    pub jump: Option<Input>,
+}
+
+impl SortingState {
+    pub fn new(unsorted: Vec<MergeCandidate>, merge_chain: Vec<MergeCandidate>) -> Self {
+        let mut state = SortingState {
+            unsorted,
+            current_index: 0,
+            merge_chain,
+            order: SortOrder::default(),
+            auto: None,
+            marked: vec![],
+            options: None,
+            moving: None,
+            jump: None,
+        };
+        state.order.sort(&mut state.unsorted);
+        state
+    }
+
+    /** select the previous unsorted candidate, wrapping around to the last */
+    pub fn select_prev(&mut self) {
+        self.current_index = if self.current_index == 0 {
+            self.unsorted.len().saturating_sub(1)
+        } else {
+            self.current_index - 1
+        };
+    }
+
+    /** select the next unsorted candidate, wrapping around to the first */
+    pub fn select_next(&mut self) {
+        self.current_index = if self.current_index + 1 >= self.unsorted.len() {
+            0
+        } else {
+            self.current_index + 1
+        };
+    }
+
+    /** select the candidate rows further down, or up for negative rows, stopping at the ends */
+    pub fn select_by(&mut self, rows: isize) {
+        let last = self.unsorted.len().saturating_sub(1);
+        self.current_index = self.current_index.saturating_add_signed(rows).min(last);
+    }
+
+    /** select the unsorted candidate with the number, or the one at that place in the list if
+    there's none with it */
+    pub fn jump_to(&mut self, typed: &str) {
+        let Ok(n) = typed.trim().trim_start_matches('#').parse::<usize>() else {
+            warn!("{typed} is neither a pull number nor a place in the list");
+            return;
+        };
+        if let Some(i) = self.unsorted.iter().position(|c| c.pull.number == n as u64) {
+            self.current_index = i;
+        } else if (1..=self.unsorted.len()).contains(&n) {
+            self.current_index = n - 1;
+        } else {
+            warn!(
+                "there's no pull #{n} left to chain, and only {} in the list",
+                self.unsorted.len()
+            );
+        }
+    }
+
+    pub fn select_first(&mut self) {
+        self.current_index = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.current_index = self.unsorted.len().saturating_sub(1);
+    }
+
+    /** move the chained candidate that was moved by the last key, or the last one, one place up
+    or down the chain */
+    pub fn move_chained(&mut self, moving: Option<usize>, up: bool) {
+        let Some(last) = self.merge_chain.len().checked_sub(1) else {
+            return;
+        };
+        let from = moving.unwrap_or(last).min(last);
+        let to = if up {
+            from.saturating_sub(1)
+        } else {
+            (from + 1).min(last)
+        };
+        self.merge_chain.swap(from, to);
+        self.moving = Some(to);
+    }
+
+    /** mark the selected candidate to be chained with the other marked ones, or unmark it */
+    pub fn toggle_mark(&mut self) {
+        let Some(c) = self.unsorted.get(self.current_index) else {
+            return;
+        };
+        let number = c.pull.number;
+        match self.marked.iter().position(|n| *n == number) {
+            Some(i) => {
+                self.marked.remove(i);
+            }
+            None => self.marked.push(number),
+        }
+    }
+
+    /** put the marked candidates on top of the chain in the order they were marked */
+    pub fn chain_marked(&mut self) {
+        for number in std::mem::take(&mut self.marked) {
+            if let Some(i) = self.unsorted.iter().position(|c| c.pull.number == number) {
+                let next_head = self.unsorted.remove(i);
+                self.merge_chain.push(next_head);
+            }
+        }
+        self.current_index = 0;
+    }
+
+    /** put the selected candidate on top of the chain */
+    pub fn chain_selected(&mut self) {
+        if self.current_index < self.unsorted.len() {
+            let next_head = self.unsorted.remove(self.current_index);
+            self.merge_chain.push(next_head);
+        }
+        self.current_index = 0;
+    }
+
+    /** toggle squashing the selected candidate into a single commit */
+    pub fn toggle_squash(&mut self) {
+        if let Some(c) = self.unsorted.get_mut(self.current_index) {
+            c.squash = !c.squash;
+        }
+    }
+
+    /** order the unsorted candidates by the next column */
+    pub fn reorder(&mut self) {
+        self.order = self.order.next();
+        self.order.sort(&mut self.unsorted);
+        self.current_index = 0;
+    }
+
+    /** chain all the unsorted candidates at once. if the last key did that already, they're put
+    back first and chained in the next order */
+    pub fn chain_all(&mut self, auto: Option<(AutoOrder, usize)>) {
+        let order = match auto {
+            Some((order, chained)) => {
+                let back = self
+                    .merge_chain
+                    .split_off(self.merge_chain.len().saturating_sub(chained));
+                self.unsorted.extend(back);
+                order.next()
+            }
+            None => AutoOrder::Created,
+        };
+        let unsorted = std::mem::take(&mut self.unsorted);
+        let chained = unsorted.len();
+        self.marked.clear();
+        self.merge_chain.extend(order.order(unsorted));
+        self.auto = Some((order, chained));
+        self.current_index = 0;
+    }
+
+    /** put the top of the chain back with the unsorted candidates */
+    pub fn unchain_last(&mut self) {
+        if let Some(head) = self.merge_chain.pop() {
+            self.unsorted.push(head);
+        }
+        self.current_index = 0;
+    }
+
+    /** move through the options popup of the selected candidate and change them */
+    pub fn option_key(&mut self, code: &KeyCode, row: usize) {
+        let count = CandidateOption::ALL.len();
+        self.options = match code {
+            KeyCode::Up | KeyCode::Char('k') => Some((row + count - 1) % count),
+            KeyCode::Down | KeyCode::Char('j') => Some((row + 1) % count),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let bases = self.bases();
+                if let Some(c) = self.unsorted.get_mut(self.current_index) {
+                    match CandidateOption::ALL[row] {
+                        CandidateOption::Base => c.next_base(&bases),
+                        option => c.toggle(option),
+                    }
+                }
+                Some(row)
+            }
+            KeyCode::Esc | KeyCode::Char('O' | 'q') => None,
+            _ => Some(row),
+        };
+    }
+
+    /** the branches the pulls are based on, which a candidate can be rebased onto instead */
+    pub fn bases(&self) -> Vec<String> {
+        let mut bases = self
+            .unsorted
+            .iter()
+            .chain(&self.merge_chain)
+            .map(|c| c.original_base.clone())
+            .collect::<Vec<String>>();
+        bases.sort();
+        bases.dedup();
+        bases
+    }
+}
+
+/// what the unsorted pulls are ordered by, o cycles through these
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    Number,
+    Author,
+    Age,
+    Commits,
+    Lines,
+    Labels,
+    Base,
+}
+
+impl SortOrder {
+    fn next(self) -> SortOrder {
+        match self {
+            SortOrder::Number => SortOrder::Author,
+            SortOrder::Author => SortOrder::Age,
+            SortOrder::Age => SortOrder::Commits,
+            SortOrder::Commits => SortOrder::Lines,
+            SortOrder::Lines => SortOrder::Labels,
+            SortOrder::Labels => SortOrder::Base,
+            SortOrder::Base => SortOrder::Number,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::Number => "number",
+            SortOrder::Author => "author",
+            SortOrder::Age => "age",
+            SortOrder::Commits => "commits",
+            SortOrder::Lines => "changed lines",
+            SortOrder::Labels => "labels",
+            SortOrder::Base => "base branch",
+        }
+    }
+
+    /** oldest and smallest first. pulls whose stats didn't arrive yet go last */
+    fn sort(self, pulls: &mut [MergeCandidate]) {
+        match self {
+            SortOrder::Number => pulls.sort_by_key(|c| c.pull.number),
+            SortOrder::Author => pulls.sort_by_key(|c| c.pull.author.clone()),
+            SortOrder::Age => pulls.sort_by_key(|c| c.pull.created_at),
+            SortOrder::Commits => pulls.sort_by_key(|c| c.stats.map_or(u64::MAX, |s| s.commits)),
+            SortOrder::Lines => {
+                pulls.sort_by_key(|c| c.stats.map_or(u64::MAX, |s| s.additions + s.deletions));
+            }
+            SortOrder::Labels => pulls.sort_by_key(|c| c.pull.labels.clone()),
+            SortOrder::Base => pulls.sort_by_key(|c| c.pull.base.branch.clone()),
+        }
+    }
+}
+
+/// the orders a can put all the unsorted pulls into the chain in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoOrder {
+    Created,
+    Updated,
+    /// pulls based on another pull's branch come after it
+    Stacked,
+}
+
+impl AutoOrder {
+    pub fn next(self) -> AutoOrder {
+        match self {
+            AutoOrder::Created => AutoOrder::Updated,
+            AutoOrder::Updated => AutoOrder::Stacked,
+            AutoOrder::Stacked => AutoOrder::Created,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AutoOrder::Created => "creation date",
+            AutoOrder::Updated => "last update",
+            AutoOrder::Stacked => "base branch",
+        }
+    }
+
+    /** the pulls in the order they should be chained, oldest first */
+    fn order(self, mut pulls: Vec<MergeCandidate>) -> Vec<MergeCandidate> {
+        match self {
+            AutoOrder::Created => pulls.sort_by_key(|c| c.pull.created_at),
+            AutoOrder::Updated => pulls.sort_by_key(|c| c.pull.updated_at),
+            AutoOrder::Stacked => {
+                pulls.sort_by_key(|c| c.pull.created_at);
+                let mut ordered = Vec::with_capacity(pulls.len());
+                while !pulls.is_empty() {
+                    let heads = pulls
+                        .iter()
+                        .map(|c| c.pull.head.branch.clone())
+                        .collect::<HashSet<String>>();
+                    // a pull is ready once the pull it's based on is chained. if they're based
+                    // on each other in a circle, just take the oldest
+                    let next = pulls
+                        .iter()
+                        .position(|c| !heads.contains(&c.pull.base.branch))
+                        .unwrap_or(0);
+                    ordered.push(pulls.remove(next));
+                }
+                return ordered;
+            }
+        }
+        pulls
+    }
+}
+
+/// commit and line counts of the open pulls. only the single pull endpoint has them,
+/// so they're fetched one by one in the background while the user sorts
+pub struct PullStatsCache {
+    known: HashMap<u64, PullStats>,
+    requested: HashSet<u64>,
+    tx: Sender<(u64, PullStats)>,
+    rx: Receiver<(u64, PullStats)>,
+}
+
+impl PullStatsCache {
+    pub fn new() -> Self {
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        PullStatsCache {
+            known: HashMap::new(),
+            requested: HashSet::new(),
+            tx,
+            rx,
+        }
+    }
+
+    /** fill in the stats that arrived so far and request the ones nobody asked for yet */
+    fn update(&mut self, forge: &Arc<dyn Forge>, remote: &Remote, state: &mut SortingState) {
+        while let Ok((number, stats)) = self.rx.try_recv() {
+            self.known.insert(number, stats);
+        }
+        for c in state.unsorted.iter_mut().chain(&mut state.merge_chain) {
+            if c.stats.is_some() {
+                continue;
+            }
+            let number = c.pull.number;
+            if let Some(stats) = self.known.get(&number) {
+                c.stats = Some(*stats);
+            } else if self.requested.insert(number) {
+                let forge = forge.clone();
+                let remote = remote.clone();
+                let tx = self.tx.clone();
+                tokio::spawn(async move {
+                    match forge.pull(&remote, number).await {
+                        Ok(pull) => {
+                            let stats = PullStats {
+                                commits: pull.commits.unwrap_or_default(),
+                                additions: pull.additions.unwrap_or_default(),
+                                deletions: pull.deletions.unwrap_or_default(),
+                            };
+                            let _ = tx.send((number, stats)).await;
+                        }
+                        Err(e) => debug!("could not get the stats of #{number}: {e:#}"),
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// the rest of the chain being edited while marge waits for the user
+#[derive(Debug)]
+pub struct ChainEditState {
+    /// the remaining chain and the open pulls that aren't part of the run
+    pub sorting: SortingState,
+    /// the state to go back to, with the edited chain
+    pub waiting: Box<AppState>,
+}
+
+/// the files a rebase stopped on, o and t resolve the selected one by taking a side
+#[derive(Debug)]
+pub struct ConflictState {
+    pub files: Vec<Conflict>,
+    pub current_index: usize,
+    /// the files as they're listed again, and whether to continue the rebase once there are
+    /// none left because the user took a side
+    listing: Option<(Receiver<anyhow::Result<Vec<Conflict>>>, bool)>,
+}
+
+/// a file with conflicts
+#[derive(Debug)]
+pub struct Conflict {
+    pub path: String,
+    /// who last touched the lines it conflicts on, to know who to ask
+    pub owners: Vec<String>,
+}
+
+impl ConflictState {
+    pub fn new() -> Self {
+        ConflictState {
+            files: vec![],
+            current_index: 0,
+            listing: Some((resolve_file(None), false)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BranchPickState {
+    pub branches: Vec<String>,
+    pub current_index: usize,
+}
+
+#[derive(Debug)]
+pub struct WorkingState {
+    pub current_checkout: MergeCandidate,
+    pub next: Vec<MergeCandidate>,
+    pub done: Vec<MergeCandidate>,
+}
+
+/// the targets the merged chain still needs to be backported to
+#[derive(Clone, Debug, Default)]
+pub struct Backports {
+    pub targets: Vec<String>,
+    /// run the opened backport pulls through the pipeline and merge them
+    pub merge: bool,
+    /// the branch the original chain was merged into
+    pub base: String,
+    /// the original chain, in merge order
+    pub sources: Vec<MergeCandidate>,
+}
+
+#[derive(Debug)]
+pub struct BackportState {
+    pub target: String,
+    pub current: MergeCandidate,
+    pub next: Vec<MergeCandidate>,
+    pub done: Vec<MergeCandidate>,
+    /// the backport pulls opened so far
+    pub opened: Vec<MergeCandidate>,
+}
+
+impl BackportState {
+    pub fn branch_name(&self) -> String {
+        format!("backport/{}/{}", self.target, self.current.pull.head.branch)
+    }
+}
+
+impl WorkingState {
+    /** the branch the current candidate goes on top of, the one set in its options if there is one */
+    pub fn base(&self, branch: &str) -> String {
+        if let Some(onto) = &self.current_checkout.onto {
+            return onto.clone();
+        }
+        self.done
+            .last()
+            .map(|c| c.pull.head.branch.clone())
+            .unwrap_or(branch.to_owned())
+    }
+
+    /** what git rebases the current candidate onto: the fetched copy of its base if it has one */
+    pub fn base_ref(&self, branch: &str) -> String {
+        match &self.current_checkout.onto_ref {
+            Some(onto_ref) => onto_ref.clone(),
+            None => self.base(branch),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MergingState {
+    pub to_merge: Vec<MergeCandidate>,
+    /// how often the forge turned the first pull away because it wasn't ready yet
+    pub attempts: u32,
+    /// how often the previous merge or the first pull weren't settled yet
+    pub polls: u32,
+    /// don't merge before then
+    pub wait_until: Option<Instant>,
+}
+
+/// the candidates rebased with --parallel, pushed in order as their validations pass
+#[derive(Debug)]
+pub struct ParallelPush {
+    /// the whole chain, in order
+    pub done: Vec<MergeCandidate>,
+    /// the candidate whose validation or push is waited for
+    pub current: usize,
+    pub validation: Option<Receiver<anyhow::Result<bool>>>,
+    pub push: Option<Receiver<anyhow::Result<String>>>,
+}
+
+impl ParallelPush {
+    /** push the rebased chain, skipping the candidates that were pushed already */
+    pub fn new(done: Vec<MergeCandidate>) -> ParallelPush {
+        let current = done
+            .iter()
+            .position(|c| c.status != Status::Pushed)
+            .unwrap_or(done.len());
+        ParallelPush {
+            done,
+            current,
+            validation: None,
+            push: None,
+        }
+    }
+
+    /** the chain as it looks from the current candidate */
+    pub fn working_state(&self) -> WorkingState {
+        WorkingState {
+            current_checkout: self.done[self.current].clone(),
+            next: self.done[self.current + 1..].to_vec(),
+            done: self.done[..self.current].to_vec(),
+        }
+    }
+}
+
+impl MergingState {
+    /** merge the pulls in order, after the gap from the settings */
+    pub fn new(to_merge: Vec<MergeCandidate>, merging: Merging) -> MergingState {
+        MergingState {
+            to_merge,
+            attempts: 0,
+            polls: 0,
+            wait_until: Some(Instant::now() + Duration::from_secs(merging.gap)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AppState {
+    /// ask whether to go on with the chain an earlier run in this repo left unfinished
+    OfferingSession(Plan),
+    /// make sure that the current state of the repo is clean
+    CheckingRepo(Receiver<anyhow::Result<RepoStatus>>),
+    /// waiting for the user to tell us to check again, or to abort what's in progress
+    WaitingForCleanRepo(CleanState),
+    /// get the branches on the remote to pick the target branch from
+    ListingBranches(Receiver<anyhow::Result<Vec<String>>>),
+    /// wait for the user to pick the target branch
+    WaitingForBranch(BranchPickState),
+    /// check out our target branch
+    CheckingOutTargetBranch(Receiver<anyhow::Result<()>>),
+    /// pull the latest state from the remote
+    PullingRemote(Receiver<anyhow::Result<()>>),
+    /// get the list of open pull requests
+    GettingPulls,
+    /// wait for the user to select the pulls to be merged
+    WaitingForSort(SortingState),
+    /// get the open pulls again without losing the chain the user built so far
+    RefreshingPulls(SortingState),
+    /// start the run with the sorted or planned chain
+    StartingChain(Vec<MergeCandidate>),
+    /// point the pulls retargeted so far back at their old bases, then show the failure again
+    UndoingRetargets(Failure),
+    /// change the base of the current pull request to the previous one (or target)
+    UpdatingCandidate(WorkingState),
+    /// check out the branch belonging to the current pull request
+    CheckingOutCandidate(Receiver<anyhow::Result<()>>, WorkingState),
+    /// run rebase on the current branch
+    RebaseCandidate(Receiver<anyhow::Result<bool>>, WorkingState),
+    /// check if the rebase resulted in conflicts
+    CheckingForConflicts(Receiver<anyhow::Result<bool>>, WorkingState),
+    /// wait for the user to manually fix the results and then signal
+    WaitingForResolution(ConflictState, WorkingState),
+    /// compare the commits of the current candidate before and after the rebase
+    ComparingRebase(Receiver<anyhow::Result<String>>, WorkingState),
+    /// with --review-rebase, compare the validated candidate with its old head before asking to push it
+    ComparingBeforePush(Receiver<anyhow::Result<String>>, WorkingState),
+    /// write the default squash message for the current candidate
+    PreparingSquash(Receiver<anyhow::Result<PathBuf>>, WorkingState),
+    /// wait for the user to edit the squash message and signal us
+    WaitingForSquashMessage(PathBuf, WorkingState),
+    /// squash the rebased candidate into a single commit
+    Squashing(Receiver<anyhow::Result<()>>, WorkingState),
+    /// bring the submodules in line with the rebased branch
+    UpdatingSubmodules(Receiver<anyhow::Result<()>>, WorkingState),
+    /// download the LFS objects the validation needs
+    PullingLfs(Receiver<anyhow::Result<()>>, WorkingState),
+    /// check that the rebased branch passes the validation statement
+    Validating(Receiver<anyhow::Result<bool>>, WorkingState),
+    /// wait for the user to fix any errors and signal us
+    WaitingForFix(WorkingState),
+    /// force-push the branch to the remote
+    PushingCandidate(Receiver<anyhow::Result<String>>, WorkingState),
+    /// wait for the validations in the worktrees and push the candidates that passed, with
+    /// --parallel
+    PushingValidated(ParallelPush),
+    /// check out the candidate that failed its validation in a worktree, to fix it
+    CheckingOutToFix(Receiver<anyhow::Result<()>>, WorkingState),
+    /// run the workflow that validates the pushed candidate, with --workflow
+    DispatchingWorkflow(WorkingState),
+    /// wait for the workflow run on the pushed candidate to pass
+    WaitingForWorkflow(WorkingState, Instant),
+    /// wait for the checks on the pushed candidate before merging it right away, with --rolling
+    WaitingForChecks(WorkingState, Instant),
+    /// merge the pushed candidate on its own
+    MergingCandidate(WorkingState),
+    /// bring the local target branch up to date with the merge before the next candidate is
+    /// rebased onto it
+    UpdatingTarget(Receiver<anyhow::Result<()>>, WorkingState),
+    /// cherry-pick the current pull's commits onto the integration branch
+    CherryPicking(Receiver<anyhow::Result<bool>>, WorkingState),
+    /// wait for the user to resolve cherry-pick conflicts on the integration branch
+    WaitingForPickResolution(WorkingState),
+    /// push the finished integration branch and open a pull for it
+    PushingIntegration(Receiver<anyhow::Result<()>>, WorkingState),
+    /// merge all the pulls that were rebased
+    Merging(MergingState),
+    /// point the open pulls based on merged branches at the target and prune the refs
+    /// of the deleted branches
+    CleaningUp,
+    /// fetch the merged branches and make sure the merged commits are on them
+    VerifyingMerges(Receiver<anyhow::Result<Vec<(u64, bool)>>>),
+    /// cherry-pick the current merged pull onto its backport branch
+    Backporting(Receiver<anyhow::Result<bool>>, BackportState),
+    /// wait for the user to resolve cherry-pick conflicts and signal us
+    WaitingForBackportResolution(BackportState),
+    /// push the backport branch and open a pull for it
+    PushingBackport(Receiver<anyhow::Result<()>>, BackportState),
+    Done,
+    /// something went wrong, wait for the user to tell us what to do
+    Failed(Failure),
+    /// clean up whatever git operation the failure left behind
+    Aborting(Receiver<anyhow::Result<()>>),
+    /// the user gave up on the run, time to quit
+    Aborted,
+    /// wait for the user to ok the next destructive step
+    WaitingForConfirmation(Confirmation),
+    /// get the open pulls that could be added to the rest of the chain
+    LoadingChainEdit(Box<AppState>),
+    /// append the pulls that were opened since the run started to the chain
+    RefreshingChain(Box<AppState>),
+    /// let the user reorder, remove or add the pulls that are still to come
+    EditingChain(ChainEditState),
+}
+
+impl AppState {
+    /** the pulls on screen, if the user is sorting them */
+    fn sorting_state(&mut self) -> Option<&mut SortingState> {
+        match self {
+            AppState::WaitingForSort(s) => Some(s),
+            AppState::EditingChain(s) => Some(&mut s.sorting),
+            _ => None,
+        }
+    }
+
+    /** whether marge waits for the user to resolve conflicts */
+    fn has_conflicts(&self) -> bool {
+        matches!(
+            self,
+            AppState::WaitingForResolution(..)
+                | AppState::WaitingForPickResolution(_)
+                | AppState::WaitingForBackportResolution(_)
+        )
+    }
+
+    /** the working state of a state waiting for the user, if its chain can be edited */
+    fn waiting_working_state(&mut self) -> Option<&mut WorkingState> {
+        match self {
+            AppState::WaitingForResolution(_, s)
+            | AppState::WaitingForSquashMessage(_, s)
+            | AppState::WaitingForFix(s)
+            | AppState::WaitingForPickResolution(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /** a short name for the state, for the status bar */
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppState::OfferingSession(_) => "offering the last session",
+            AppState::CheckingRepo(..) => "checking repo",
+            AppState::WaitingForCleanRepo(..) => "waiting for a clean repo",
+            AppState::ListingBranches(..) => "listing branches",
+            AppState::WaitingForBranch(..) => "picking the branch",
+            AppState::CheckingOutTargetBranch(..) => "checking out the target",
+            AppState::PullingRemote(..) => "pulling",
+            AppState::GettingPulls => "getting pulls",
+            AppState::WaitingForSort(..) => "sorting",
+            AppState::RefreshingPulls(..) => "refreshing pulls",
+            AppState::StartingChain(..) => "starting",
+            AppState::UndoingRetargets(..) => "undoing retargets",
+            AppState::UpdatingCandidate(..) => "retargeting",
+            AppState::CheckingOutCandidate(..) => "checking out",
+            AppState::RebaseCandidate(..) => "rebasing",
+            AppState::CheckingForConflicts(..) => "checking for conflicts",
+            AppState::WaitingForResolution(..) => "resolving conflicts",
+            AppState::ComparingRebase(..) => "comparing the rebase",
+            AppState::ComparingBeforePush(..) => "comparing before pushing",
+            AppState::PreparingSquash(..) => "preparing the squash",
+            AppState::WaitingForSquashMessage(..) => "editing the squash message",
+            AppState::Squashing(..) => "squashing",
+            AppState::UpdatingSubmodules(..) => "updating submodules",
+            AppState::PullingLfs(..) => "pulling lfs objects",
+            AppState::Validating(..) => "validating",
+            AppState::WaitingForFix(..) => "fixing validation",
+            AppState::PushingCandidate(..) => "pushing",
+            AppState::PushingValidated(..) => "pushing validated",
+            AppState::CheckingOutToFix(..) => "checking out",
+            AppState::DispatchingWorkflow(..) => "dispatching the workflow",
+            AppState::WaitingForWorkflow(..) => "waiting for the workflow",
+            AppState::WaitingForChecks(..) => "waiting for checks",
+            AppState::MergingCandidate(..) => "merging",
+            AppState::UpdatingTarget(..) => "updating the target",
+            AppState::CherryPicking(..) => "cherry-picking",
+            AppState::WaitingForPickResolution(..) => "resolving conflicts",
+            AppState::PushingIntegration(..) => "pushing the integration branch",
+            AppState::Merging(..) => "merging",
+            AppState::CleaningUp => "cleaning up",
+            AppState::VerifyingMerges(_) => "verifying merges",
+            AppState::Backporting(..) => "backporting",
+            AppState::WaitingForBackportResolution(..) => "resolving backport conflicts",
+            AppState::PushingBackport(..) => "pushing the backport",
+            AppState::Done => "done",
+            AppState::Failed(..) => "failed",
+            AppState::Aborting(..) => "aborting",
+            AppState::Aborted => "aborted",
+            AppState::WaitingForConfirmation(..) => "waiting for confirmation",
+            AppState::LoadingChainEdit(..) => "loading the chain",
+            AppState::RefreshingChain(..) => "refreshing the chain",
+            AppState::EditingChain(..) => "editing the chain",
+        }
+    }
+
+    /** the pull the state is about: the selected one while sorting, the one being worked on later */
+    pub fn current_candidate(&self) -> Option<&MergeCandidate> {
+        if let Some(s) = self.working_state() {
+            return Some(&s.current_checkout);
+        }
+        match self {
+            AppState::WaitingForSort(s) | AppState::RefreshingPulls(s) => {
+                s.unsorted.get(s.current_index)
+            }
+            AppState::EditingChain(s) => s.sorting.unsorted.get(s.sorting.current_index),
+            AppState::Merging(s) => s.to_merge.first(),
+            AppState::PushingValidated(p) => p.done.get(p.current),
+            AppState::Backporting(_, s)
+            | AppState::WaitingForBackportResolution(s)
+            | AppState::PushingBackport(_, s) => Some(&s.current),
+            _ => None,
+        }
+    }
+
+    /** whether quitting in the state could leave a branch or pull half changed */
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            AppState::UpdatingCandidate(_)
+                | AppState::RebaseCandidate(..)
+                | AppState::Squashing(..)
+                | AppState::PushingCandidate(..)
+                | AppState::PushingValidated(_)
+                | AppState::MergingCandidate(_)
+                | AppState::CherryPicking(..)
+                | AppState::PushingIntegration(..)
+                | AppState::Merging(_)
+                | AppState::CleaningUp
+                | AppState::Backporting(..)
+                | AppState::PushingBackport(..)
+                | AppState::UndoingRetargets(_)
+        )
+    }
+
+    /** whether the state waits for a background task */
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            AppState::CheckingRepo(_)
+                | AppState::ListingBranches(_)
+                | AppState::CheckingOutTargetBranch(_)
+                | AppState::PullingRemote(_)
+                | AppState::CheckingOutCandidate(..)
+                | AppState::RebaseCandidate(..)
+                | AppState::CheckingForConflicts(..)
+                | AppState::ComparingRebase(..)
+                | AppState::ComparingBeforePush(..)
+                | AppState::PreparingSquash(..)
+                | AppState::Squashing(..)
+                | AppState::UpdatingSubmodules(..)
+                | AppState::PullingLfs(..)
+                | AppState::Validating(..)
+                | AppState::PushingCandidate(..)
+                | AppState::PushingValidated(_)
+                | AppState::CheckingOutToFix(..)
+                | AppState::UpdatingTarget(..)
+                | AppState::CherryPicking(..)
+                | AppState::PushingIntegration(..)
+                | AppState::VerifyingMerges(_)
+                | AppState::Backporting(..)
+                | AppState::PushingBackport(..)
+                | AppState::Aborting(_)
+        )
+    }
+
+    /** whether the user is typing into the state, so single keys don't do anything else */
+    pub fn typing(&self) -> bool {
+        match self {
+            AppState::WaitingForSort(s)
+            | AppState::EditingChain(ChainEditState { sorting: s, .. }) => s.jump.is_some(),
+            _ => false,
+        }
+    }
+
+    /** the chain being worked through, in any state that works on a candidate */
+    pub fn working_state(&self) -> Option<&WorkingState> {
+        match self {
+            AppState::UpdatingCandidate(s)
+            | AppState::WaitingForFix(s)
+            | AppState::WaitingForPickResolution(s)
+            | AppState::CheckingOutCandidate(_, s)
+            | AppState::WaitingForResolution(_, s)
+            | AppState::WaitingForSquashMessage(_, s)
+            | AppState::PushingCandidate(_, s)
+            | AppState::CheckingOutToFix(_, s)
+            | AppState::DispatchingWorkflow(s)
+            | AppState::WaitingForWorkflow(s, _)
+            | AppState::WaitingForChecks(s, _)
+            | AppState::MergingCandidate(s)
+            | AppState::UpdatingTarget(_, s)
+            | AppState::RebaseCandidate(_, s)
+            | AppState::CheckingForConflicts(_, s)
+            | AppState::Validating(_, s)
+            | AppState::CherryPicking(_, s)
+            | AppState::ComparingRebase(_, s)
+            | AppState::ComparingBeforePush(_, s)
+            | AppState::PreparingSquash(_, s)
+            | AppState::Squashing(_, s)
+            | AppState::UpdatingSubmodules(_, s)
+            | AppState::PullingLfs(_, s)
+            | AppState::PushingIntegration(_, s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /** the pulls of the chain the state still has, in the states that know about them. the
+    merged ones are gone from it once marge merges the whole chain at the end */
+    pub fn chain(&self) -> Option<Vec<&MergeCandidate>> {
+        match self {
+            AppState::Merging(m) => Some(m.to_merge.iter().collect()),
+            AppState::PushingValidated(p) => Some(p.done.iter().collect()),
+            AppState::Done => Some(vec![]),
+            state => state.working_state().map(|s| {
+                s.done
+                    .iter()
+                    .chain([&s.current_checkout])
+                    .chain(&s.next)
+                    .collect()
+            }),
+        }
+    }
+
+    /** the pulls of the chain that aren't merged yet. none are left once it's done */
+    pub fn chain_left(&self) -> Option<Vec<&MergeCandidate>> {
+        let mut chain = self.chain()?;
+        chain.retain(|c| !matches!(c.status, Status::Merged | Status::Skipped));
+        Some(chain)
+    }
+
+    /** what the user should be told if marge waits for them in this state */
+    pub fn attention(&self) -> Option<&'static str> {
+        match self {
+            AppState::OfferingSession(_) => Some("the last chain wasn't finished"),
+            AppState::WaitingForCleanRepo(_) => Some("the repo needs to be cleaned up"),
+            AppState::WaitingForBranch(_) => Some("pick the branch to merge into"),
+            AppState::WaitingForSort(_) => Some("pick the pulls to merge"),
+            AppState::WaitingForResolution(..)
+            | AppState::WaitingForPickResolution(_)
+            | AppState::WaitingForBackportResolution(_) => Some("there are conflicts to resolve"),
+            AppState::WaitingForSquashMessage(..) => Some("the squash message is ready to edit"),
+            AppState::WaitingForFix(_) => Some("validation failed"),
+            AppState::WaitingForConfirmation(_) => Some("the next step needs your confirmation"),
+            AppState::Failed(_) => Some("a step failed"),
+            AppState::Done => Some("the chain is merged"),
+            _ => None,
+        }
+    }
+}
+
+/// a command that ran but exited unsuccessfully
+#[derive(Debug)]
+pub struct CommandFailed {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exit_code {
+            Some(code) => write!(f, "`{}` exited with {code}", self.command),
+            None => write!(f, "`{}` was killed", self.command),
+        }
+    }
+}
+
+impl std::error::Error for CommandFailed {}
+
+/** turn an unsuccessful exit into a CommandFailed error */
+fn check_status(command: &str, output: Output) -> anyhow::Result<Output> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(CommandFailed {
+            command: command.to_owned(),
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+        .into())
+    }
+}
+
+/// re-enters the step that failed with a fresh task
+pub struct Retry(Box<dyn FnOnce() -> AppState + Send>);
+
+impl std::fmt::Debug for Retry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Retry")
+    }
+}
+
+/// a destructive step waiting for the user's go-ahead
+pub struct Confirmation {
+    pub prompt: String,
+    proceed: Box<dyn FnOnce() -> AppState + Send>,
+}
+
+impl std::fmt::Debug for Confirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Confirmation")
+            .field("prompt", &self.prompt)
+            .finish_non_exhaustive()
+    }
+}
+
+/** if the user asked to confirm destructive steps, wait for a y before entering the state proceed returns */
+fn ask_first(
+    confirm: bool,
+    prompt: String,
+    proceed: impl FnOnce() -> AppState + Send + 'static,
+) -> AppState {
+    if !confirm {
+        return proceed();
+    }
+    AppState::WaitingForConfirmation(Confirmation {
+        prompt,
+        proceed: Box::new(proceed),
+    })
+}
+
+/** retarget the current candidate onto its predecessor, asking first if needed */
+fn update_candidate(confirm: bool, branch: &str, mut s: WorkingState) -> AppState {
+    s.current_checkout.start();
+    let prompt = format!(
+        "press y to retarget #{} onto {}",
+        s.current_checkout.pull.number,
+        s.base(branch)
+    );
+    ask_first(confirm, prompt, move || AppState::UpdatingCandidate(s))
+}
+
+/** merge the first of the pulls, asking first if needed */
+fn merge_next(options: GitOptions, to_merge: Vec<MergeCandidate>) -> AppState {
+    let prompt = format!("press y to merge #{}", to_merge[0].pull.number);
+    ask_first(options.confirm, prompt, move || {
+        AppState::Merging(MergingState::new(to_merge, options.merging))
+    })
+}
+
+/// what kind of step failed, for telling a validation that couldn't run from the other steps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FailureKind {
+    #[default]
+    Step,
+    Validation,
+}
+
+/// everything we know about why the run stopped
+#[derive(Debug)]
+pub struct Failure {
+    /// what marge was doing when it failed
+    pub step: &'static str,
+    pub kind: FailureKind,
+    pub error: anyhow::Error,
+    pub retry: Option<Retry>,
+}
+
+impl Failure {
+    pub fn new(step: &'static str, error: anyhow::Error) -> Failure {
+        error!("failed while {step}: {error:#}");
+        Failure {
+            step,
+            kind: FailureKind::default(),
+            error,
+            retry: None,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: FailureKind) -> Failure {
+        self.kind = kind;
+        self
+    }
+
+    /** let the user re-enter the failed step instead of aborting the run */
+    pub fn with_retry(mut self, retry: impl FnOnce() -> AppState + Send + 'static) -> Failure {
+        self.retry = Some(Retry(Box::new(retry)));
+        self
+    }
+
+    /** the command that failed, if the failure came from one */
+    pub fn command(&self) -> Option<&CommandFailed> {
+        self.error.chain().find_map(|e| e.downcast_ref())
+    }
+
+    /** a guess at what the user can do about it */
+    pub fn remediation(&self) -> &'static str {
+        if self
+            .error
+            .chain()
+            .any(|e| e.is::<octocrab::Error>() || e.is::<reqwest::Error>())
+        {
+            return "check your network connection and that the token may access the repo";
+        }
+        match self.command().map(|c| c.command.as_str()) {
+            Some(c) if c.starts_with("git push") => {
+                "the push was rejected, check the hook output and your push permissions"
+            }
+            Some(c) if c.starts_with("git pull") => {
+                "the target branch diverged from the remote, reconcile it by hand"
+            }
+            Some(c) if c.starts_with("git checkout") => {
+                "make sure the branch exists on the remote and the working tree is clean"
+            }
+            Some(_) => "run the command by hand to see what went wrong",
+            None => "check the logs for details",
+        }
+    }
+}
+
+/** the failed state for a step whose task returned an error or went away */
+fn failed<T>(
+    step: &'static str,
+    result: Option<anyhow::Result<T>>,
+    retry: impl FnOnce() -> AppState + Send + 'static,
+) -> AppState {
+    failed_as(FailureKind::Step, step, result, retry)
+}
+
+/** like `failed`, for a step of the given kind */
+fn failed_as<T>(
+    kind: FailureKind,
+    step: &'static str,
+    result: Option<anyhow::Result<T>>,
+    retry: impl FnOnce() -> AppState + Send + 'static,
+) -> AppState {
+    let error = match result {
+        Some(Err(e)) => e,
+        Some(Ok(_)) => anyhow!("unexpected result"),
+        None => anyhow!("the task went away without an answer"),
+    };
+    AppState::Failed(Failure::new(step, error).with_kind(kind).with_retry(retry))
+}
+
+/** the failed state for an error coming out of the event pump */
+fn event_failed(e: &anyhow::Error) -> AppState {
+    AppState::Failed(Failure::new("reading events", anyhow!("{e:#}")))
+}
+
+/// the main app struct
+pub struct Marge {
+    pub app_state: Box<AppState>,
+    pub forge: Arc<dyn Forge>,
+    pub remote: Remote,
+    /// who marge works as on the forge, shown in the title
+    pub login: Option<String>,
+    pub cmd: String,
+    pub branch: String,
+    pub pick_branch: bool,
+    pub strategy: Strategy,
+    pub git_options: GitOptions,
+    /// finds the stack and the place in it in a pull title, from the `[sorting]` table
+    pub stack_title: Option<Regex>,
+    /// the workflow on the forge that validates the pushed candidates instead of the command, with
+    /// --workflow
+    pub workflow: Option<String>,
+    pub signer: Signer,
+    pub backports: Backports,
+    pub active_pane: ActivePane,
+    pub last_event: AppEvent,
+    pub log_state: TuiWidgetState,
+    pub log_level: log::LevelFilter,
+    pub log_file: PathBuf,
+    pub notifier: Notifier,
+    /// hold the pipeline after the step that's currently running
+    pub paused: bool,
+    /// the pulls that were open when marge last looked, to tell which ones are new
+    pub seen_pulls: HashSet<u64>,
+    pub pull_stats: PullStatsCache,
+    /// the chain to start with instead of letting the user sort the pulls
+    pub plan: Option<Plan>,
+    /// where to save the chain once the run starts
+    pub save_plan: Option<PathBuf>,
+    /// stop after saving the chain instead of running it
+    pub plan_only: bool,
+    /// the planned chain was rebased and pushed by an earlier run, it only needs merging
+    pub merge_only: bool,
+    /// what's left of the chain, for the next run if this one stops halfway
+    pub session: Session,
+    pub clipboard: Clipboard,
+    /// the pulls merged so far
+    pub merged: Vec<MergeCandidate>,
+    /// where to write the changelog of the merged pulls once the run is done
+    pub changelog: Option<PathBuf>,
+    /// the text shown over the panes, if any
+    pub viewer: Option<Viewer>,
+    /// when the app got into its current state
+    pub state_since: Instant,
+    /// ticks seen so far, they drive the spinner
+    pub ticks: usize,
+    pub app_scroll: PaneScroll,
+    /// how much of the width the app pane gets, in percent
+    pub app_width: u16,
+    /// how long the screen waits before it's drawn again when nothing but the clock changed
+    pub frame_budget: Duration,
+    /// only the active pane is shown
+    pub zoomed: bool,
+    /// ctrl-c was pressed during a step that shouldn't be cut off, the user is asked what to do
+    pub quitting: bool,
+}
+
+impl Marge {
+    pub async fn try_transition(&mut self) -> anyhow::Result<()> {
+        if self.quitting {
+            self.confirm_quit();
+        } else if matches!(self.last_event, AppEvent::Signal) && self.app_state.is_destructive() {
+            // quitting now would leave the branch half rebased or half pushed
+            warn!(
+                "{} is in progress, press y to abort it and clean up, n to go on",
+                self.app_state.name()
+            );
+            self.quitting = true;
+            self.last_event = AppEvent::Tick;
+        }
+        // the letters typed into the jump to a pull are no commands
+        let typing = self.app_state.typing();
+        if let Some(viewer) = &mut self.viewer {
+            if !viewer.handle(&self.last_event) {
+                self.viewer = None;
+            }
+            // keys go to the viewer while it's open, the run goes on underneath
+            if let AppEvent::Input(_) = self.last_event {
+                self.last_event = AppEvent::Tick;
+            }
+        } else if let AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('v'),
+            ..
+        }) = self.last_event
+        {
+            if self.active_pane == ActivePane::List && !typing && self.app_state.has_conflicts() {
+                let rx = show_conflicts();
+                self.viewer = Some(Viewer::new(
+                    "conflicts".to_owned(),
+                    Highlight::Conflicts,
+                    rx,
+                ));
+            }
+        } else if let AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(key @ ('d' | 'c')),
+            ..
+        }) = self.last_event
+        {
+            if self.active_pane == ActivePane::List && !typing {
+                self.show(key);
+            }
+        }
+        if let AppEvent::Input(KeyEvent {
+            code: code @ (KeyCode::PageUp | KeyCode::PageDown),
+            ..
+        }) = self.last_event
+        {
+            if self.active_pane == ActivePane::List {
+                let scroll = &mut self.app_scroll;
+                scroll.offset = if code == KeyCode::PageUp {
+                    scroll.offset.saturating_sub(scroll.height)
+                } else {
+                    // clamped to the content when it's drawn
+                    scroll.offset + scroll.height
+                };
+            }
+        }
+        if let AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('p'),
+            ..
+        }) = self.last_event
+        {
+            if !typing {
+                self.paused = !self.paused;
+                if self.paused {
+                    info!("pausing, marge won't start another step until you press p again");
+                } else {
+                    info!("resuming");
+                }
+            }
+        }
+        if let AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(key @ ('y' | 'Y' | 'c')),
+            ..
+        }) = self.last_event
+        {
+            if self.active_pane == ActivePane::List && !typing {
+                self.yank(key);
+            }
+        }
+        // whatever is running keeps running, its result waits in the channel until we resume.
+        // aborting still works so the user isn't stuck on a failure
+        if self.paused
+            && !matches!(
+                *self.app_state,
+                AppState::Failed(_) | AppState::UndoingRetargets(_) | AppState::Aborting(_)
+            )
+        {
+            return Ok(());
+        }
+
+        if let Some(sorting) = self.app_state.sorting_state() {
+            self.pull_stats.update(&self.forge, &self.remote, sorting);
+        }
+
+        let old_state = std::mem::replace(self.app_state.as_mut(), AppState::Done);
+        let (from, before) = (old_state.name(), std::mem::discriminant(&old_state));
+
+        let _ = std::mem::replace(
+            self.app_state.as_mut(),
             match old_state {
-                AppState::CheckingRepo(rx) => transition_checking(rx, &self.branch).await,
-                AppState::WaitingForCleanRepo => transition_waiting_clean(&self.last_event),
-                AppState::CheckingOutTargetBranch(rx) => transition_checking_out_target(rx).await,
-                AppState::PullingRemote(rx) => transition_pull_remote(rx).await,
+                AppState::OfferingSession(plan) => {
+                    transition_offering_session(
+                        &self.last_event,
+                        &mut self.plan,
+                        &self.session,
+                        plan,
+                    )
+                    .await
+                }
+                AppState::CheckingRepo(rx) => {
+                    transition_checking(
+                        rx,
+                        &self.branch,
+                        self.git_options,
+                        self.pick_branch,
+                        &self.remote,
+                    )
+                    .await
+                }
+                AppState::WaitingForCleanRepo(c) => transition_waiting_clean(&self.last_event, c),
+                AppState::ListingBranches(rx) => {
+                    transition_listing_branches(rx, &self.remote, &self.branch, self.git_options)
+                        .await
+                }
+                AppState::WaitingForBranch(s) => transition_waiting_branch(
+                    &self.active_pane,
+                    &self.last_event,
+                    &mut self.branch,
+                    self.git_options,
+                    s,
+                ),
+                AppState::CheckingOutTargetBranch(rx) => {
+                    transition_checking_out_target(rx, &self.branch, self.git_options).await
+                }
+                AppState::PullingRemote(rx) => transition_pull_remote(self.git_options, rx).await,
                 AppState::GettingPulls => {
-                    transition_getting_pulls(&self.remote, &self.instance).await
+                    transition_getting_pulls(
+                        &self.remote,
+                        &*self.forge,
+                        &mut self.seen_pulls,
+                        self.plan.as_ref(),
+                        self.stack_title.as_ref(),
+                    )
+                    .await
                 }
                 AppState::WaitingForSort(s) => {
                     transition_waiting_sort(&self.active_pane, &self.last_event, s)
                 }
-                AppState::UpdatingCandidate(s) => {
-                    transition_updating_candidate(&self.branch, &self.remote, &self.instance, s)
+                AppState::RefreshingPulls(s) => {
+                    transition_refreshing_pulls(&self.remote, &*self.forge, &mut self.seen_pulls, s)
                         .await
                 }
+                AppState::StartingChain(chain) if self.merge_only => {
+                    start_merging(&self.branch, self.git_options, chain)
+                }
+                AppState::StartingChain(chain) => {
+                    transition_starting_chain(
+                        &self.remote,
+                        &self.branch,
+                        self.strategy,
+                        self.git_options,
+                        self.save_plan.as_deref(),
+                        self.plan_only,
+                        chain,
+                    )
+                    .await
+                }
+                AppState::UpdatingCandidate(s) => {
+                    transition_updating_candidate(
+                        &self.branch,
+                        self.git_options,
+                        &self.remote,
+                        &*self.forge,
+                        s,
+                    )
+                    .await
+                }
                 AppState::CheckingOutCandidate(rx, c) => {
-                    transition_checkout_candidate(&self.branch, rx, c).await
+                    transition_checkout_candidate(
+                        &self.branch,
+                        self.git_options,
+                        &self.signer,
+                        rx,
+                        c,
+                    )
+                    .await
+                }
+                AppState::RebaseCandidate(rx, s) => {
+                    transition_rebasing(&self.branch, self.git_options, &self.signer, rx, s).await
+                }
+                AppState::CheckingForConflicts(rx, s) => {
+                    transition_check_conflicts(&self.branch, self.git_options, &self.signer, rx, s)
+                        .await
+                }
+                AppState::ComparingRebase(rx, s) => transition_comparing_rebase(
+                    &self.cmd,
+                    &self.remote,
+                    &self.branch,
+                    self.git_options,
+                    rx,
+                    s,
+                ),
+                AppState::ComparingBeforePush(rx, s) => transition_comparing_before_push(
+                    &self.remote,
+                    &self.branch,
+                    self.git_options,
+                    rx,
+                    s,
+                ),
+                AppState::PreparingSquash(rx, s) => transition_preparing_squash(rx, s).await,
+                AppState::WaitingForSquashMessage(path, s) => transition_waiting_squash_message(
+                    &self.last_event,
+                    &self.branch,
+                    self.git_options,
+                    &self.signer,
+                    path,
+                    s,
+                ),
+                AppState::Squashing(rx, s) => {
+                    transition_squashing(
+                        &self.cmd,
+                        &self.remote,
+                        &self.branch,
+                        self.git_options,
+                        rx,
+                        s,
+                    )
+                    .await
+                }
+                AppState::UpdatingSubmodules(rx, s) => {
+                    transition_updating_submodules(&self.cmd, &self.branch, self.git_options, rx, s)
+                        .await
+                }
+                AppState::PullingLfs(rx, s) => {
+                    transition_pulling_lfs(&self.cmd, &self.branch, self.git_options, rx, s).await
+                }
+                AppState::WaitingForResolution(c, s) => transition_waiting_resolution(
+                    &self.active_pane,
+                    &self.last_event,
+                    self.git_options,
+                    &self.signer,
+                    c,
+                    s,
+                ),
+                AppState::Validating(rx, s) => {
+                    transition_validate(
+                        &self.cmd,
+                        &self.remote,
+                        &self.branch,
+                        self.strategy,
+                        self.git_options,
+                        rx,
+                        s,
+                    )
+                    .await
+                }
+                AppState::WaitingForFix(s) => transition_fixing(
+                    &self.last_event,
+                    &self.cmd,
+                    &self.remote,
+                    &self.branch,
+                    self.git_options,
+                    s,
+                ),
+                AppState::PushingCandidate(rx, s) => {
+                    transition_pushing(&self.remote, &self.branch, self.git_options, rx, s).await
+                }
+                AppState::PushingValidated(p) => transition_pushing_validated(
+                    &self.cmd,
+                    &self.remote,
+                    &self.branch,
+                    self.git_options,
+                    p,
+                ),
+                AppState::CheckingOutToFix(rx, s) => {
+                    transition_checking_out_to_fix(self.git_options, rx, s).await
+                }
+                AppState::DispatchingWorkflow(s) => {
+                    transition_dispatching_workflow(
+                        &*self.forge,
+                        &self.remote,
+                        self.workflow.as_deref().unwrap_or_default(),
+                        self.git_options,
+                        s,
+                    )
+                    .await
+                }
+                AppState::WaitingForWorkflow(s, at) => {
+                    transition_waiting_for_workflow(
+                        &*self.forge,
+                        &self.remote,
+                        &self.branch,
+                        self.workflow.as_deref().unwrap_or_default(),
+                        self.git_options,
+                        at,
+                        s,
+                    )
+                    .await
+                }
+                AppState::WaitingForChecks(s, at) => {
+                    transition_waiting_for_checks(
+                        &*self.forge,
+                        &self.remote,
+                        self.git_options.confirm,
+                        at,
+                        s,
+                    )
+                    .await
+                }
+                AppState::MergingCandidate(s) => {
+                    transition_merging_candidate(
+                        &*self.forge,
+                        &self.remote,
+                        &self.branch,
+                        &mut self.backports,
+                        &mut self.merged,
+                        self.git_options,
+                        s,
+                    )
+                    .await
+                }
+                AppState::UpdatingTarget(rx, s) => {
+                    transition_updating_target(
+                        &self.remote.name,
+                        &self.branch,
+                        self.git_options,
+                        rx,
+                        s,
+                    )
+                    .await
+                }
+                AppState::CherryPicking(rx, s) => {
+                    transition_cherry_picking(
+                        &self.cmd,
+                        &self.remote,
+                        &self.branch,
+                        self.git_options,
+                        rx,
+                        s,
+                    )
+                    .await
+                }
+                AppState::WaitingForPickResolution(s) => {
+                    transition_waiting_pick_resolution(&self.last_event, self.git_options, s)
+                }
+                AppState::PushingIntegration(rx, s) => {
+                    transition_pushing_integration(
+                        &*self.forge,
+                        &self.remote,
+                        &self.branch,
+                        self.git_options,
+                        rx,
+                        s,
+                    )
+                    .await
+                }
+                AppState::Merging(s) => {
+                    transition_merging(
+                        &*self.forge,
+                        &self.remote,
+                        &self.branch,
+                        &mut self.backports,
+                        &mut self.merged,
+                        self.git_options,
+                        s,
+                    )
+                    .await
+                }
+                AppState::CleaningUp => {
+                    transition_cleaning_up(
+                        &*self.forge,
+                        &self.remote,
+                        &self.backports,
+                        &self.merged,
+                        self.git_options,
+                    )
+                    .await
+                }
+                AppState::VerifyingMerges(rx) => {
+                    transition_verifying_merges(
+                        &self.remote.name,
+                        &mut self.backports,
+                        &mut self.merged,
+                        self.git_options,
+                        rx,
+                    )
+                    .await
+                }
+                AppState::Backporting(rx, s) => {
+                    transition_backporting(&self.remote, &self.backports, self.git_options, rx, s)
+                        .await
+                }
+                AppState::WaitingForBackportResolution(s) => {
+                    transition_waiting_backport_resolution(&self.last_event, self.git_options, s)
+                }
+                AppState::PushingBackport(rx, s) => {
+                    transition_pushing_backport(
+                        &*self.forge,
+                        &self.remote,
+                        &mut self.branch,
+                        &mut self.backports,
+                        self.git_options,
+                        rx,
+                        s,
+                    )
+                    .await
+                }
+                AppState::Done => AppState::Done,
+                AppState::Failed(f) => transition_failed(&self.last_event, self.git_options, f),
+                AppState::UndoingRetargets(f) => {
+                    transition_undoing_retargets(&*self.forge, &self.remote, f).await
+                }
+                AppState::Aborting(rx) => transition_aborting(rx).await,
+                AppState::Aborted => AppState::Aborted,
+                AppState::WaitingForConfirmation(c) => {
+                    transition_waiting_confirmation(&self.last_event, c)
+                }
+                AppState::LoadingChainEdit(waiting) => {
+                    transition_loading_chain_edit(&self.remote, &*self.forge, waiting).await
+                }
+                AppState::RefreshingChain(waiting) => {
+                    transition_refreshing_chain(
+                        &self.remote,
+                        &*self.forge,
+                        &mut self.seen_pulls,
+                        waiting,
+                    )
+                    .await
+                }
+                AppState::EditingChain(s) => {
+                    transition_editing_chain(&self.active_pane, &self.last_event, s)
+                }
+            },
+        );
+
+        if before != std::mem::discriminant(self.app_state.as_ref()) {
+            observer::state_changed(from, &self.app_state);
+        }
+
+        if let Some(left) = self.app_state.chain_left() {
+            self.session.update(&left).await;
+        }
+
+        if let AppState::Done = *self.app_state {
+            if let Some(path) = self.changelog.take().filter(|_| !self.merged.is_empty()) {
+                match tokio::fs::write(&path, changelog::generate(&self.merged)).await {
+                    Ok(()) => info!("wrote the changelog to {}", path.display()),
+                    Err(e) => error!("could not write the changelog to {}: {e}", path.display()),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /** abort the run on y, go on with it on n. another ctrl-c quits right away */
+    fn confirm_quit(&mut self) {
+        let AppEvent::Input(KeyEvent { code, .. }) = self.last_event else {
+            return;
+        };
+        match code {
+            KeyCode::Char('y') => {
+                self.quitting = false;
+                *self.app_state = AppState::Aborting(abort_in_progress(self.git_options));
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.quitting = false;
+                info!("going on with the run");
+            }
+            _ => (),
+        }
+        // the dialog takes all the keys while it's open
+        self.last_event = AppEvent::Tick;
+    }
+
+    /** open the diff (d) or the commits (c) of the selected pull */
+    fn show(&mut self, key: char) {
+        let Some(sorting) = self.app_state.sorting_state() else {
+            return;
+        };
+        let Some(c) = sorting.unsorted.get(sorting.current_index) else {
+            return;
+        };
+        let (what, highlight) = match key {
+            'c' => ("commits", Highlight::Commits),
+            _ => ("diff", Highlight::Diff),
+        };
+        let title = format!("{what} of #{}: {}", c.pull.number, c.pull.head.branch);
+        let rx = show_pull(&self.remote.name, &c.pull, key, self.git_options);
+        self.viewer = Some(Viewer::new(title, highlight, rx));
+    }
+
+    /** copy the selected pull's url (y) or branch (Y) while sorting, or the merged commits (y) or the changelog (c) once done */
+    fn yank(&mut self, key: char) {
+        if let AppState::Done = *self.app_state {
+            match key {
+                'y' if !self.merged.is_empty() => {
+                    let shas = self
+                        .merged
+                        .iter()
+                        .filter_map(|c| c.merged_as.as_deref())
+                        .collect::<Vec<&str>>()
+                        .join("\n");
+                    self.clipboard.copy("the merged commits", shas);
+                }
+                'c' if !self.merged.is_empty() => {
+                    let changelog = changelog::generate(&self.merged);
+                    self.clipboard.copy("the changelog", changelog);
+                }
+                _ => (),
+            }
+            return;
+        }
+        let Some(sorting) = self.app_state.sorting_state() else {
+            return;
+        };
+        if key == 'c' {
+            return;
+        }
+        let Some(c) = sorting.unsorted.get(sorting.current_index) else {
+            return;
+        };
+        let number = c.pull.number;
+        match (key, &c.pull.html_url) {
+            ('y', Some(url)) => self
+                .clipboard
+                .copy(&format!("the url of #{number}"), url.to_string()),
+            ('y', None) => warn!("#{number} has no url"),
+            _ => self.clipboard.copy(
+                &format!("the branch of #{number}"),
+                c.pull.head.branch.clone(),
+            ),
+        }
+    }
+
+    pub async fn try_init(
+        events: Sender<AppEvent>,
+        args: AppArgs,
+        run: RunArgs,
+        plan_only: bool,
+    ) -> anyhow::Result<Marge> {
+        let config = get_config(args, run, plan_only).await?;
+        let timeout = remote_timeout(config.settings.timeouts.remote);
+        let (remote, forge) = if config.run.local.is_empty() {
+            let remote = find_remote(get_remotes().await?, config.args.remote.as_deref())?;
+            let forge =
+                forge::connect(&remote, &config.args.token, &config.settings, timeout).await?;
+            (remote, forge)
+        } else {
+            local_forge(&config).await?
+        };
+        observer::register(Arc::new(DebugLog));
+        let forge: Arc<dyn Forge> = Arc::new(Observed(forge));
+        let branch = match config.run.branch.clone() {
+            Some(branch) => branch,
+            None => default_branch(&*forge, &remote).await,
+        };
+        if config.run.rolling && config.run.strategy == Strategy::CherryPick {
+            return Err(anyhow!(
+                "--rolling merges every pull by itself, it doesn't work with the cherry-pick strategy"
+            ));
+        }
+        if config.run.workflow.is_some() && config.run.strategy == Strategy::CherryPick {
+            return Err(anyhow!(
+                "--workflow validates the pushed pulls, it doesn't work with the cherry-pick strategy"
+            ));
+        }
+        if config.run.review_rebase && config.run.strategy == Strategy::CherryPick {
+            return Err(anyhow!(
+                "--review-rebase reviews the force-pushes, it doesn't work with the cherry-pick strategy"
+            ));
+        }
+        if config.run.parallel && config.run.strategy == Strategy::CherryPick {
+            return Err(anyhow!(
+                "--parallel validates the rebased pulls, it doesn't work with the cherry-pick strategy"
+            ));
+        }
+        forge.check_access(&remote, config.run.read_only).await?;
+        let login = match forge.login(&remote).await {
+            Ok(login) if !login.is_empty() => Some(login),
+            Ok(_) => None,
+            Err(e) => {
+                debug!("could not get the login of the token: {e:#}");
+                None
+            }
+        };
+        if config.run.read_only {
+            info!("read-only: nothing will be retargeted, pushed or merged");
+        }
+        task::init(events.clone());
+        let signer = Signer::try_new(config.run.signing, events).await;
+        let git_options = GitOptions::from_git_config(&config.run, &config.settings).await?;
+        if !git_options.submodules && tokio::fs::try_exists(".gitmodules").await? {
+            info!("repo has submodules, consider running with --submodules");
+        }
+        let notifier = Notifier::new(
+            config.run.notify,
+            config.settings.notifications.webhook,
+            format!("{}/{}", remote.owner, remote.repo),
+            network::http_client(&config.settings.network)?,
+        );
+        let stack_title = match &config.settings.sorting.stack {
+            Some(pattern) => Some(stack_title(pattern)?),
+            None => None,
+        };
+        theme::init(config.settings.layout.colors);
+        hooks::init(config.settings.hooks.clone());
+        audit::init(format!("{}/{}", remote.owner, remote.repo))?;
+        let log_level = config.args.log_level();
+        let plan = match &config.run.plan {
+            Some(path) => Some(Plan::load(path).await?),
+            // the branches are chained in the order they were given
+            None if !config.run.local.is_empty() => Some(Plan::in_order(config.run.local.len())),
+            None => None,
+        };
+        let session = Session::open().await;
+        // a chain that was given or is only planned doesn't need the one from last time
+        let unfinished = match (&plan, config.plan_only) {
+            (None, false) => session.unfinished().await,
+            _ => None,
+        };
+        let log_file = match config.args.log_file {
+            Some(log_file) => log_file,
+            None => default_log_file()?,
+        };
+
+        let log_state = TuiWidgetState::new()
+            .set_default_display_level(log_level)
+            .set_level_for_target("debug", log::LevelFilter::Debug)
+            .set_level_for_target("error", log::LevelFilter::Error)
+            .set_level_for_target("warn", log::LevelFilter::Warn)
+            .set_level_for_target("info", log::LevelFilter::Info);
+
+        Ok(Marge {
+            app_state: Box::new(match unfinished {
+                Some(plan) => AppState::OfferingSession(plan),
+                None => AppState::CheckingRepo(is_repo_clean()),
+            }),
+            remote,
+            forge,
+            login,
+            cmd: config.run.cmd,
+            branch,
+            pick_branch: config.run.pick_branch,
+            strategy: config.run.strategy,
+            git_options,
+            stack_title,
+            workflow: config.run.workflow,
+            signer,
+            backports: Backports {
+                targets: config.run.backport,
+                merge: config.run.backport_merge,
+                ..Default::default()
+            },
+            active_pane: ActivePane::List,
+            last_event: AppEvent::Tick,
+            log_state,
+            log_level,
+            log_file,
+            notifier,
+            paused: false,
+            seen_pulls: HashSet::new(),
+            pull_stats: PullStatsCache::new(),
+            plan,
+            save_plan: config.run.save_plan,
+            plan_only: config.plan_only,
+            merge_only: config.run.merge_only,
+            session,
+            clipboard: Clipboard::default(),
+            merged: vec![],
+            changelog: config.run.changelog,
+            viewer: None,
+            state_since: Instant::now(),
+            ticks: 0,
+            app_scroll: PaneScroll::default(),
+            app_width: config
+                .settings
+                .layout
+                .app_width
+                .clamp(MIN_WIDTH, 100 - MIN_WIDTH),
+            frame_budget: Duration::from_millis(config.settings.layout.frame_ms),
+            zoomed: false,
+            quitting: false,
+        })
+    }
+}
+
+/** the remote named target, the only one if there's no target, or origin if there are several */
+pub fn find_remote(remotes: Vec<Remote>, target: Option<&str>) -> anyhow::Result<Remote> {
+    if let (None, [_]) = (target, remotes.as_slice()) {
+        return Ok(remotes.into_iter().next().expect("there's one remote"));
+    }
+    let target = target.unwrap_or("origin");
+    let names = remotes
+        .iter()
+        .map(|r| r.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    remotes
+        .into_iter()
+        .find(|r| r.name == target)
+        .context(format!(
+            "could not find remote {target}, pick one of {names} with --remote"
+        ))
+}
+
+/** marge's dir in the XDG state dir, ~/.local/state/marge by default */
+pub fn state_dir() -> anyhow::Result<PathBuf> {
+    let state_dir = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").context("could not find the home dir")?)
+            .join(".local/state"),
+    };
+    Ok(state_dir.join("marge"))
+}
+
+/** a fresh log file for this run in the state dir */
+fn default_log_file() -> anyhow::Result<PathBuf> {
+    let timestamp = chrono::Local::now().format("%Y-%m-%dT%H-%M-%S");
+    Ok(state_dir()?.join(format!("{timestamp}.log")))
+}
+
+async fn get_config(args: AppArgs, run: RunArgs, plan_only: bool) -> anyhow::Result<AppConfig> {
+    let settings = Settings::load(args.config.as_deref()).await?;
+    Ok(AppConfig {
+        args,
+        run,
+        plan_only,
+        settings,
+    })
+}
+
+/** ask for a token and store it where --token points, instead of putting it there by hand */
+pub async fn login(token_file: &str) -> anyhow::Result<()> {
+    eprint!("paste a github token with the repo scope: ");
+    let token = tokio::task::spawn_blocking(|| {
+        let mut token = String::new();
+        std::io::stdin().read_line(&mut token).map(|_| token)
+    })
+    .await??;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(anyhow!("no token given"));
+    }
+    save_token(token_file, token).await
+}
+
+/** write the token where get_token finds it, readable only by the user */
+pub async fn save_token(token_file: &str, token: &str) -> anyhow::Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options
+        .open(token_file)
+        .await
+        .context(format!("could not write the token to {token_file}"))?;
+    // the mode only applies to new files, one that was there already keeps its own until now
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let only_me = std::fs::Permissions::from_mode(0o600);
+        file.set_permissions(only_me).await?;
+    }
+    // tokio only hands the write to the OS on flush
+    async {
+        file.write_all(token.as_bytes()).await?;
+        file.flush().await
+    }
+    .await
+    .context(format!("could not write the token to {token_file}"))?;
+    eprintln!("saved the token to {token_file}");
+    Ok(())
+}
+
+pub async fn get_token(file_path: &str) -> anyhow::Result<String> {
+    let contents_bytes = tokio::fs::read(file_path)
+        .await
+        .context("could not read token")?;
+    let contents = std::str::from_utf8(&contents_bytes).context("token is not valid utf8")?;
+    Ok(contents.trim().to_owned())
+}
+
+/** start with the chain an earlier run left unfinished on y, or forget it and start over on n */
+async fn transition_offering_session(
+    last_event: &AppEvent,
+    plan: &mut Option<Plan>,
+    session: &Session,
+    unfinished: Plan,
+) -> AppState {
+    match last_event {
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('y'),
+            ..
+        }) => {
+            info!("resuming the last chain");
+            *plan = Some(unfinished);
+        }
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('n'),
+            ..
+        }) => session.discard().await,
+        AppEvent::Error(e) => return event_failed(e),
+        _ => return AppState::OfferingSession(unfinished),
+    }
+    AppState::CheckingRepo(is_repo_clean())
+}
+
+/** transition from the repo checking state */
+async fn transition_checking(
+    mut rx: Receiver<anyhow::Result<RepoStatus>>,
+    branchname: &str,
+    options: GitOptions,
+    pick_branch: bool,
+    remote: &Remote,
+) -> AppState {
+    if let Some(maybe_clean) = task::finished(&mut rx) {
+        if let Some(Ok(status)) = maybe_clean {
+            return if !status.is_clean() {
+                if let Some(operation) = status.in_progress {
+                    warn!("there's a {} in progress in the repo", operation.name());
                 }
-                AppState::RebaseCandidate(rx, s) => transition_rebasing(&self.cmd, rx, s).await,
-                AppState::CheckingForConflicts(rx, s) => {
-                    transition_check_conflicts(&self.cmd, rx, s).await
+                AppState::WaitingForCleanRepo(CleanState::new(status))
+            } else if pick_branch {
+                AppState::ListingBranches(list_remote_branches(&remote.name, options))
+            } else {
+                AppState::CheckingOutTargetBranch(checkout_target(branchname, options))
+            };
+        }
+        return failed("checking the repo", maybe_clean, || {
+            AppState::CheckingRepo(is_repo_clean())
+        });
+    }
+
+    AppState::CheckingRepo(rx)
+}
+
+/** transition out of the waiting for clean repo state. a aborts the operation in progress. the
+repo is looked at again every few seconds, and marge goes on by itself once it's clean. space
+checks right away */
+fn transition_waiting_clean(last_event: &AppEvent, mut c: CleanState) -> AppState {
+    if let Some(rx) = &mut c.refresh {
+        if let Some(refreshed) = task::finished(rx) {
+            match refreshed {
+                Some(Ok(status)) if status.is_clean() => {
+                    info!("the repo is clean now");
+                    // checked once more the logged way, which goes on like after space
+                    return AppState::CheckingRepo(is_repo_clean());
                 }
-                AppState::WaitingForResolution(s) => {
-                    transition_waiting_resolution(&self.last_event, s)
+                Some(Ok(status)) => c.status = status,
+                Some(Err(e)) => debug!("could not look at the repo again: {e:#}"),
+                None => (),
+            }
+            c.refresh = None;
+            c.at = Instant::now() + CLEAN_REFRESH_DELAY;
+        }
+    } else if Instant::now() >= c.at {
+        c.refresh = Some(refresh_repo_status());
+    }
+    match (last_event, c.status.in_progress) {
+        (
+            AppEvent::Input(KeyEvent {
+                code: KeyCode::Char(' '),
+                ..
+            }),
+            _,
+        ) => AppState::CheckingRepo(is_repo_clean()),
+        (
+            AppEvent::Input(KeyEvent {
+                code: KeyCode::Char('a'),
+                ..
+            }),
+            Some(operation),
+        ) => AppState::CheckingRepo(abort_operation(operation)),
+        (AppEvent::Error(e), _) => event_failed(e),
+        _ => AppState::WaitingForCleanRepo(c),
+    }
+}
+
+async fn transition_listing_branches(
+    mut rx: Receiver<anyhow::Result<Vec<String>>>,
+    remote: &Remote,
+    branchname: &str,
+    options: GitOptions,
+) -> AppState {
+    let remote_name = remote.name.clone();
+    if let Some(maybe_branches) = task::finished(&mut rx) {
+        if let Some(Ok(branches)) = maybe_branches {
+            if branches.is_empty() {
+                let failure = Failure::new(
+                    "listing remote branches",
+                    anyhow!("the remote has no branches"),
+                );
+                return AppState::Failed(failure.with_retry(move || {
+                    AppState::ListingBranches(list_remote_branches(&remote_name, options))
+                }));
+            }
+            let current_index = branches.iter().position(|b| b == branchname).unwrap_or(0);
+            return AppState::WaitingForBranch(BranchPickState {
+                branches,
+                current_index,
+            });
+        }
+        return failed("listing remote branches", maybe_branches, move || {
+            AppState::ListingBranches(list_remote_branches(&remote_name, options))
+        });
+    }
+
+    // still waiting for the branch list...
+    AppState::ListingBranches(rx)
+}
+
+/** let the user move through the remote branches and pick one as the target branch */
+fn transition_waiting_branch(
+    pane: &ActivePane,
+    last_event: &AppEvent,
+    branch: &mut String,
+    options: GitOptions,
+    state: BranchPickState,
+) -> AppState {
+    if pane == &ActivePane::Log {
+        return AppState::WaitingForBranch(state);
+    };
+
+    let BranchPickState {
+        branches,
+        current_index,
+    } = state;
+
+    let current_index = match last_event {
+        AppEvent::Error(e) => return event_failed(e),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Up, ..
+        }) => current_index.checked_sub(1).unwrap_or(branches.len() - 1),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Down,
+            ..
+        }) => (current_index + 1) % branches.len(),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        }) => {
+            branch.clone_from(&branches[current_index]);
+            info!("picked {branch} as the target branch");
+            return AppState::CheckingOutTargetBranch(checkout_target(branch, options));
+        }
+        _ => current_index,
+    };
+
+    AppState::WaitingForBranch(BranchPickState {
+        branches,
+        current_index,
+    })
+}
+
+/** go ahead on y, stop the run on n */
+fn transition_waiting_confirmation(last_event: &AppEvent, c: Confirmation) -> AppState {
+    match last_event {
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('y'),
+            ..
+        }) => (c.proceed)(),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('n'),
+            ..
+        }) => {
+            let failure = Failure::new("confirming", anyhow!("declined: {}", c.prompt));
+            // retrying asks again
+            AppState::Failed(failure.with_retry(move || AppState::WaitingForConfirmation(c)))
+        }
+        AppEvent::Error(e) => event_failed(e),
+        _ => AppState::WaitingForConfirmation(c),
+    }
+}
+
+/** abort the run on q, cleaning up after the failed step, or retry the step on r */
+fn transition_failed(last_event: &AppEvent, options: GitOptions, mut f: Failure) -> AppState {
+    match last_event {
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('u'),
+            ..
+        }) => AppState::UndoingRetargets(f),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('q'),
+            ..
+        }) => AppState::Aborting(abort_in_progress(options)),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char('r'),
+            ..
+        }) => match f.retry.take() {
+            Some(Retry(retry)) => {
+                info!("retrying {}", f.step);
+                retry()
+            }
+            None => AppState::Failed(f),
+        },
+        _ => AppState::Failed(f),
+    }
+}
+
+/** point the pulls this run retargeted back at their old bases, then show the failure again */
+async fn transition_undoing_retargets(forge: &dyn Forge, remote: &Remote, f: Failure) -> AppState {
+    match undo_retargets(forge, remote, audit::run_id()).await {
+        Ok(outcomes) => outcomes.iter().for_each(|o| info!("{o}")),
+        Err(e) => error!("{e:#}"),
+    }
+    AppState::Failed(f)
+}
+
+/** point the pulls a run retargeted back at the bases they had before, the last run's if there's no id.
+returns what happened to each of them */
+pub async fn undo_retargets(
+    forge: &dyn Forge,
+    remote: &Remote,
+    run_id: Option<&str>,
+) -> anyhow::Result<Vec<String>> {
+    let repo = format!("{}/{}", remote.owner, remote.repo);
+    let (run_id, retargets) = audit::retargets(&repo, run_id)?;
+    let mut outcomes = vec![format!(
+        "undoing {} retargets of run {run_id}",
+        retargets.len()
+    )];
+    for (number, base) in retargets {
+        let result = forge.retarget(remote, number, &base).await;
+        match result {
+            Ok(_) => {
+                let action = format!("retargeted #{number} back onto {base}");
+                audit::record(&action);
+                outcomes.push(action);
+            }
+            Err(e) => outcomes.push(format!(
+                "could not retarget #{number} back onto {base}: {e:#}"
+            )),
+        }
+    }
+    Ok(outcomes)
+}
+
+/** the branch the chain goes onto without --branch: the repo's default branch on the forge, or the
+one the remote's HEAD points at */
+async fn default_branch(forge: &dyn Forge, remote: &Remote) -> String {
+    let branch = match forge.default_branch(remote).await {
+        Ok(branch) => Ok(branch),
+        Err(e) => {
+            debug!("could not ask the forge for the default branch: {e:#}");
+            remote_head(&remote.name).await
+        }
+    };
+    match branch {
+        Ok(branch) => {
+            info!("going onto {branch}, the default branch of the repo");
+            branch
+        }
+        Err(e) => {
+            warn!("could not find the default branch, going with main: {e:#}");
+            "main".to_owned()
+        }
+    }
+}
+
+/** the branch the remote's HEAD points at, as the last clone or fetch saw it */
+async fn remote_head(remote: &str) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args([
+            "symbolic-ref",
+            "--short",
+            &format!("refs/remotes/{remote}/HEAD"),
+        ])
+        .logged_output()
+        .await?;
+    let output = check_status("git symbolic-ref", output)?;
+    let head = std::str::from_utf8(&output.stdout)?.trim();
+    Ok(head
+        .strip_prefix(&format!("{remote}/"))
+        .unwrap_or(head)
+        .to_owned())
+}
+
+/** the remote and the stand-in forge for --local. the remote is only pushed to, so it doesn't need to be on a forge */
+async fn local_forge(config: &AppConfig) -> anyhow::Result<(Remote, Arc<dyn Forge>)> {
+    if config.run.strategy == Strategy::CherryPick {
+        return Err(anyhow!(
+            "--local only works with the rebase strategy, there's no forge to open the integration pull on"
+        ));
+    }
+    let name = config.args.remote.clone().unwrap_or("origin".to_owned());
+    let repo = std::env::current_dir()?
+        .file_name()
+        .map_or("repo".to_owned(), |n| n.to_string_lossy().into_owned());
+    let remote = Remote {
+        name,
+        host: String::new(),
+        owner: "local".to_owned(),
+        repo,
+    };
+    let target = match config.run.branch.clone() {
+        Some(branch) => branch,
+        None => remote_head(&remote.name).await.unwrap_or_else(|e| {
+            warn!("could not find the default branch, going with main: {e:#}");
+            "main".to_owned()
+        }),
+    };
+    let forge = Local::new(config.run.local.clone(), target);
+    Ok((remote, Arc::new(forge)))
+}
+
+/** the forge client and the remote the args point at */
+pub async fn connect(args: &AppArgs) -> anyhow::Result<(Arc<dyn Forge>, Remote)> {
+    let (settings, remotes) =
+        futures::future::try_join(Settings::load(args.config.as_deref()), get_remotes()).await?;
+    let remote = find_remote(remotes, args.remote.as_deref())?;
+    let timeout = remote_timeout(settings.timeouts.remote);
+    let forge = forge::connect(&remote, &args.token, &settings, timeout).await?;
+    Ok((forge, remote))
+}
+
+async fn transition_aborting(mut rx: Receiver<anyhow::Result<()>>) -> AppState {
+    if let Some(maybe_aborted) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_aborted);
+        return AppState::Aborted;
+    }
+
+    // still cleaning up...
+    AppState::Aborting(rx)
+}
+
+fn transition_waiting_resolution(
+    pane: &ActivePane,
+    last_event: &AppEvent,
+    options: GitOptions,
+    signer: &Signer,
+    mut c: ConflictState,
+    s: WorkingState,
+) -> AppState {
+    if let Some((rx, resolving)) = &mut c.listing {
+        match task::finished(rx) {
+            Some(Some(Ok(files))) if files.is_empty() && *resolving => {
+                info!("all conflicts resolved, continuing the rebase");
+                return AppState::CheckingForConflicts(has_no_conflicts(options, signer), s);
+            }
+            Some(Some(Ok(files))) => {
+                c.current_index = c.current_index.min(files.len().saturating_sub(1));
+                c.files = files;
+                c.listing = None;
+            }
+            Some(Some(Err(e))) => {
+                error!("{e:#}");
+                c.listing = None;
+            }
+            Some(None) => {
+                error!("the conflicted files could not be listed, press space to check again");
+                c.listing = None;
+            }
+            None => (),
+        }
+    }
+    let code = match last_event {
+        AppEvent::Error(e) => return event_failed(e),
+        AppEvent::Input(KeyEvent { code, .. }) => code,
+        _ => return AppState::WaitingForResolution(c, s),
+    };
+    match code {
+        KeyCode::Char(' ') => {
+            return AppState::CheckingForConflicts(has_no_conflicts(options, signer), s)
+        }
+        KeyCode::Char(key @ ('e' | 'r')) => {
+            return change_chain(*key, AppState::WaitingForResolution(c, s))
+        }
+        _ if pane != &ActivePane::List || c.listing.is_some() => (),
+        KeyCode::Up => {
+            c.current_index = match c.current_index {
+                0 => c.files.len().saturating_sub(1),
+                i => i - 1,
+            }
+        }
+        KeyCode::Down => {
+            c.current_index = (c.current_index + 1) % c.files.len().max(1);
+        }
+        // while rebasing, ours is the base the pull is rebased onto and theirs the pull
+        KeyCode::Char(key @ ('o' | 't')) => {
+            if let Some(file) = c.files.get(c.current_index) {
+                let side = if *key == 'o' { "ours" } else { "theirs" };
+                c.listing = Some((resolve_file(Some((side, file.path.clone()))), true));
+            }
+        }
+        _ => (),
+    }
+    AppState::WaitingForResolution(c, s)
+}
+
+async fn transition_checking_out_target(
+    mut rx: Receiver<anyhow::Result<()>>,
+    branchname: &str,
+    options: GitOptions,
+) -> AppState {
+    if let Some(maybe_clean) = task::finished(&mut rx) {
+        if let Some(Ok(())) = maybe_clean {
+            return AppState::PullingRemote(pull_remote(options));
+        }
+        let branchname = branchname.to_owned();
+        return failed("checking out the target branch", maybe_clean, move || {
+            AppState::CheckingOutTargetBranch(checkout_target(&branchname, options))
+        });
+    }
+
+    // still waiting for the checkout...
+    AppState::CheckingOutTargetBranch(rx)
+}
+
+async fn transition_pull_remote(
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+) -> AppState {
+    if let Some(maybe_clean) = task::finished(&mut rx) {
+        if let Some(Ok(())) = maybe_clean {
+            return AppState::GettingPulls;
+        }
+        return failed("pulling the target branch", maybe_clean, move || {
+            AppState::PullingRemote(pull_remote(options))
+        });
+    }
+
+    // still waiting for the checkout...
+    AppState::PullingRemote(rx)
+}
+
+async fn transition_getting_pulls(
+    remote: &Remote,
+    forge: &dyn Forge,
+    seen_pulls: &mut HashSet<u64>,
+    plan: Option<&Plan>,
+    stack_title: Option<&Regex>,
+) -> AppState {
+    match forge.pulls(remote).await {
+        Ok(pulls) => {
+            seen_pulls.extend(pulls.iter().map(|p| p.number));
+            if let Some(plan) = plan {
+                return match plan.chain(&pulls) {
+                    Ok(chain) => AppState::StartingChain(chain),
+                    Err(e) => AppState::Failed(
+                        Failure::new("loading the plan", e).with_retry(|| AppState::GettingPulls),
+                    ),
+                };
+            }
+            let mut candidates = pulls.into_iter().map(MergeCandidate::new).collect();
+            let chain = match stack_title {
+                Some(re) => title_stacks(re, &mut candidates),
+                None => vec![],
+            };
+
+            AppState::WaitingForSort(SortingState::new(candidates, chain))
+        }
+        Err(e) => AppState::Failed(
+            Failure::new("getting the pulls", e).with_retry(|| AppState::GettingPulls),
+        ),
+    }
+}
+
+fn transition_waiting_sort(
+    pane: &ActivePane,
+    last_event: &AppEvent,
+    state: SortingState,
+) -> AppState {
+    if let AppEvent::Error(e) = last_event {
+        return event_failed(e);
+    };
+
+    let AppEvent::Input(key @ KeyEvent { code, .. }) = last_event else {
+        return AppState::WaitingForSort(state);
+    };
+
+    if pane == &ActivePane::Log {
+        return AppState::WaitingForSort(state);
+    };
+
+    // the options popup and the jump to a pull get all the keys while they're open
+    if state.options.is_some() || state.jump.is_some() {
+        return AppState::WaitingForSort(sort_key(key, state));
+    }
+
+    // get the pulls again, keeping the chain built so far
+    if let KeyCode::Char('r') = code {
+        return AppState::RefreshingPulls(state);
+    }
+
+    // continue to next step
+    if let KeyCode::Char(' ') = code {
+        return AppState::StartingChain(state.merge_chain);
+    }
+
+    AppState::WaitingForSort(sort_key(key, state))
+}
+
+/** save the chain if the user wants to reuse it, then start working on its first pull */
+async fn transition_starting_chain(
+    remote: &Remote,
+    branch: &str,
+    strategy: Strategy,
+    options: GitOptions,
+    save_plan: Option<&Path>,
+    plan_only: bool,
+    mut chain: Vec<MergeCandidate>,
+) -> AppState {
+    if chain.is_empty() {
+        return AppState::Done;
+    }
+    if let Some(path) = save_plan {
+        match Plan::from_chain(&chain).save(path).await {
+            Ok(()) => info!("saved the chain to {}", path.display()),
+            Err(e) if plan_only => return AppState::Failed(Failure::new("saving the plan", e)),
+            Err(e) => error!("{e:#}"),
+        }
+    }
+    if plan_only {
+        return AppState::Done;
+    }
+    let current_checkout = chain.remove(0);
+    let mut s = WorkingState {
+        current_checkout,
+        next: chain,
+        done: vec![],
+    };
+    match strategy {
+        Strategy::Rebase => update_candidate(options.confirm, branch, s),
+        Strategy::CherryPick => {
+            s.current_checkout.start();
+            AppState::CherryPicking(pick_candidate(remote, branch, options, &s), s)
+        }
+    }
+}
+
+/** merge a chain an earlier run already rebased and pushed, without touching the branches */
+fn start_merging(branch: &str, options: GitOptions, mut chain: Vec<MergeCandidate>) -> AppState {
+    if chain.is_empty() {
+        return AppState::Done;
+    }
+    // a pull that isn't on its predecessor anymore would be merged into the wrong branch
+    let mut base = branch.to_owned();
+    for c in &chain {
+        if c.pull.base.branch != base {
+            let e = anyhow!(
+                "#{} is based on {}, not on {base}. run the chain again without --merge-only",
+                c.pull.number,
+                c.pull.base.branch
+            );
+            return AppState::Failed(Failure::new("checking the chain", e));
+        }
+        c.pull.head.branch.clone_into(&mut base);
+    }
+    info!(
+        "merging the {} pulls an earlier run rebased and pushed",
+        chain.len()
+    );
+    for c in &mut chain {
+        c.new_head = Some(c.pull.head.sha.clone());
+        c.set(Status::Pushed);
+    }
+    merge_next(options, chain)
+}
+
+/** bring the sorting state up to date with the open pulls: closed ones drop out, new ones are added to the unsorted pulls */
+async fn transition_refreshing_pulls(
+    remote: &Remote,
+    forge: &dyn Forge,
+    seen_pulls: &mut HashSet<u64>,
+    state: SortingState,
+) -> AppState {
+    let pulls = match forge.pulls(remote).await {
+        Ok(pulls) => pulls,
+        Err(e) => {
+            error!("could not refresh the pulls: {e:#}");
+            return AppState::WaitingForSort(state);
+        }
+    };
+    seen_pulls.extend(pulls.iter().map(|p| p.number));
+    let mut fresh = pulls
+        .into_iter()
+        .map(|p| (p.number, p))
+        .collect::<HashMap<u64, Pull>>();
+
+    let SortingState {
+        unsorted,
+        merge_chain,
+        order,
+        marked,
+        ..
+    } = state;
+    let mut refresh = |c: MergeCandidate| match fresh.remove(&c.pull.number) {
+        Some(pull) => Some(MergeCandidate {
+            original_head: pull.head.sha.clone(),
+            original_base: pull.base.branch.clone(),
+            pull,
+            ..c
+        }),
+        None => {
+            info!("#{} isn't open anymore, dropping it", c.pull.number);
+            None
+        }
+    };
+    let merge_chain = merge_chain.into_iter().filter_map(&mut refresh).collect();
+    let mut unsorted = unsorted
+        .into_iter()
+        .filter_map(&mut refresh)
+        .collect::<Vec<MergeCandidate>>();
+
+    let mut new = fresh
+        .into_values()
+        .map(MergeCandidate::new)
+        .collect::<Vec<MergeCandidate>>();
+    new.sort_by_key(|c| c.pull.number);
+    for c in &new {
+        info!("#{} is new", c.pull.number);
+    }
+    unsorted.append(&mut new);
+    order.sort(&mut unsorted);
+
+    AppState::WaitingForSort(SortingState {
+        unsorted,
+        current_index: 0,
+        merge_chain,
+        order,
+        auto: None,
+        marked,
+        options: None,
+        moving: None,
+        jump: None,
+    })
+}
+
+/// how many pulls page up and page down move the selection by
+const SORT_PAGE: isize = 10;
+
+/** move through the pulls and build the chain. used for the initial sort and for editing the chain mid-run */
+fn sort_key(key: &KeyEvent, mut state: SortingState) -> SortingState {
+    let code = &key.code;
+    if let Some(mut jump) = state.jump.take() {
+        match jump.handle(&AppEvent::Input(*key)) {
+            Edit::Submitted(typed) => state.jump_to(&typed),
+            Edit::Cancelled => (),
+            _ => state.jump = Some(jump),
+        }
+        return state;
+    }
+    if let Some(row) = state.options {
+        state.option_key(code, row);
+        return state;
+    }
+    let auto = state.auto.take();
+    let moving = state.moving.take();
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    match code {
+        KeyCode::Up if shift => state.move_chained(moving, true),
+        KeyCode::Down if shift => state.move_chained(moving, false),
+        KeyCode::Up | KeyCode::Char('k') => state.select_prev(),
+        KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+        KeyCode::PageUp => state.select_by(-SORT_PAGE),
+        KeyCode::PageDown => state.select_by(SORT_PAGE),
+        KeyCode::Home | KeyCode::Char('g') => state.select_first(),
+        KeyCode::End | KeyCode::Char('G') => state.select_last(),
+        KeyCode::Char('x') => state.toggle_mark(),
+        KeyCode::Enter if !state.marked.is_empty() => state.chain_marked(),
+        KeyCode::Enter => state.chain_selected(),
+        KeyCode::Char('s') => state.toggle_squash(),
+        KeyCode::Char('O') if !state.unsorted.is_empty() => state.options = Some(0),
+        KeyCode::Char('o') => state.reorder(),
+        KeyCode::Char('#') => state.jump = Some(Input::new()),
+        KeyCode::Char('a') => state.chain_all(auto),
+        KeyCode::Esc => state.unchain_last(),
+        _ => (),
+    }
+    state
+}
+
+/** fetch a base set in the options, since unlike the target branch it wasn't pulled at the start.
+returns the ref to rebase onto: the remote's copy, as the local branch may be behind or have gone
+its own way. with --local the local branch is the only copy */
+async fn fetch_base(remote: &Remote, onto: &str, options: GitOptions) -> anyhow::Result<String> {
+    if options.local {
+        return Ok(onto.to_owned());
+    }
+    let output = output_remote(
+        Command::new("git").args(["fetch", &remote.name, onto]),
+        options.remote_timeout,
+    )
+    .await?;
+    check_status(&format!("git fetch {} {onto}", remote.name), output)?;
+    Ok(format!("{}/{onto}", remote.name))
+}
+
+/** update the current candidate to point at the previous candidates head, or the base set in its options, then start checking it out. */
+async fn transition_updating_candidate(
+    branch: &str,
+    options: GitOptions,
+    remote: &Remote,
+    forge: &dyn Forge,
+    s: WorkingState,
+) -> AppState {
+    let base = s.base(branch);
+    let WorkingState {
+        mut current_checkout,
+        next,
+        done,
+    } = s;
+
+    let result = match retarget_candidate(
+        remote,
+        forge,
+        &current_checkout,
+        &base,
+        options.read_only,
+    )
+    .await
+    {
+        Ok(()) if current_checkout.onto.is_some() && base != branch => {
+            fetch_base(remote, &base, options).await.map(Some)
+        }
+        result => result.map(|()| None),
+    };
+    current_checkout.onto_ref = match result {
+        Ok(onto_ref) => onto_ref,
+        Err(e) => {
+            let failure = Failure::new("retargeting the candidate", e);
+            return AppState::Failed(failure.with_retry(move || {
+                AppState::UpdatingCandidate(WorkingState {
+                    current_checkout,
+                    next,
+                    done,
+                })
+            }));
+        }
+    };
+    let rx = checkout_branch(&current_checkout.pull.head.branch, options);
+
+    AppState::CheckingOutCandidate(
+        rx,
+        WorkingState {
+            current_checkout,
+            next,
+            done,
+        },
+    )
+}
+
+async fn transition_checkout_candidate(
+    branch: &str,
+    options: GitOptions,
+    signer: &Signer,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    // 0. update pull to point at prev
+    // 1. checkout candidate head (next[0])
+    // 2. rebase on base
+    // 3. conflicts? wait for space -> goto 3
+    // 4. solved? force push -> gh should show no conflicts
+    let (next_base, base_ref) = (s.base(branch), s.base_ref(branch));
+    let WorkingState {
+        current_checkout,
+        next,
+        done,
+    } = s;
+
+    if let Some(maybe_checked_out) = task::finished(&mut rx) {
+        if let Some(Ok(())) = maybe_checked_out {
+            let env = hooks::env(&current_checkout, &next_base);
+            let rx_reb = rebase_branch(&base_ref, options, signer, env);
+            let new_s = WorkingState {
+                current_checkout,
+                next,
+                done,
+            };
+            return AppState::RebaseCandidate(rx_reb, new_s);
+        }
+        let s = WorkingState {
+            current_checkout,
+            next,
+            done,
+        };
+        return failed("checking out the candidate", maybe_checked_out, move || {
+            let rx = checkout_branch(&s.current_checkout.pull.head.branch, options);
+            AppState::CheckingOutCandidate(rx, s)
+        });
+    }
+
+    AppState::CheckingOutCandidate(
+        rx,
+        WorkingState {
+            current_checkout,
+            next,
+            done,
+        },
+    )
+}
+
+async fn transition_rebasing(
+    branch: &str,
+    options: GitOptions,
+    signer: &Signer,
+    mut rx: Receiver<anyhow::Result<bool>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_rebased) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_rebased);
+        if let Some(Ok(done)) = maybe_rebased {
+            return if done {
+                rebased(branch, s)
+            } else {
+                if options.rerere {
+                    info!("the rebase stopped, trying to continue with what rerere resolved");
                 }
-                AppState::Validating(rx, s) => transition_validate(rx, s).await,
-                AppState::WaitingForFix(s) => transition_fixing(&self.last_event, &self.cmd, s),
-                AppState::PushingCandidate(rx, s) => transition_pushing(rx, s).await,
-                AppState::Merging(s) => transition_merging(&self.instance, &self.remote, s).await,
-                AppState::Done => AppState::Done,
-                AppState::Failed => AppState::Failed,
+                let rx = has_no_conflicts(options, signer);
+                AppState::CheckingForConflicts(rx, s)
+            };
+        }
+        let (base, base_ref, signer) = (s.base(branch), s.base_ref(branch), signer.clone());
+        return failed("rebasing the candidate", maybe_rebased, move || {
+            let env = hooks::env(&s.current_checkout, &base);
+            AppState::RebaseCandidate(rebase_branch(&base_ref, options, &signer, env), s)
+        });
+    }
+
+    // still waiting for the rebase...
+    AppState::RebaseCandidate(rx, s)
+}
+
+async fn transition_check_conflicts(
+    branch: &str,
+    options: GitOptions,
+    signer: &Signer,
+    mut rx: Receiver<anyhow::Result<bool>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_conflicts_state) = task::finished(&mut rx) {
+        if let Some(Ok(no_conflicts)) = maybe_conflicts_state {
+            return if no_conflicts {
+                rebased(branch, s)
+            } else {
+                AppState::WaitingForResolution(ConflictState::new(), s)
+            };
+        }
+        let signer = signer.clone();
+        return failed("continuing the rebase", maybe_conflicts_state, move || {
+            AppState::CheckingForConflicts(has_no_conflicts(options, &signer), s)
+        });
+    }
+
+    AppState::CheckingForConflicts(rx, s)
+}
+
+/** compare the freshly rebased candidate with what it was before */
+fn rebased(branch: &str, mut s: WorkingState) -> AppState {
+    s.current_checkout.set(Status::Rebased);
+    hooks::spawn(
+        Hook::PostRebase,
+        hooks::env(&s.current_checkout, &s.base(branch)),
+    );
+    let rx = range_diff(&s.current_checkout.original_head);
+    AppState::ComparingRebase(rx, s)
+}
+
+/** `git range-diff` of the commits up to the old head and the ones up to HEAD, from where they
+split off. the commits of the pulls before it show up too, rebased along with it */
+fn range_diff(old_head: &str) -> Receiver<anyhow::Result<String>> {
+    let (tx, rx) = task::channel("range-diff");
+    let range = format!("{old_head}...HEAD");
+    tokio::spawn(async move {
+        let result = async {
+            let output = Command::new("git")
+                .args(["range-diff", "--no-color", &range])
+                .logged_output()
+                .await?;
+            let output = check_status(&format!("git range-diff {range}"), output)?;
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        .await;
+        tx.send(result).await;
+    });
+
+    rx
+}
+
+/** keep the range-diff with the candidate, then go on as if there was none. a commit marked with !
+changed in more than its base */
+fn transition_comparing_rebase(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<String>>,
+    mut s: WorkingState,
+) -> AppState {
+    let Some(maybe_diff) = task::finished(&mut rx) else {
+        return AppState::ComparingRebase(rx, s);
+    };
+    let number = s.current_checkout.pull.number;
+    match maybe_diff {
+        Some(Ok(diff)) => {
+            let changed = changed_commits(&diff);
+            if changed > 0 {
+                warn!(
+                    "{changed} commits of #{number} changed in the rebase, see the range-diff in \
+                     the pull tab"
+                );
+            } else {
+                info!("#{number} has the same changes after the rebase");
+            }
+            s.current_checkout.range_diff = Some(diff);
+        }
+        Some(Err(e)) => warn!("could not compare #{number} before and after the rebase: {e:#}"),
+        None => warn!("could not compare #{number} before and after the rebase"),
+    }
+    compared(cmd, remote, branch, options, s)
+}
+
+/** the commits of a range-diff that are there on both sides but differ */
+pub fn changed_commits(range_diff: &str) -> usize {
+    range_diff
+        .lines()
+        .filter(|line| line.split_whitespace().nth(2) == Some("!"))
+        .count()
+}
+
+/** squash the rebased candidate if it's marked for it, otherwise validate it */
+fn compared(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    if s.current_checkout.squash {
+        let rx = prepare_squash_message(&s.current_checkout);
+        AppState::PreparingSquash(rx, s)
+    } else {
+        start_validation(cmd, remote, branch, options, s)
+    }
+}
+
+/** validate the current candidate, updating its submodules first if needed */
+fn start_validation(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    if options.parallel {
+        return validate_in_background(cmd, branch, options, s);
+    }
+    if options.workflow && !s.current_checkout.skip_validation {
+        return push_for_workflow(remote, branch, options, s);
+    }
+    if s.current_checkout.skip_validation {
+        info!(
+            "not validating #{}, it's set to skip that",
+            s.current_checkout.pull.number
+        );
+        let (tx, rx) = task::channel("validation");
+        tx.try_send(Ok(true));
+        return AppState::Validating(rx, s);
+    }
+    validate_checkout(cmd, branch, options, s)
+}
+
+/** bring the submodules and the LFS objects of the checkout up to date if asked to, then
+validate it */
+fn validate_checkout(cmd: &str, branch: &str, options: GitOptions, s: WorkingState) -> AppState {
+    if options.submodules {
+        AppState::UpdatingSubmodules(update_submodules(options), s)
+    } else {
+        pull_lfs_or_validate(cmd, branch, options, s)
+    }
+}
+
+/** leave the rebased candidate to be validated in a worktree and go on with the next one. the
+pushes wait for the validations once the whole chain is rebased */
+fn validate_in_background(
+    cmd: &str,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    let number = s.current_checkout.pull.number;
+    let rx = if s.current_checkout.skip_validation {
+        info!("not validating #{number}, it's set to skip that");
+        let (tx, rx) = task::channel("validation");
+        tx.try_send(Ok(true));
+        rx
+    } else {
+        validate_in_worktree(cmd, branch, options, &s)
+    };
+    worktree::track(number, rx);
+    let WorkingState {
+        current_checkout,
+        mut next,
+        mut done,
+    } = s;
+    done.push(current_checkout);
+    if next.is_empty() {
+        return AppState::PushingValidated(ParallelPush::new(done));
+    }
+    let current_checkout = next.remove(0);
+    let new_s = WorkingState {
+        current_checkout,
+        next,
+        done,
+    };
+    update_candidate(options.confirm, branch, new_s)
+}
+
+/** push the candidates in order once their validations in the worktrees pass. one that fails is
+checked out to be fixed, and the rest of the chain is rebased onto the fix */
+fn transition_pushing_validated(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut p: ParallelPush,
+) -> AppState {
+    if p.current == p.done.len() {
+        return if options.local {
+            info!(
+                "pushed all {} branches, there are no pulls to merge",
+                p.done.len()
+            );
+            AppState::Done
+        } else {
+            merge_next(options, p.done)
+        };
+    }
+    let s = p.working_state();
+    let env = hooks::env(&s.current_checkout, &s.base(branch));
+    let head = s.current_checkout.pull.head.branch.clone();
+    let number = s.current_checkout.pull.number;
+
+    if let Some(rx) = &mut p.push {
+        let Some(maybe_pushed) = task::finished(rx) else {
+            // still waiting for the push...
+            return AppState::PushingValidated(p);
+        };
+        debug!("{:?}", maybe_pushed);
+        if let Some(Ok(pushed)) = maybe_pushed {
+            let c = &mut p.done[p.current];
+            c.set(Status::Pushed);
+            c.new_head = Some(pushed);
+            p.current += 1;
+            p.push = None;
+            return AppState::PushingValidated(p);
+        }
+        p.done[p.current].set(Status::Failed);
+        let remote = remote.name.clone();
+        return failed("pushing the candidate", maybe_pushed, move || {
+            p.push = Some(push_validated(&remote, &head, options, env));
+            AppState::PushingValidated(p)
+        });
+    }
+
+    let validation = match p.validation.take().or_else(|| worktree::take(number)) {
+        Some(rx) => p.validation.insert(rx),
+        // nothing ran for it in this run, e.g. after a retry
+        None => p
+            .validation
+            .insert(validate_in_worktree(cmd, branch, options, &s)),
+    };
+    let Some(maybe_validated) = task::finished(validation) else {
+        // still waiting for the validation...
+        return AppState::PushingValidated(p);
+    };
+    debug!("{:?}", maybe_validated);
+    p.validation = None;
+    match maybe_validated {
+        Some(Ok(true)) => {
+            info!("#{number} passed its validation, pushing it");
+            p.done[p.current].retries = 0;
+            p.push = Some(push_validated(&remote.name, &head, options, env));
+            AppState::PushingValidated(p)
+        }
+        Some(Ok(false)) if retry_validation(options, &mut p.done[p.current]) => {
+            // there's no validation left, so the next transition starts one
+            AppState::PushingValidated(p)
+        }
+        Some(Ok(false)) => {
+            warn!("#{number} failed its validation, checking it out to fix it");
+            let mut s = s;
+            s.current_checkout.set(Status::Failed);
+            AppState::CheckingOutToFix(checkout_branch(&head, options), s)
+        }
+        maybe_validated => failed_as(
+            FailureKind::Validation,
+            "validating the candidate",
+            maybe_validated,
+            move || AppState::PushingValidated(p),
+        ),
+    }
+}
+
+async fn transition_checking_out_to_fix(
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_checked_out) = task::finished(&mut rx) {
+        if let Some(Ok(())) = maybe_checked_out {
+            return AppState::WaitingForFix(s);
+        }
+        let head = s.current_checkout.pull.head.branch.clone();
+        return failed("checking out the candidate", maybe_checked_out, move || {
+            AppState::CheckingOutToFix(checkout_branch(&head, options), s)
+        });
+    }
+
+    // still waiting for the checkout...
+    AppState::CheckingOutToFix(rx, s)
+}
+
+fn pull_lfs_or_validate(cmd: &str, branch: &str, options: GitOptions, s: WorkingState) -> AppState {
+    if options.no_lfs {
+        AppState::PullingLfs(pull_lfs(options), s)
+    } else {
+        AppState::Validating(validate(cmd, branch, &s), s)
+    }
+}
+
+async fn transition_updating_submodules(
+    cmd: &str,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_updated) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_updated);
+        if let Some(Ok(())) = maybe_updated {
+            return pull_lfs_or_validate(cmd, branch, options, s);
+        }
+        return failed("updating submodules", maybe_updated, move || {
+            AppState::UpdatingSubmodules(update_submodules(options), s)
+        });
+    }
+
+    // still waiting for the submodules...
+    AppState::UpdatingSubmodules(rx, s)
+}
+
+async fn transition_pulling_lfs(
+    cmd: &str,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_pulled) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_pulled);
+        if let Some(Ok(())) = maybe_pulled {
+            return AppState::Validating(validate(cmd, branch, &s), s);
+        }
+        return failed("pulling lfs objects", maybe_pulled, move || {
+            AppState::PullingLfs(pull_lfs(options), s)
+        });
+    }
+
+    // still waiting for lfs...
+    AppState::PullingLfs(rx, s)
+}
+
+async fn transition_preparing_squash(
+    mut rx: Receiver<anyhow::Result<PathBuf>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_path) = task::finished(&mut rx) {
+        if let Some(Ok(path)) = maybe_path {
+            info!("edit {} to change the squash message", path.display());
+            return AppState::WaitingForSquashMessage(path, s);
+        }
+        return failed("preparing the squash message", maybe_path, || {
+            AppState::PreparingSquash(prepare_squash_message(&s.current_checkout), s)
+        });
+    }
+
+    AppState::PreparingSquash(rx, s)
+}
+
+fn transition_waiting_squash_message(
+    last_event: &AppEvent,
+    branch: &str,
+    options: GitOptions,
+    signer: &Signer,
+    path: PathBuf,
+    s: WorkingState,
+) -> AppState {
+    match last_event {
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(' '),
+            ..
+        }) => AppState::Squashing(squash_onto(&s.base_ref(branch), &path, options, signer), s),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(key @ ('e' | 'r')),
+            ..
+        }) => change_chain(*key, AppState::WaitingForSquashMessage(path, s)),
+        AppEvent::Error(e) => event_failed(e),
+        _ => AppState::WaitingForSquashMessage(path, s),
+    }
+}
+
+async fn transition_squashing(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_squashed) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_squashed);
+        if let Some(Ok(())) = maybe_squashed {
+            return start_validation(cmd, remote, branch, options, s);
+        }
+        // the reset is idempotent, so squashing can start over from a fresh message
+        return failed("squashing the candidate", maybe_squashed, || {
+            AppState::PreparingSquash(prepare_squash_message(&s.current_checkout), s)
+        });
+    }
+
+    // still waiting for the squash...
+    AppState::Squashing(rx, s)
+}
+
+async fn transition_validate(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    strategy: Strategy,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<bool>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_validated) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_validated);
+        if let Some(Ok(is_validated)) = maybe_validated {
+            if is_validated {
+                let mut s = s;
+                s.current_checkout.retries = 0;
+                return match strategy {
+                    Strategy::Rebase if options.review_rebase => {
+                        let rx = range_diff(&s.current_checkout.original_head);
+                        AppState::ComparingBeforePush(rx, s)
+                    }
+                    Strategy::Rebase => {
+                        let prompt =
+                            format!("press y to force-push #{}", s.current_checkout.pull.number);
+                        let env = hooks::env(&s.current_checkout, &s.base(branch));
+                        let remote_name = remote.name.clone();
+                        ask_first(options.confirm, prompt, move || {
+                            AppState::PushingCandidate(
+                                push_candidate(&remote_name, options, env),
+                                s,
+                            )
+                        })
+                    }
+                    Strategy::CherryPick => next_integration_step(remote, branch, options, s),
+                };
+            }
+            let mut s = s;
+            if retry_validation(options, &mut s.current_checkout) {
+                return AppState::Validating(validate(cmd, branch, &s), s);
+            }
+            return AppState::WaitingForFix(s);
+        }
+        let cmd = cmd.to_owned();
+        let branch = branch.to_owned();
+        return failed_as(
+            FailureKind::Validation,
+            "validating the candidate",
+            maybe_validated,
+            move || AppState::Validating(validate(&cmd, &branch, &s), s),
+        );
+    }
+
+    // still waiting for validation...
+    AppState::Validating(rx, s)
+}
+
+/** show the range-diff of the validated candidate and push it once the user is fine with it. a
+range-diff that can't be made stops the run, since it's what the push is waiting on */
+fn transition_comparing_before_push(
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<String>>,
+    mut s: WorkingState,
+) -> AppState {
+    let Some(maybe_diff) = task::finished(&mut rx) else {
+        return AppState::ComparingBeforePush(rx, s);
+    };
+    let Some(Ok(diff)) = maybe_diff else {
+        return failed(
+            "comparing the candidate before pushing it",
+            maybe_diff,
+            move || {
+                let rx = range_diff(&s.current_checkout.original_head);
+                AppState::ComparingBeforePush(rx, s)
             },
         );
+    };
+    let prompt = format!(
+        "range-diff of #{} against its old head:\n\n{}\n\npress y to force-push it",
+        s.current_checkout.pull.number,
+        diff.trim_end()
+    );
+    s.current_checkout.range_diff = Some(diff);
+    let env = hooks::env(&s.current_checkout, &s.base(branch));
+    let remote_name = remote.name.clone();
+    AppState::WaitingForConfirmation(Confirmation {
+        prompt,
+        proceed: Box::new(move || {
+            AppState::PushingCandidate(push_candidate(&remote_name, options, env), s)
+        }),
+    })
+}
+
+/** whether the failed validation of the candidate is run again by itself, to get past flaky tests.
+counts the attempt, or starts over for the next fix if there are none left */
+fn retry_validation(options: GitOptions, c: &mut MergeCandidate) -> bool {
+    if c.retries >= options.validate_retries {
+        c.retries = 0;
+        return false;
+    }
+    c.retries += 1;
+    warn!(
+        "#{} failed its validation, running it again ({}/{})",
+        c.pull.number, c.retries, options.validate_retries
+    );
+    true
+}
+
+async fn transition_pushing(
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<String>>,
+    mut s: WorkingState,
+) -> AppState {
+    if let Some(maybe_rebased) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_rebased);
+        if let Some(Ok(head)) = maybe_rebased {
+            s.current_checkout.set(Status::Pushed);
+            s.current_checkout.new_head = Some(head);
+            if options.workflow && !s.current_checkout.skip_validation {
+                return AppState::DispatchingWorkflow(s);
+            }
+            return pushed(branch, options, s);
+        }
+        s.current_checkout.set(Status::Failed);
+        let env = hooks::env(&s.current_checkout, &s.base(branch));
+        let remote_name = remote.name.clone();
+        return failed("pushing the candidate", maybe_rebased, move || {
+            AppState::PushingCandidate(push_candidate(&remote_name, options, env), s)
+        });
+    }
+
+    // still waiting for the push...
+    AppState::PushingCandidate(rx, s)
+}
+
+/** go on with the chain once the current candidate is pushed and validated */
+fn pushed(branch: &str, options: GitOptions, s: WorkingState) -> AppState {
+    if options.rolling {
+        info!(
+            "waiting for the checks of #{} before merging it",
+            s.current_checkout.pull.number
+        );
+        // give the forge a moment to notice the push and start the checks
+        return AppState::WaitingForChecks(s, Instant::now() + CHECKS_POLL_DELAY);
+    }
+    let mut done = s.done;
+    done.push(s.current_checkout);
+    let mut next = s.next;
 
-        Ok(())
+    if next.is_empty() && options.local {
+        info!(
+            "pushed all {} branches, there are no pulls to merge",
+            done.len()
+        );
+        AppState::Done
+    } else if next.is_empty() {
+        merge_next(options, done)
+    } else {
+        let current_checkout = next.remove(0);
+        let new_s = WorkingState {
+            current_checkout,
+            next,
+            done,
+        };
+        update_candidate(options.confirm, branch, new_s)
     }
+}
 
-    pub async fn try_init() -> anyhow::Result<Marge> {
-        let (config, remotes) = futures::future::try_join(get_config(), get_remotes()).await?;
-        let instance = Octocrab::builder().personal_token(config.token).build()?;
-        let remote = find_remote(remotes, &config.args.remote)?;
+/** push the rebased candidate so the workflow can validate it, asking first if needed */
+fn push_for_workflow(
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    let prompt = format!(
+        "press y to force-push #{} and validate it",
+        s.current_checkout.pull.number
+    );
+    let env = hooks::env(&s.current_checkout, &s.base(branch));
+    let remote_name = remote.name.clone();
+    ask_first(options.confirm, prompt, move || {
+        AppState::PushingCandidate(push_candidate(&remote_name, options, env), s)
+    })
+}
 
-        let log_state = TuiWidgetState::new()
-            .set_default_display_level(log::LevelFilter::Info)
-            .set_level_for_target("debug", log::LevelFilter::Debug)
-            .set_level_for_target("error", log::LevelFilter::Error)
-            .set_level_for_target("warn", log::LevelFilter::Warn)
-            .set_level_for_target("info", log::LevelFilter::Info);
+/// how long to wait between looking at the workflow run validating a candidate
+const WORKFLOW_POLL_DELAY: Duration = Duration::from_secs(10);
 
-        Ok(Marge {
-            app_state: Box::new(AppState::CheckingRepo(is_repo_clean())),
-            remote,
-            instance,
-            cmd: config.args.cmd,
-            branch: config.args.branch,
-            active_pane: ActivePane::List,
-            last_event: AppEvent::Tick,
-            log_state,
-        })
+/** run the workflow on the pushed candidate */
+async fn transition_dispatching_workflow(
+    forge: &dyn Forge,
+    remote: &Remote,
+    workflow: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    let c = &s.current_checkout;
+    if options.read_only {
+        info!(
+            "read-only: would run the workflow {workflow} on #{} to validate it",
+            c.pull.number
+        );
+        return AppState::WaitingForWorkflow(s, Instant::now());
+    }
+    info!("running the workflow {workflow} on #{}", c.pull.number);
+    match forge
+        .dispatch_workflow(remote, workflow, &c.pull.head.branch)
+        .await
+    {
+        // the run takes a moment to show up
+        Ok(()) => AppState::WaitingForWorkflow(s, Instant::now() + WORKFLOW_POLL_DELAY),
+        Err(e) => AppState::Failed(
+            Failure::new("dispatching the workflow", e)
+                .with_retry(move || AppState::DispatchingWorkflow(s)),
+        ),
     }
 }
 
-fn find_remote(mut remotes: Vec<Remote>, target: &str) -> anyhow::Result<Remote> {
-    let default_remote = remotes.pop().expect("should have a remote");
-    remotes
-        .into_iter()
-        .find(|r| r.name == target)
-        .or_else(|| {
-            if default_remote.name == target {
-                Some(default_remote)
-            } else {
-                None
+/** go on with the chain once the workflow run on the pushed candidate passes, or wait for a fix
+if it fails */
+async fn transition_waiting_for_workflow(
+    forge: &dyn Forge,
+    remote: &Remote,
+    branch: &str,
+    workflow: &str,
+    options: GitOptions,
+    at: Instant,
+    s: WorkingState,
+) -> AppState {
+    if Instant::now() < at {
+        return AppState::WaitingForWorkflow(s, at);
+    }
+    if options.read_only {
+        return pushed(branch, options, s);
+    }
+    let c = &s.current_checkout;
+    let number = c.pull.number;
+    let sha = c.new_head.as_deref().unwrap_or(&c.pull.head.sha);
+    match forge.workflow_run(remote, workflow, sha).await {
+        Ok(Checks::Passing) => {
+            info!("#{number} passed the workflow {workflow}");
+            let mut s = s;
+            s.current_checkout.retries = 0;
+            pushed(branch, options, s)
+        }
+        Ok(Checks::Pending | Checks::Missing) => {
+            AppState::WaitingForWorkflow(s, Instant::now() + WORKFLOW_POLL_DELAY)
+        }
+        Ok(Checks::Failing) => {
+            warn!("#{number} failed the workflow {workflow}");
+            let mut s = s;
+            if retry_validation(options, &mut s.current_checkout) {
+                return AppState::DispatchingWorkflow(s);
             }
-        })
-        .context(format!("could not find remote {target}"))
+            AppState::WaitingForFix(s)
+        }
+        Err(e) => {
+            warn!("could not get the run of {workflow} on #{number}: {e:#}");
+            AppState::WaitingForWorkflow(s, Instant::now() + WORKFLOW_POLL_DELAY)
+        }
+    }
 }
 
-async fn get_config() -> anyhow::Result<AppConfig> {
-    let args = AppArgs::try_parse()?;
-    let token = get_token(&args.token).await?;
-    Ok(AppConfig { args, token })
+/// how often to look at the checks of a candidate waiting to be merged with --rolling
+const CHECKS_POLL_DELAY: Duration = Duration::from_secs(15);
+
+/** merge the pushed candidate once its checks pass, asking first if needed */
+async fn transition_waiting_for_checks(
+    forge: &dyn Forge,
+    remote: &Remote,
+    confirm: bool,
+    at: Instant,
+    s: WorkingState,
+) -> AppState {
+    if Instant::now() < at {
+        return AppState::WaitingForChecks(s, at);
+    }
+    let c = &s.current_checkout;
+    let number = c.pull.number;
+    let sha = c.new_head.as_deref().unwrap_or(&c.pull.head.sha);
+    match forge.checks(remote, sha).await {
+        Ok(Checks::Passing | Checks::Missing) => {
+            let prompt = format!("press y to merge #{number}");
+            ask_first(confirm, prompt, move || AppState::MergingCandidate(s))
+        }
+        Ok(Checks::Pending) => AppState::WaitingForChecks(s, Instant::now() + CHECKS_POLL_DELAY),
+        Ok(Checks::Failing) => {
+            let e = anyhow!("the checks of #{number} failed");
+            AppState::Failed(
+                Failure::new("waiting for the checks", e)
+                    .with_retry(move || AppState::WaitingForChecks(s, Instant::now())),
+            )
+        }
+        Err(e) => {
+            warn!("could not get the checks of #{number}: {e:#}");
+            AppState::WaitingForChecks(s, Instant::now() + CHECKS_POLL_DELAY)
+        }
+    }
 }
 
-async fn get_token(file_path: &str) -> anyhow::Result<String> {
-    let contents_bytes = tokio::fs::read(file_path)
-        .await
-        .context("could not read token")?;
-    let contents = std::str::from_utf8(&contents_bytes).context("token is not valid utf8")?;
-    Ok(contents.trim().to_owned())
+/** merge the current candidate by itself and move on to the next one, which goes onto the target */
+async fn transition_merging_candidate(
+    forge: &dyn Forge,
+    remote: &Remote,
+    branch: &str,
+    backports: &mut Backports,
+    merged: &mut Vec<MergeCandidate>,
+    options: GitOptions,
+    mut s: WorkingState,
+) -> AppState {
+    let number = s.current_checkout.pull.number;
+    if options.read_only {
+        info!("read-only: would merge #{number}");
+        s.current_checkout.set(Status::Skipped);
+    } else {
+        match forge
+            .merge(remote, number, s.current_checkout.merge_method)
+            .await
+        {
+            Err(e) => {
+                let e = e.context(format!("could not merge pull {number}"));
+                s.current_checkout.set(Status::Failed);
+                return AppState::Failed(
+                    Failure::new("merging", e).with_retry(move || AppState::MergingCandidate(s)),
+                );
+            }
+            Ok(sha) => {
+                audit::record(&format!(
+                    "merged #{number} into {} as {}",
+                    s.current_checkout.pull.base.branch,
+                    sha.as_deref().unwrap_or("?")
+                ));
+                s.current_checkout.merged_as = sha;
+                s.current_checkout.set(Status::Merged);
+                let base = s.current_checkout.pull.base.branch.clone();
+                hooks::spawn(Hook::PostMerge, hooks::env(&s.current_checkout, &base));
+            }
+        }
+    }
+
+    // the merged candidates leave the chain, so the next one's base is the target again
+    let WorkingState {
+        current_checkout,
+        mut next,
+        done,
+    } = s;
+    merged.push(current_checkout);
+    if next.is_empty() {
+        if backports.sources.is_empty() {
+            branch.clone_into(&mut backports.base);
+            backports.sources.clone_from(merged);
+        }
+        return AppState::CleaningUp;
+    }
+    let s = WorkingState {
+        current_checkout: next.remove(0),
+        next,
+        done,
+    };
+    AppState::UpdatingTarget(fast_forward(&remote.name, branch, options), s)
 }
 
-/** transition from the repo checking state */
-async fn transition_checking(mut rx: Receiver<anyhow::Result<bool>>, branchname: &str) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let task = rx.recv().fuse();
+/** fast-forward the local branch to the remote's, without checking it out */
+fn fast_forward(remote: &str, branch: &str, options: GitOptions) -> Receiver<anyhow::Result<()>> {
+    let (tx, rx) = task::channel("fast-forward");
+    let (remote, branch) = (remote.to_owned(), branch.to_owned());
+    tokio::spawn(async move {
+        let result = async {
+            let refspec = format!("{branch}:{branch}");
+            let fetch = output_remote(
+                Command::new("git").args(["fetch", &remote, &refspec]),
+                options.remote_timeout,
+            )
+            .await?;
+            check_status(&format!("git fetch {remote} {refspec}"), fetch)?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        tx.send(result.context(format!("could not update {branch}")))
+            .await;
+    });
+
+    rx
+}
+
+async fn transition_updating_target(
+    remote: &str,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_updated) = task::finished(&mut rx) {
+        if let Some(Ok(())) = maybe_updated {
+            return update_candidate(options.confirm, branch, s);
+        }
+        let (remote, branch) = (remote.to_owned(), branch.to_owned());
+        return failed("updating the target", maybe_updated, move || {
+            AppState::UpdatingTarget(fast_forward(&remote, &branch, options), s)
+        });
+    }
 
-        futures::pin_mut!(ready, task);
+    // still fetching...
+    AppState::UpdatingTarget(rx, s)
+}
 
-        futures::select! {
-            maybe_clean = task => {
-                if let Some(Ok(is_clean)) = maybe_clean {
-                    return if is_clean {AppState::CheckingOutTargetBranch(checkout_branch(branchname))} else {AppState::WaitingForCleanRepo}
-                }
-                return AppState::Failed;
-            },
-            () = ready => (),
+/** cherry-pick the current candidate's commits onto the integration branch. return true if done */
+fn pick_candidate(
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: &WorkingState,
+) -> Receiver<anyhow::Result<bool>> {
+    let integration = integration_branch(branch);
+    let start = if s.done.is_empty() {
+        format!("{}/{branch}", remote.name)
+    } else {
+        integration.clone()
+    };
+    let pull = &s.current_checkout.pull;
+    cherry_pick_onto(
+        &remote.name,
+        &integration,
+        &start,
+        &format!(
+            "{0}/{1}..{0}/{2}",
+            remote.name, pull.base.branch, pull.head.branch
+        ),
+        options,
+    )
+}
+
+async fn transition_cherry_picking(
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<bool>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_picked) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_picked);
+        if let Some(Ok(done)) = maybe_picked {
+            return if done {
+                let mut s = s;
+                s.current_checkout.set(Status::Rebased);
+                start_validation(cmd, remote, branch, options, s)
+            } else {
+                AppState::WaitingForPickResolution(s)
+            };
+        }
+        let (remote, branch) = (remote.clone(), branch.to_owned());
+        return failed("cherry-picking the candidate", maybe_picked, move || {
+            AppState::CherryPicking(pick_candidate(&remote, &branch, options, &s), s)
+        });
+    }
+
+    // still waiting for the cherry-pick...
+    AppState::CherryPicking(rx, s)
+}
+
+fn transition_waiting_pick_resolution(
+    last_event: &AppEvent,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    match last_event {
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(' '),
+            ..
+        }) => AppState::CherryPicking(continue_cherry_pick(options), s),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(key @ ('e' | 'r')),
+            ..
+        }) => change_chain(*key, AppState::WaitingForPickResolution(s)),
+        AppEvent::Error(e) => event_failed(e),
+        _ => AppState::WaitingForPickResolution(s),
+    }
+}
+
+/** pick the next candidate onto the integration branch, or push it if all are picked */
+fn next_integration_step(
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    let WorkingState {
+        current_checkout,
+        mut next,
+        mut done,
+    } = s;
+    done.push(current_checkout);
+
+    if next.is_empty() {
+        let current_checkout = done.pop().expect("just pushed");
+        let s = WorkingState {
+            current_checkout,
+            next,
+            done,
         };
+        let rx = push_new_branch(&remote.name, &integration_branch(branch), options);
+        return AppState::PushingIntegration(rx, s);
     }
 
-    AppState::CheckingRepo(rx)
+    let mut s = WorkingState {
+        current_checkout: next.remove(0),
+        next,
+        done,
+    };
+    s.current_checkout.start();
+    AppState::CherryPicking(pick_candidate(remote, branch, options, &s), s)
+}
+
+/** open a pull for the pushed integration branch and merge it */
+async fn transition_pushing_integration(
+    forge: &dyn Forge,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: WorkingState,
+) -> AppState {
+    if let Some(maybe_pushed) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_pushed);
+        if let Some(Ok(())) = maybe_pushed {
+            return open_integration_pull(forge, remote, branch, options, s).await;
+        }
+        let (remote, head) = (remote.name.clone(), integration_branch(branch));
+        return failed("pushing the integration branch", maybe_pushed, move || {
+            AppState::PushingIntegration(push_new_branch(&remote, &head, options), s)
+        });
+    }
+
+    // still waiting for the push...
+    AppState::PushingIntegration(rx, s)
+}
+
+async fn open_integration_pull(
+    forge: &dyn Forge,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
+    let WorkingState {
+        current_checkout,
+        mut done,
+        ..
+    } = s;
+    done.push(current_checkout);
+    let numbers = done
+        .iter()
+        .map(|c| format!("#{}", c.pull.number))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let body = done
+        .iter()
+        .map(|c| {
+            format!(
+                "* #{} {}",
+                c.pull.number,
+                c.pull.title.as_deref().unwrap_or("<untitled>")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    if options.read_only {
+        info!("read-only: would open a pull to integrate {numbers} into {branch} and merge it");
+        return AppState::Done;
+    }
+
+    let result = forge
+        .open_pull(
+            remote,
+            format!("Integrate {numbers} into {branch}"),
+            format!("Cherry-picked by marge from:\n\n{body}"),
+            &integration_branch(branch),
+            branch,
+        )
+        .await;
+
+    match result {
+        Ok(pull) => {
+            audit::record(&format!(
+                "opened integration pull #{} for {}",
+                pull.number,
+                integration_branch(branch)
+            ));
+            info!("{numbers} are superseded by it and can be closed after the merge");
+            merge_next(options, vec![MergeCandidate::new(pull)])
+        }
+        Err(e) => {
+            let current_checkout = done.pop().expect("just pushed");
+            let s = WorkingState {
+                current_checkout,
+                next: vec![],
+                done,
+            };
+            let (remote, head) = (remote.name.clone(), integration_branch(branch));
+            let failure = Failure::new("opening the integration pull", e);
+            // pushing again is a no-op and leads right back to opening the pull
+            AppState::Failed(failure.with_retry(move || {
+                AppState::PushingIntegration(push_new_branch(&remote, &head, options), s)
+            }))
+        }
+    }
 }
 
-/** transition out of the waiting for clean repo state */
-fn transition_waiting_clean(last_event: &AppEvent) -> AppState {
+fn transition_fixing(
+    last_event: &AppEvent,
+    cmd: &str,
+    remote: &Remote,
+    branch: &str,
+    options: GitOptions,
+    s: WorkingState,
+) -> AppState {
     match last_event {
         AppEvent::Input(KeyEvent {
             code: KeyCode::Char(' '),
             ..
-        }) => AppState::CheckingRepo(is_repo_clean()),
-        AppEvent::Error(_) => AppState::Failed,
-        _ => AppState::WaitingForCleanRepo,
+        }) if options.workflow => push_for_workflow(remote, branch, options, s),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(' '),
+            ..
+        }) => validate_checkout(cmd, branch, options, s),
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(key @ ('e' | 'r')),
+            ..
+        }) => change_chain(*key, AppState::WaitingForFix(s)),
+        AppEvent::Error(e) => event_failed(e),
+        _ => AppState::WaitingForFix(s),
+    }
+}
+
+/** e edits the rest of the chain, r appends newly opened pulls to it */
+fn change_chain(key: char, waiting: AppState) -> AppState {
+    if key == 'e' {
+        AppState::LoadingChainEdit(Box::new(waiting))
+    } else {
+        AppState::RefreshingChain(Box::new(waiting))
+    }
+}
+
+/** append the pulls opened since marge last looked to the end of the chain */
+async fn transition_refreshing_chain(
+    remote: &Remote,
+    forge: &dyn Forge,
+    seen_pulls: &mut HashSet<u64>,
+    mut waiting: Box<AppState>,
+) -> AppState {
+    let Some(s) = waiting.waiting_working_state() else {
+        return *waiting;
+    };
+    match forge.pulls(remote).await {
+        Ok(pulls) => {
+            let mut new = pulls
+                .into_iter()
+                .filter(|p| seen_pulls.insert(p.number))
+                .map(MergeCandidate::new)
+                .collect::<Vec<MergeCandidate>>();
+            // oldest first, that's the order a stack gets opened in
+            new.sort_by_key(|c| c.pull.number);
+            if new.is_empty() {
+                info!("no new pulls");
+            }
+            for c in &new {
+                info!("appending #{} to the chain", c.pull.number);
+            }
+            s.next.append(&mut new);
+        }
+        Err(e) => error!("could not refresh the pulls: {e:#}"),
+    }
+
+    *waiting
+}
+
+/** offer the open pulls that aren't part of the run yet next to the rest of the chain */
+async fn transition_loading_chain_edit(
+    remote: &Remote,
+    forge: &dyn Forge,
+    mut waiting: Box<AppState>,
+) -> AppState {
+    let Some(s) = waiting.waiting_working_state() else {
+        return *waiting;
+    };
+    let pulls = match forge.pulls(remote).await {
+        Ok(pulls) => pulls,
+        Err(e) => {
+            error!("could not get the pulls to edit the chain: {e:#}");
+            return *waiting;
+        }
+    };
+    let in_run = s
+        .done
+        .iter()
+        .chain([&s.current_checkout])
+        .chain(&s.next)
+        .map(|c| c.pull.number)
+        .collect::<HashSet<u64>>();
+    let unsorted = pulls
+        .into_iter()
+        .filter(|p| !in_run.contains(&p.number))
+        .map(MergeCandidate::new)
+        .collect();
+    let merge_chain = std::mem::take(&mut s.next);
+
+    AppState::EditingChain(ChainEditState {
+        sorting: SortingState::new(unsorted, merge_chain),
+        waiting,
+    })
+}
+
+/** sort the rest of the chain like at the start, space goes back to where marge was waiting */
+fn transition_editing_chain(
+    pane: &ActivePane,
+    last_event: &AppEvent,
+    state: ChainEditState,
+) -> AppState {
+    let ChainEditState {
+        sorting,
+        mut waiting,
+    } = state;
+
+    let key = match last_event {
+        AppEvent::Error(e) => return event_failed(e),
+        AppEvent::Input(key) if pane == &ActivePane::List => key,
+        _ => return AppState::EditingChain(ChainEditState { sorting, waiting }),
+    };
+
+    let code = &key.code;
+    if let (KeyCode::Char(' ' | 'r'), None, None) = (code, sorting.options, &sorting.jump) {
+        if let Some(s) = waiting.waiting_working_state() {
+            info!("{} pulls left in the chain", sorting.merge_chain.len());
+            s.next = sorting.merge_chain;
+        }
+        // r starts editing over with fresh pulls
+        return match code {
+            KeyCode::Char('r') => AppState::LoadingChainEdit(waiting),
+            _ => *waiting,
+        };
+    }
+
+    AppState::EditingChain(ChainEditState {
+        sorting: sort_key(key, sorting),
+        waiting,
+    })
+}
+
+async fn transition_merging(
+    forge: &dyn Forge,
+    remote: &Remote,
+    branch: &str,
+    backports: &mut Backports,
+    merged: &mut Vec<MergeCandidate>,
+    options: GitOptions,
+    s: MergingState,
+) -> AppState {
+    if s.wait_until.is_some_and(|at| Instant::now() < at) {
+        return AppState::Merging(s);
+    }
+    let MergingState {
+        mut to_merge,
+        attempts,
+        polls,
+        ..
+    } = s;
+
+    // only the original chain gets backported, not the backport pulls themselves
+    if backports.sources.is_empty() {
+        branch.clone_into(&mut backports.base);
+        backports.sources.clone_from(&to_merge);
+    }
+
+    // one pull per transition, so pausing and confirming work between merges
+    let number = to_merge[0].pull.number;
+    if !options.read_only {
+        match ready_to_merge(forge, remote, merged.last(), &mut to_merge[0]).await {
+            Ok(None) => {}
+            Ok(Some(waiting)) if polls < MAX_POLLS => {
+                debug!("waiting for {waiting} before merging #{number}");
+                return AppState::Merging(MergingState {
+                    to_merge,
+                    attempts,
+                    polls: polls + 1,
+                    wait_until: Some(Instant::now() + POLL_DELAY),
+                });
+            }
+            Ok(Some(waiting)) => warn!("gave up waiting for {waiting}, merging #{number} anyway"),
+            Err(e) => warn!("could not check on #{number} before merging it: {e:#}"),
+        }
+    }
+    let title = &to_merge[0].pull.title;
+    info!(
+        "merging pull {number} with {}",
+        title.as_deref().unwrap_or("<untitled>")
+    );
+    if options.read_only {
+        info!("read-only: would merge #{number}");
+        to_merge[0].set(Status::Skipped);
+    } else {
+        match forge.merge(remote, number, to_merge[0].merge_method).await {
+            Err(e) if forge::merge_blocked(&e) && attempts < options.merging.retries => {
+                warn!(
+                    "#{number} can't be merged yet ({e:#}), trying again ({}/{})",
+                    attempts + 1,
+                    options.merging.retries
+                );
+                if let Err(e) = bring_up_to_date(forge, remote, &mut to_merge[0]).await {
+                    warn!("could not update #{number} with its base: {e:#}");
+                }
+                return AppState::Merging(MergingState {
+                    to_merge,
+                    attempts: attempts + 1,
+                    polls,
+                    wait_until: Some(Instant::now() + MERGE_RETRY_DELAY),
+                });
+            }
+            Err(e) => {
+                let e = e.context(format!("could not merge pull {number}"));
+                to_merge[0].set(Status::Failed);
+                return AppState::Failed(Failure::new("merging", e).with_retry(move || {
+                    AppState::Merging(MergingState::new(to_merge, options.merging))
+                }));
+            }
+            Ok(sha) => {
+                audit::record(&format!(
+                    "merged #{number} into {} as {}",
+                    to_merge[0].pull.base.branch,
+                    sha.as_deref().unwrap_or("?")
+                ));
+                to_merge[0].merged_as = sha;
+                to_merge[0].set(Status::Merged);
+                let base = to_merge[0].pull.base.branch.clone();
+                hooks::spawn(Hook::PostMerge, hooks::env(&to_merge[0], &base));
+            }
+        }
     }
-}
 
-fn transition_waiting_resolution(last_event: &AppEvent, s: WorkingState) -> AppState {
-    match last_event {
-        AppEvent::Input(KeyEvent {
-            code: KeyCode::Char(' '),
-            ..
-        }) => AppState::CheckingForConflicts(has_no_conflicts(), s),
-        AppEvent::Error(_) => AppState::Failed,
-        _ => AppState::WaitingForResolution(s),
+    merged.push(to_merge.remove(0));
+    if to_merge.is_empty() {
+        AppState::CleaningUp
+    } else {
+        merge_next(options, to_merge)
     }
 }
 
-async fn transition_checking_out_target(mut rx: Receiver<anyhow::Result<()>>) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let nxt = rx.recv().fuse();
-
-        futures::pin_mut!(ready, nxt);
+/// how long to give the forge after a merge it turned away before trying again
+const MERGE_RETRY_DELAY: Duration = Duration::from_secs(5);
 
-        futures::select! {
-            maybe_clean = nxt => {
-                if let Some(Ok(())) = maybe_clean {
-                    return AppState::PullingRemote(pull_remote());
-                }
-                return AppState::Failed;
+/// how often to look at the previous merge and the next pull before merging anyway, and how
+/// long to wait in between
+const MAX_POLLS: u32 = 30;
+const POLL_DELAY: Duration = Duration::from_secs(2);
 
-            },
-            () = ready => (),
-        };
+/** what the forge still has to get done before candidate can be merged, if anything: the
+previous merge landing on its base, and the forge working out whether candidate merges cleanly */
+async fn ready_to_merge(
+    forge: &dyn Forge,
+    remote: &Remote,
+    previous: Option<&MergeCandidate>,
+    candidate: &mut MergeCandidate,
+) -> anyhow::Result<Option<&'static str>> {
+    if let Some(previous) = previous.filter(|p| p.status == Status::Merged) {
+        if !forge.pull(remote, previous.pull.number).await?.merged {
+            return Ok(Some("the previous merge to land"));
+        }
     }
-
-    // still waiting for the checkout...
-    AppState::CheckingOutTargetBranch(rx)
+    candidate.pull = forge.pull(remote, candidate.pull.number).await?;
+    Ok(candidate
+        .pull
+        .mergeable
+        .is_none()
+        .then_some("the forge to check whether it merges cleanly"))
 }
 
-async fn transition_pull_remote(mut rx: Receiver<anyhow::Result<()>>) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let nxt = rx.recv().fuse();
-
-        futures::pin_mut!(ready, nxt);
+/** get the pull as the forge sees it now, and have the forge update it with its base if it fell behind */
+async fn bring_up_to_date(
+    forge: &dyn Forge,
+    remote: &Remote,
+    candidate: &mut MergeCandidate,
+) -> anyhow::Result<()> {
+    candidate.pull = forge.pull(remote, candidate.pull.number).await?;
+    let Pull {
+        number, base, head, ..
+    } = &candidate.pull;
+    if forge.behind_by(remote, &base.branch, &head.branch).await? > 0 {
+        info!("updating #{number} with {}", base.branch);
+        forge.update_branch(remote, *number).await?;
+        audit::record(&format!("updated #{number} with {}", base.branch));
+    }
+    Ok(())
+}
 
-        futures::select! {
-            maybe_clean = nxt => {
-                if let Some(Ok(())) = maybe_clean {
-                    return AppState::GettingPulls;
+/** once the chain is merged, point the open pulls that were based on one of its branches at
+the branch it was merged into, so none are left on a branch that's gone. then prune the
+remote-tracking refs of the deleted branches and make sure the merges landed */
+async fn transition_cleaning_up(
+    forge: &dyn Forge,
+    remote: &Remote,
+    backports: &Backports,
+    merged: &[MergeCandidate],
+    options: GitOptions,
+) -> AppState {
+    let heads = backports
+        .sources
+        .iter()
+        .filter(|c| c.status == Status::Merged)
+        .map(|c| c.pull.head.branch.as_str())
+        .collect::<HashSet<&str>>();
+    let target = &backports.base;
+    match forge.pulls(remote).await {
+        Ok(pulls) => {
+            for Pull { number, base, .. } in pulls {
+                if !heads.contains(base.branch.as_str()) {
+                    continue;
                 }
-                return AppState::Failed;
-            },
-            () = ready => (),
-        };
+                if options.read_only {
+                    info!(
+                        "read-only: would retarget #{number} from {} onto {target}",
+                        base.branch
+                    );
+                    continue;
+                }
+                match forge.retarget(remote, number, target).await {
+                    Ok(()) => {
+                        info!(
+                            "retargeted #{number} from the merged {} onto {target}",
+                            base.branch
+                        );
+                        audit::record(&format!(
+                            "retargeted #{number} from {} onto {target}",
+                            base.branch
+                        ));
+                    }
+                    Err(e) => warn!("could not retarget #{number} onto {target}: {e:#}"),
+                }
+            }
+        }
+        Err(e) => warn!("could not look for pulls left on the merged branches: {e:#}"),
     }
-
-    // still waiting for the checkout...
-    AppState::PullingRemote(rx)
+    match output_remote(
+        Command::new("git").args(["remote", "prune", &remote.name]),
+        options.remote_timeout,
+    )
+    .await
+    {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => warn!(
+            "could not prune the refs of deleted branches: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => warn!("could not prune the refs of deleted branches: {e:#}"),
+    }
+    AppState::VerifyingMerges(verify_merges(&remote.name, merged, options))
 }
 
-async fn transition_getting_pulls(remote: &Remote, instance: &Octocrab) -> AppState {
-    if let Ok(pulls) = get_pulls(remote, instance).await {
-        let candidates = pulls.into_iter().map(MergeCandidate::new).collect();
-
-        AppState::WaitingForSort(SortingState {
-            unsorted: candidates,
-            current_index: 0,
-            merge_chain: vec![],
+/** fetch the branches the candidates were merged into and check that their merge commits are
+on them. the ones that were checked already or whose merge commit isn't known are left out */
+fn verify_merges(
+    remote: &str,
+    merged: &[MergeCandidate],
+    options: GitOptions,
+) -> Receiver<anyhow::Result<Vec<(u64, bool)>>> {
+    let (tx, rx) = task::channel("verifying merges");
+    let remote = remote.to_owned();
+    let checks = merged
+        .iter()
+        .filter(|c| c.landed.is_none())
+        .filter_map(|c| {
+            Some((
+                c.pull.number,
+                c.merged_as.clone()?,
+                c.pull.base.branch.clone(),
+            ))
         })
-    } else {
-        AppState::Failed
-    }
+        .collect::<Vec<(u64, String, String)>>();
+    tokio::spawn(async move {
+        let result = async {
+            if checks.is_empty() {
+                return Ok(vec![]);
+            }
+            let fetch = output_remote(
+                Command::new("git").args(["fetch", &remote]),
+                options.remote_timeout,
+            )
+            .await?;
+            check_status(&format!("git fetch {remote}"), fetch)?;
+            let mut landed = vec![];
+            for (number, sha, base) in checks {
+                let status = Command::new("git")
+                    .args([
+                        "merge-base",
+                        "--is-ancestor",
+                        &sha,
+                        &format!("{remote}/{base}"),
+                    ])
+                    .logged_status()
+                    .await?;
+                // 1 means it's not an ancestor, anything else that git couldn't tell, like
+                // when the base was deleted right after
+                match status.code() {
+                    Some(0) => landed.push((number, true)),
+                    Some(1) => landed.push((number, false)),
+                    _ => debug!("could not tell whether #{number} landed on {base}"),
+                }
+            }
+            Ok::<_, anyhow::Error>(landed)
+        }
+        .await;
+        tx.send(result.context("could not verify the merges")).await;
+    });
+
+    rx
 }
 
-fn transition_waiting_sort(
-    pane: &ActivePane,
-    last_event: &AppEvent,
-    state: SortingState,
+async fn transition_verifying_merges(
+    remote: &str,
+    backports: &mut Backports,
+    merged: &mut [MergeCandidate],
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<Vec<(u64, bool)>>>,
 ) -> AppState {
-    if let AppEvent::Error(_) = last_event {
-        return AppState::Failed;
+    let Some(maybe_landed) = task::finished(&mut rx) else {
+        return AppState::VerifyingMerges(rx);
     };
+    match maybe_landed {
+        Some(Ok(landed)) => {
+            for (number, on_base) in landed {
+                let Some(c) = merged.iter_mut().find(|c| c.pull.number == number) else {
+                    continue;
+                };
+                if on_base {
+                    debug!("#{number} landed on {}", c.pull.base.branch);
+                } else {
+                    warn!(
+                        "#{number} was merged as {}, but that's not on {}",
+                        c.merged_as.as_deref().unwrap_or("?"),
+                        c.pull.base.branch
+                    );
+                }
+                c.landed = Some(on_base);
+            }
+        }
+        Some(Err(e)) => warn!("{e:#}"),
+        None => warn!("the merges could not be verified"),
+    }
+    start_next_backport(remote, backports, options)
+}
 
-    let AppEvent::Input(KeyEvent { code, .. }) = last_event else {
-        return AppState::WaitingForSort(state);
-    };
+/** start cherry-picking the merged chain onto the next backport target, if there is one */
+fn start_next_backport(remote: &str, backports: &mut Backports, options: GitOptions) -> AppState {
+    if backports.targets.is_empty() {
+        return AppState::Done;
+    }
+    let target = backports.targets.remove(0);
+    let mut next = backports.sources.clone();
+    let current = next.remove(0);
+    info!("backporting {} pulls onto {target}", next.len() + 1);
 
-    if pane == &ActivePane::Log {
-        return AppState::WaitingForSort(state);
+    let s = BackportState {
+        target,
+        current,
+        next,
+        done: vec![],
+        opened: vec![],
     };
+    let rx = cherry_pick_current(remote, backports, options, &s);
+    AppState::Backporting(rx, s)
+}
 
-    let SortingState {
-        current_index,
-        mut unsorted,
-        mut merge_chain,
-    } = state;
+fn cherry_pick_current(
+    remote: &str,
+    backports: &Backports,
+    options: GitOptions,
+    s: &BackportState,
+) -> Receiver<anyhow::Result<bool>> {
+    let range_start = s
+        .done
+        .last()
+        .map(|c| c.pull.head.branch.clone())
+        .unwrap_or(backports.base.clone());
+    // each backport starts from the target, the range only holds the pull's own commits
+    cherry_pick_onto(
+        remote,
+        &s.branch_name(),
+        &format!("{remote}/{}", s.target),
+        &format!("{range_start}..{}", s.current.pull.head.branch),
+        options,
+    )
+}
 
-    let new_state = match code {
-        // select prev candidate
-        KeyCode::Up => {
-            let current_index = if current_index == 0 {
-                unsorted.len() - 1
-            } else {
-                current_index - 1
-            };
-            SortingState {
-                unsorted,
-                current_index,
-                merge_chain,
-            }
-        }
-        // select next candidate
-        KeyCode::Down => {
-            let current_index = if current_index == unsorted.len() - 1 {
-                0
+async fn transition_backporting(
+    remote: &Remote,
+    backports: &Backports,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<bool>>,
+    s: BackportState,
+) -> AppState {
+    if let Some(maybe_picked) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_picked);
+        if let Some(Ok(done)) = maybe_picked {
+            return if done {
+                let rx = push_new_branch(&remote.name, &s.branch_name(), options);
+                AppState::PushingBackport(rx, s)
             } else {
-                current_index + 1
+                AppState::WaitingForBackportResolution(s)
             };
-            SortingState {
-                unsorted,
-                current_index,
-                merge_chain,
-            }
-        }
-        // put current selected candidate at top of merge_chain
-        KeyCode::Enter => {
-            if unsorted.is_empty() {
-                SortingState {
-                    current_index: 0,
-                    merge_chain,
-                    unsorted,
-                }
-            } else {
-                let next_head = unsorted.remove(current_index);
-                merge_chain.push(next_head);
-                SortingState {
-                    current_index: 0,
-                    merge_chain,
-                    unsorted,
-                }
-            }
-        }
-        // pop current merge_chain head back into unsorted
-        KeyCode::Esc => {
-            let head = merge_chain.pop();
-            if let Some(head) = head {
-                unsorted.push(head);
-            }
-            SortingState {
-                current_index: 0,
-                merge_chain,
-                unsorted,
-            }
         }
-        // continue to next step
-        KeyCode::Char(' ') => {
-            if merge_chain.is_empty() {
-                return AppState::Done;
-            }
-            let current_checkout = merge_chain.remove(0);
-            let s = WorkingState {
-                current_checkout,
-                next: merge_chain,
-                done: vec![],
-            };
-            return AppState::UpdatingCandidate(s);
+        let (remote, backports) = (remote.name.clone(), backports.clone());
+        return failed("cherry-picking the backport", maybe_picked, move || {
+            AppState::Backporting(cherry_pick_current(&remote, &backports, options, &s), s)
+        });
+    }
+
+    // still waiting for the cherry-pick...
+    AppState::Backporting(rx, s)
+}
+
+fn transition_waiting_backport_resolution(
+    last_event: &AppEvent,
+    options: GitOptions,
+    s: BackportState,
+) -> AppState {
+    match last_event {
+        AppEvent::Input(KeyEvent {
+            code: KeyCode::Char(' '),
+            ..
+        }) => AppState::Backporting(continue_cherry_pick(options), s),
+        AppEvent::Error(e) => event_failed(e),
+        _ => AppState::WaitingForBackportResolution(s),
+    }
+}
+
+/** open the backport pull once its branch is pushed, then continue with the next one */
+async fn transition_pushing_backport(
+    forge: &dyn Forge,
+    remote: &Remote,
+    branch: &mut String,
+    backports: &mut Backports,
+    options: GitOptions,
+    mut rx: Receiver<anyhow::Result<()>>,
+    s: BackportState,
+) -> AppState {
+    if let Some(maybe_pushed) = task::finished(&mut rx) {
+        debug!("{:?}", maybe_pushed);
+        if let Some(Ok(())) = maybe_pushed {
+            return open_backport_and_continue(forge, remote, branch, backports, options, s).await;
         }
-        _ => SortingState {
-            unsorted,
-            current_index,
-            merge_chain,
-        },
-    };
+        let remote = remote.name.clone();
+        return failed("pushing the backport branch", maybe_pushed, move || {
+            AppState::PushingBackport(push_new_branch(&remote, &s.branch_name(), options), s)
+        });
+    }
 
-    AppState::WaitingForSort(new_state)
+    // still waiting for the push...
+    AppState::PushingBackport(rx, s)
 }
 
-/** update the current candidate to point at the previous candidates head, then start checking it out. */
-async fn transition_updating_candidate(
-    branch: &str,
+async fn open_backport_and_continue(
+    forge: &dyn Forge,
     remote: &Remote,
-    instance: &Octocrab,
-    s: WorkingState,
+    branch: &mut String,
+    backports: &mut Backports,
+    options: GitOptions,
+    s: BackportState,
 ) -> AppState {
-    let WorkingState {
-        current_checkout,
-        next,
-        done,
+    let head = s.branch_name();
+    let BackportState {
+        target,
+        current,
+        mut next,
+        mut done,
+        mut opened,
     } = s;
-
-    let Ok(()) = retarget_candidate(
-        remote,
-        instance,
-        &current_checkout,
-        &done
-            .last()
-            .map(|c| c.pull.head.ref_field.clone())
-            .unwrap_or(branch.to_owned()),
-    )
-    .await
-    else {
-        return AppState::Failed;
+    let opening = if options.read_only {
+        info!("read-only: would open a backport pull for {head} onto {target}");
+        Ok(None)
+    } else {
+        open_backport_pull(remote, forge, &current, &head, &target)
+            .await
+            .map(Some)
     };
-    let rx = checkout_branch(&current_checkout.pull.head.ref_field);
+    match opening {
+        Ok(None) => {}
+        Ok(Some(pull)) => {
+            audit::record(&format!("opened backport pull #{} for {head}", pull.number));
+            opened.push(MergeCandidate::new(pull));
+        }
+        Err(e) => {
+            let s = BackportState {
+                target,
+                current,
+                next,
+                done,
+                opened,
+            };
+            let remote = remote.name.clone();
+            let failure = Failure::new("opening the backport pull", e);
+            return AppState::Failed(failure.with_retry(move || {
+                AppState::PushingBackport(push_new_branch(&remote, &head, options), s)
+            }));
+        }
+    }
+    done.push(current);
 
-    AppState::CheckingOutCandidate(
-        rx,
-        WorkingState {
+    if next.is_empty() {
+        if !backports.merge {
+            return start_next_backport(&remote.name, backports, options);
+        }
+        // run the backport pulls through the pipeline with the backport target as base
+        *branch = target;
+        let current_checkout = opened.remove(0);
+        let s = WorkingState {
             current_checkout,
-            next,
-            done,
-        },
-    )
-}
+            next: opened,
+            done: vec![],
+        };
+        return update_candidate(options.confirm, branch, s);
+    }
 
-async fn transition_checkout_candidate(
-    branch: &str,
-    mut rx: Receiver<anyhow::Result<()>>,
-    s: WorkingState,
-) -> AppState {
-    // 0. update pull to point at prev
-    // 1. checkout candidate head (next[0])
-    // 2. rebase on base
-    // 3. conflicts? wait for space -> goto 3
-    // 4. solved? force push -> gh should show no conflicts
-    let WorkingState {
-        current_checkout,
+    let s = BackportState {
+        target,
+        current: next.remove(0),
         next,
         done,
-    } = s;
+        opened,
+    };
+    let rx = cherry_pick_current(&remote.name, backports, options, &s);
+    AppState::Backporting(rx, s)
+}
 
-    {
-        let ready = futures::future::ready(()).fuse();
-        let nxt = rx.recv().fuse();
-
-        futures::pin_mut!(ready, nxt);
-
-        futures::select! {
-            maybe_checked_out = nxt => {
-                if let Some(Ok(())) = maybe_checked_out {
-                    let next_base = done.last()
-                    .map(|c| c.pull.head.ref_field.clone())
-                    .unwrap_or(branch.to_owned());
-                    let rx_reb = rebase_branch(&next_base);
-                    let new_s = WorkingState {current_checkout, next, done};
-                    return AppState::RebaseCandidate(rx_reb, new_s)
-                }
-                return AppState::Failed;
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::forge::Ref;
+
+    pub(crate) fn candidate(number: u64, title: &str) -> MergeCandidate {
+        MergeCandidate::new(Pull {
+            number,
+            title: Some(title.to_owned()),
+            body: None,
+            author: None,
+            labels: vec![],
+            html_url: None,
+            created_at: None,
+            updated_at: None,
+            head: Ref {
+                branch: format!("pr-{number}"),
+                sha: format!("{number:040}"),
             },
-            () = ready => (),
-        };
+            base: Ref {
+                branch: "main".to_owned(),
+                sha: "0".repeat(40),
+            },
+            commits: None,
+            additions: None,
+            deletions: None,
+            merged: false,
+            mergeable: None,
+        })
     }
 
-    AppState::CheckingOutCandidate(
-        rx,
-        WorkingState {
-            current_checkout,
-            next,
-            done,
-        },
-    )
-}
-
-async fn transition_rebasing(
-    cmd: &str,
-    mut rx: Receiver<anyhow::Result<bool>>,
-    s: WorkingState,
-) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let task = rx.recv().fuse();
-
-        futures::pin_mut!(ready, task);
-
-        futures::select! {
-            maybe_rebased = task => {
-                info!("{:?}", maybe_rebased);
-                if let Some(Ok(done)) = maybe_rebased {
-                    return if done {
-                        AppState::Validating(validate(cmd), s)
-                    } else {
-                        let rx = has_no_conflicts();
-                        AppState::CheckingForConflicts(rx, s)
-                    };
-                }
-                return AppState::Failed;
+    /** marge merging into main of o/r in the state, without a forge or a terminal behind it */
+    pub(crate) fn marge(app_state: AppState) -> Marge {
+        Marge {
+            app_state: Box::new(app_state),
+            forge: Arc::new(Local::new(vec![], "main".to_owned())),
+            remote: Remote {
+                name: "origin".to_owned(),
+                host: "github.com".to_owned(),
+                owner: "o".to_owned(),
+                repo: "r".to_owned(),
             },
-            () = ready => (),
-        };
+            login: None,
+            cmd: "true".to_owned(),
+            branch: "main".to_owned(),
+            pick_branch: false,
+            strategy: Strategy::Rebase,
+            git_options: GitOptions::default(),
+            stack_title: None,
+            workflow: None,
+            signer: Signer(None),
+            backports: Backports::default(),
+            active_pane: ActivePane::List,
+            last_event: AppEvent::Tick,
+            log_state: TuiWidgetState::new(),
+            log_level: log::LevelFilter::Info,
+            log_file: PathBuf::new(),
+            notifier: Notifier::new(false, None, "o/r".to_owned(), reqwest::Client::new()),
+            paused: false,
+            seen_pulls: HashSet::new(),
+            pull_stats: PullStatsCache::new(),
+            plan: None,
+            save_plan: None,
+            plan_only: false,
+            merge_only: false,
+            session: Session::none(),
+            clipboard: Clipboard::default(),
+            merged: vec![],
+            changelog: None,
+            viewer: None,
+            state_since: Instant::now(),
+            ticks: 0,
+            app_scroll: PaneScroll::default(),
+            app_width: 50,
+            frame_budget: Duration::ZERO,
+            zoomed: false,
+            quitting: false,
+        }
     }
 
-    // still waiting for the rebase...
-    AppState::RebaseCandidate(rx, s)
-}
+    /** waiting for the prompt to be confirmed, after which marge is done */
+    pub(crate) fn confirmation(prompt: &str) -> AppState {
+        ask_first(true, prompt.to_owned(), || AppState::Done)
+    }
 
-async fn transition_check_conflicts(
-    cmd: &str,
-    mut rx: Receiver<anyhow::Result<bool>>,
-    s: WorkingState,
-) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let task = rx.recv().fuse();
-
-        futures::pin_mut!(ready, task);
-
-        futures::select! {
-            maybe_conflicts_state = task => {
-                if let Some(Ok(no_conflicts)) = maybe_conflicts_state {
-                    return if no_conflicts {
-                        let rx = validate(cmd);
-                        AppState::Validating(rx, s)
-                    } else {
-                        AppState::WaitingForResolution(s)
-                    };
-                }
-                return AppState::Failed;
-            },
-            () = ready => (),
-        };
+    /** the conflicted files as they were listed, without listing them again */
+    pub(crate) fn conflicts(files: Vec<Conflict>) -> ConflictState {
+        ConflictState {
+            files,
+            current_index: 0,
+            listing: None,
+        }
     }
 
-    AppState::CheckingForConflicts(rx, s)
-}
+    fn numbers(candidates: &[MergeCandidate]) -> Vec<u64> {
+        candidates.iter().map(|c| c.pull.number).collect()
+    }
 
-async fn transition_validate(mut rx: Receiver<anyhow::Result<bool>>, s: WorkingState) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let task = rx.recv().fuse();
-
-        futures::pin_mut!(ready, task);
-
-        futures::select! {
-            maybe_validated = task => {
-                info!("{:?}", maybe_validated);
-                if let Some(Ok(is_validated)) = maybe_validated {
-                    if is_validated {
-                        let rx = push_candidate();
-                        return AppState::PushingCandidate(rx, s);
-                    }
-                    return AppState::WaitingForFix(s);
-                }
-                return AppState::Failed;
-            },
-            () = ready => (),
-        };
+    #[test]
+    fn stacks_follow_each_other_by_their_lowest_pull() {
+        let re = stack_title(r"\[(?<stack>\w+) (?<index>\d+)/\d+\]").unwrap();
+        let mut candidates = vec![
+            candidate(5, "[auth 2/2] tokens"),
+            candidate(3, "[db 2/2] indexes"),
+            candidate(7, "unrelated"),
+            candidate(4, "[auth 1/2] users"),
+            candidate(9, "[db 1/2] schema"),
+        ];
+        let chain = title_stacks(&re, &mut candidates);
+        // db has #3, which is lower than anything in auth
+        assert_eq!(numbers(&chain), [9, 3, 4, 5]);
+        assert_eq!(numbers(&candidates), [7]);
     }
 
-    // still waiting for validation...
-    AppState::Validating(rx, s)
-}
+    #[test]
+    fn stacks_are_ordered_by_index_not_by_text() {
+        let re = stack_title(r"^(?<stack>\S+)#(?<index>\d+)").unwrap();
+        let mut candidates = vec![
+            candidate(1, "s#10 last"),
+            candidate(2, "s#9 middle"),
+            candidate(3, "s#1 first"),
+        ];
+        assert_eq!(numbers(&title_stacks(&re, &mut candidates)), [3, 2, 1]);
+    }
 
-async fn transition_pushing(mut rx: Receiver<anyhow::Result<()>>, s: WorkingState) -> AppState {
-    {
-        let ready = futures::future::ready(()).fuse();
-        let task = rx.recv().fuse();
-
-        futures::pin_mut!(ready, task);
-
-        futures::select! {
-            maybe_rebased = task => {
-                info!("{:?}", maybe_rebased);
-                if let Some(Ok(())) = maybe_rebased {
-                    let mut done = s.done;
-                    done.push(s.current_checkout);
-                    let mut next = s.next;
-
-
-                    return if next.is_empty() {
-                        let new_s = MergingState {
-                            to_merge: done
-                        };
-                        AppState::Merging(new_s)
-                    } else {
-                        let current_checkout = next.remove(0);
-                        let new_s = WorkingState {
-                            current_checkout,
-                            next,
-                            done
-                        };
-                        AppState::UpdatingCandidate(new_s)
-                    };
-                }
-                return AppState::Failed;
-            },
-            () = ready => (),
+    #[test]
+    fn optional_groups_that_did_not_match_leave_the_pull_alone() {
+        let re = stack_title(r"^(?:\[(?<stack>\w+)\])? ?(?:(?<index>\d+)\.)?").unwrap();
+        let mut candidates = vec![
+            candidate(1, "[a] 2. second"),
+            candidate(2, "[a] no index"),
+            candidate(3, "1. no stack"),
+            candidate(4, "[a] 1. first"),
+        ];
+        let chain = title_stacks(&re, &mut candidates);
+        assert_eq!(numbers(&chain), [4, 1]);
+        assert_eq!(numbers(&candidates), [2, 3]);
+    }
+
+    #[test]
+    fn stack_title_needs_both_groups() {
+        assert!(stack_title(r"(?<stack>\w+)").is_err());
+        assert!(stack_title(r"(?<stack>\w+) (?<index>\d+)").is_ok());
+    }
+
+    #[test]
+    fn candidates_go_onto_the_fetched_base() {
+        let mut c = candidate(1, "pull");
+        c.onto = Some("release".to_owned());
+        let mut s = WorkingState {
+            current_checkout: c,
+            next: vec![],
+            done: vec![],
         };
+        assert_eq!(s.base_ref("main"), "release");
+        s.current_checkout.onto_ref = Some("origin/release".to_owned());
+        assert_eq!(s.base("main"), "release");
+        assert_eq!(s.base_ref("main"), "origin/release");
     }
 
-    // still waiting for the push...
-    AppState::PushingCandidate(rx, s)
-}
+    #[test]
+    fn protected_patterns_match_whole_names() {
+        let protected = Protected::new(&["main".to_owned(), "release/*".to_owned()]).unwrap();
+        assert_eq!(protected.pattern("release/1.0"), Some("release/*"));
+        assert!(protected.guard("main", "force-push").is_err());
+        assert!(protected.guard("main-fix", "force-push").is_ok());
+        assert!(Protected::default().guard("main", "force-push").is_ok());
+    }
 
-fn transition_fixing(last_event: &AppEvent, cmd: &str, s: WorkingState) -> AppState {
-    match last_event {
-        AppEvent::Input(KeyEvent {
-            code: KeyCode::Char(' '),
-            ..
-        }) => AppState::Validating(validate(cmd), s),
-        AppEvent::Error(_) => AppState::Failed,
-        _ => AppState::WaitingForFix(s),
+    fn sorting(unsorted: &[u64], chained: &[u64]) -> SortingState {
+        let pulls = |numbers: &[u64]| numbers.iter().map(|n| candidate(*n, "pull")).collect();
+        SortingState::new(pulls(unsorted), pulls(chained))
     }
-}
 
-async fn transition_merging(instance: &Octocrab, remote: &Remote, s: MergingState) -> AppState {
-    let MergingState { to_merge } = s;
-    for MergeCandidate {
-        pull: PullRequest { number, title, .. },
-    } in to_merge
-    {
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-        info!(
-            "merging pull {number} with {}",
-            title.unwrap_or("<untitled>".to_string())
-        );
-        let result = instance
-            .pulls(&remote.owner, &remote.repo)
-            .merge(number)
-            .method(params::pulls::MergeMethod::Rebase)
-            .send()
-            .await;
-        match result {
-            Err(e) => {
-                info!("failed with {:?}", e);
-                return AppState::Failed;
+    #[test]
+    fn selection_stays_put_without_pulls() {
+        let mut state = sorting(&[], &[]);
+        state.select_prev();
+        assert_eq!(state.current_index, 0);
+        state.select_next();
+        assert_eq!(state.current_index, 0);
+        state.select_last();
+        assert_eq!(state.current_index, 0);
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut state = sorting(&[1, 2, 3], &[]);
+        state.select_prev();
+        assert_eq!(state.current_index, 2);
+        state.select_next();
+        assert_eq!(state.current_index, 0);
+    }
+
+    #[test]
+    fn chaining_past_the_end_chains_nothing() {
+        let mut state = sorting(&[1, 2], &[]);
+        state.current_index = 5;
+        state.chain_selected();
+        assert_eq!(numbers(&state.unsorted), [1, 2]);
+        assert!(state.merge_chain.is_empty());
+        assert_eq!(state.current_index, 0);
+    }
+
+    #[test]
+    fn chained_pulls_stop_at_the_ends() {
+        let mut state = sorting(&[], &[1, 2, 3]);
+        state.move_chained(Some(0), true);
+        assert_eq!(numbers(&state.merge_chain), [1, 2, 3]);
+        assert_eq!(state.moving, Some(0));
+        state.move_chained(Some(2), false);
+        assert_eq!(numbers(&state.merge_chain), [1, 2, 3]);
+        assert_eq!(state.moving, Some(2));
+        // without one being moved, it's the last one
+        state.move_chained(None, true);
+        assert_eq!(numbers(&state.merge_chain), [1, 3, 2]);
+        assert_eq!(state.moving, Some(1));
+
+        let mut empty = sorting(&[], &[]);
+        empty.move_chained(None, false);
+        assert_eq!(empty.moving, None);
+    }
+
+    #[test]
+    fn chaining_all_again_tries_the_next_order() {
+        let mut state = sorting(&[1, 2, 3], &[9]);
+        state.chain_all(None);
+        assert!(state.unsorted.is_empty());
+        assert_eq!(state.merge_chain.len(), 4);
+        assert_eq!(state.auto, Some((AutoOrder::Created, 3)));
+        let auto = state.auto.take();
+        state.chain_all(auto);
+        assert!(state.unsorted.is_empty());
+        assert_eq!(state.merge_chain.len(), 4);
+        // the pull chained before stays where it was
+        assert_eq!(state.merge_chain[0].pull.number, 9);
+        assert_eq!(state.auto, Some((AutoOrder::Updated, 3)));
+    }
+
+    #[test]
+    fn jumping_out_of_range_keeps_the_selection() {
+        let mut state = sorting(&[10, 11], &[]);
+        state.jump_to("5");
+        assert_eq!(state.current_index, 0);
+        state.jump_to("not a number");
+        assert_eq!(state.current_index, 0);
+        state.jump_to("2");
+        assert_eq!(state.current_index, 1);
+        state.jump_to("#10");
+        assert_eq!(state.current_index, 0);
+    }
+
+    /// the keys the sorting view reacts to, and a few it doesn't
+    fn sort_keys() -> Vec<KeyEvent> {
+        let codes = [
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Char('k'),
+            KeyCode::Char('j'),
+            KeyCode::PageUp,
+            KeyCode::PageDown,
+            KeyCode::Home,
+            KeyCode::End,
+            KeyCode::Char('x'),
+            KeyCode::Enter,
+            KeyCode::Char('s'),
+            KeyCode::Char('O'),
+            KeyCode::Char('o'),
+            KeyCode::Char('#'),
+            KeyCode::Char('1'),
+            KeyCode::Char('3'),
+            KeyCode::Char('a'),
+            KeyCode::Esc,
+            KeyCode::Char('z'),
+        ];
+        let mut keys = codes
+            .into_iter()
+            .map(|code| KeyEvent::new(code, KeyModifiers::NONE))
+            .collect::<Vec<KeyEvent>>();
+        keys.push(KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT));
+        keys.push(KeyEvent::new(KeyCode::Down, KeyModifiers::SHIFT));
+        keys
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn random_keys_neither_lose_nor_duplicate_pulls(
+            unsorted in 0..8u64,
+            chained in 0..4u64,
+            presses in proptest::collection::vec(0..21usize, 0..64),
+        ) {
+            let keys = sort_keys();
+            let unsorted = (1..=unsorted).collect::<Vec<u64>>();
+            let chained = (100..100 + chained).collect::<Vec<u64>>();
+            let mut all = unsorted.iter().chain(&chained).copied().collect::<Vec<u64>>();
+            all.sort();
+            let mut state = sorting(&unsorted, &chained);
+            for press in presses {
+                state = sort_key(&keys[press], state);
+                proptest::prop_assert!(state.current_index < state.unsorted.len().max(1));
+                let mut now = numbers(&state.unsorted);
+                now.extend(numbers(&state.merge_chain));
+                now.sort();
+                proptest::prop_assert_eq!(&now, &all);
             }
-            Ok(p) => info!("merged? {:?}", p.merged),
         }
     }
-
-    AppState::Done
 }
@@ -0,0 +1,387 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use url::Url;
+
+use crate::{
+    forge::{Checks, Forge, MergeMethod, Pull, Ref},
+    git::Remote,
+    network,
+    settings::Network,
+};
+
+/// how many items gitea returns per page at most
+const PAGE_SIZE: usize = 50;
+
+/// a gitea or forgejo instance, through its REST API. the two don't differ in anything
+/// marge uses
+pub struct Gitea {
+    client: reqwest::Client,
+    /// the api root without the trailing slash, like https://codeberg.org/api/v1
+    api: String,
+    token: String,
+    timeout: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct GiteaPull {
+    number: u64,
+    title: Option<String>,
+    body: Option<String>,
+    user: Option<GiteaUser>,
+    #[serde(default)]
+    labels: Vec<GiteaLabel>,
+    html_url: Option<Url>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    head: GiteaRef,
+    base: GiteaRef,
+    merge_commit_sha: Option<String>,
+    /// only newer versions send these
+    additions: Option<u64>,
+    deletions: Option<u64>,
+    #[serde(default)]
+    merged: bool,
+    mergeable: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct GiteaUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaRef {
+    #[serde(rename = "ref")]
+    branch: String,
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaRepo {
+    permissions: Option<GiteaPermissions>,
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GiteaPermissions {
+    push: bool,
+}
+
+#[derive(Deserialize)]
+struct GiteaStatus {
+    state: Option<String>,
+    #[serde(default)]
+    total_count: u64,
+}
+
+#[derive(Deserialize)]
+struct GiteaCompare {
+    total_commits: u64,
+}
+
+impl From<GiteaPull> for Pull {
+    fn from(pull: GiteaPull) -> Pull {
+        Pull {
+            number: pull.number,
+            title: pull.title,
+            body: pull.body,
+            author: pull.user.map(|u| u.login),
+            labels: pull.labels.into_iter().map(|l| l.name).collect(),
+            html_url: pull.html_url,
+            created_at: pull.created_at,
+            updated_at: pull.updated_at,
+            head: Ref {
+                branch: pull.head.branch,
+                sha: pull.head.sha,
+            },
+            base: Ref {
+                branch: pull.base.branch,
+                sha: pull.base.sha,
+            },
+            commits: None,
+            additions: pull.additions,
+            deletions: pull.deletions,
+            merged: pull.merged,
+            mergeable: pull.mergeable,
+        }
+    }
+}
+
+impl Gitea {
+    pub fn new(
+        api: Url,
+        token: String,
+        timeout: Option<Duration>,
+        network: &Network,
+    ) -> anyhow::Result<Gitea> {
+        Ok(Gitea {
+            client: network::http_client(network)?,
+            api: api.as_str().trim_end_matches('/').to_owned(),
+            token,
+            timeout,
+        })
+    }
+
+    fn repo_url(&self, remote: &Remote, path: &str) -> String {
+        format!("{}/repos/{}/{}{path}", self.api, remote.owner, remote.repo)
+    }
+
+    /** send the request with the token, turning gitea's error messages into errors */
+    async fn send(&self, request: reqwest::RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        let mut request = request
+            .header("Authorization", &format!("token {}", self.token))
+            .header("Accept", "application/json");
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            let message = response.text().await.unwrap_or_default();
+            Err(anyhow!("{} answered {status}: {message}", self.api))
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: String) -> anyhow::Result<T> {
+        Ok(self.send(self.client.get(url)).await?.json().await?)
+    }
+}
+
+impl Forge for Gitea {
+    fn pulls<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<Vec<Pull>>> {
+        async move {
+            let mut pulls = vec![];
+            for page in 1.. {
+                let url = self.repo_url(
+                    remote,
+                    &format!("/pulls?state=open&limit={PAGE_SIZE}&page={page}"),
+                );
+                let batch: Vec<GiteaPull> = self.get(url).await.context(format!(
+                    "could not get pulls for repo {}/{}",
+                    remote.owner, remote.repo
+                ))?;
+                let last = batch.len() < PAGE_SIZE;
+                pulls.extend(batch.into_iter().map(Pull::from));
+                if last {
+                    break;
+                }
+            }
+            Ok(pulls)
+        }
+        .boxed()
+    }
+
+    fn pull<'a>(&'a self, remote: &'a Remote, number: u64) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        async move {
+            let pull: GiteaPull = self
+                .get(self.repo_url(remote, &format!("/pulls/{number}")))
+                .await?;
+            // the pull itself doesn't say, but the paged commit list has the total in a header
+            let url = self.repo_url(remote, &format!("/pulls/{number}/commits?limit=1"));
+            let commits = self
+                .send(self.client.get(url))
+                .await?
+                .headers()
+                .get("x-total-count")
+                .and_then(|count| count.to_str().ok()?.parse().ok());
+            Ok(Pull {
+                commits,
+                ..pull.into()
+            })
+        }
+        .boxed()
+    }
+
+    fn default_branch<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            let found: GiteaRepo = self.get(self.repo_url(remote, "")).await?;
+            found
+                .default_branch
+                .context("gitea did not say which branch is the default")
+        }
+        .boxed()
+    }
+
+    fn check_access<'a>(
+        &'a self,
+        remote: &'a Remote,
+        read_only: bool,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            self.send(self.client.get(format!("{}/user", self.api)))
+                .await
+                .context(format!(
+                    "{} doesn't accept the token, it may have expired",
+                    self.api
+                ))?;
+            let found: GiteaRepo = self
+                .get(self.repo_url(remote, ""))
+                .await
+                .context(format!(
+                    "could not get {owner}/{repo}, the token may not have access to it"
+                ))?;
+            match found.permissions {
+                Some(p) if !p.push && !read_only => Err(anyhow!(
+                    "the token can't push to {owner}/{repo}, ask an admin for write access or try marge with --read-only"
+                )),
+                _ => Ok(()),
+            }
+        }
+        .boxed()
+    }
+
+    fn login<'a>(&'a self, _remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            let user: GiteaUser = self.get(format!("{}/user", self.api)).await?;
+            Ok(user.login)
+        }
+        .boxed()
+    }
+
+    fn retarget<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let url = self.repo_url(remote, &format!("/pulls/{number}"));
+            self.send(self.client.patch(url).json(&json!({ "base": base })))
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn open_pull<'a>(
+        &'a self,
+        remote: &'a Remote,
+        title: String,
+        body: String,
+        head: &'a str,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        async move {
+            let request = self
+                .client
+                .post(self.repo_url(remote, "/pulls"))
+                .json(&json!({
+                    "title": title,
+                    "body": body,
+                    "head": head,
+                    "base": base,
+                }));
+            let pull: GiteaPull = self.send(request).await?.json().await?;
+            Ok(pull.into())
+        }
+        .boxed()
+    }
+
+    fn merge<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        method: MergeMethod,
+    ) -> BoxFuture<'a, anyhow::Result<Option<String>>> {
+        let method = match method {
+            MergeMethod::Rebase => "rebase",
+            MergeMethod::Squash => "squash",
+            MergeMethod::Merge => "merge",
+        };
+        async move {
+            let url = self.repo_url(remote, &format!("/pulls/{number}/merge"));
+            self.send(self.client.post(url).json(&json!({ "Do": method })))
+                .await?;
+            // the merge itself answers with nothing, the pull knows where it ended up
+            let pull: GiteaPull = self
+                .get(self.repo_url(remote, &format!("/pulls/{number}")))
+                .await?;
+            Ok(pull.merge_commit_sha)
+        }
+        .boxed()
+    }
+
+    fn update_branch<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let url = self.repo_url(remote, &format!("/pulls/{number}/update"));
+            self.send(self.client.post(url)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn dispatch_workflow<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        workflow: &'a str,
+        _branch: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move { Err(anyhow!("gitea can't dispatch the workflow {workflow}")) }.boxed()
+    }
+
+    fn workflow_run<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        workflow: &'a str,
+        _sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        async move {
+            Err(anyhow!(
+                "gitea can't look up runs of the workflow {workflow}"
+            ))
+        }
+        .boxed()
+    }
+
+    fn checks<'a>(
+        &'a self,
+        remote: &'a Remote,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        async move {
+            let status: GiteaStatus = self
+                .get(self.repo_url(remote, &format!("/commits/{sha}/status")))
+                .await?;
+            Ok(match status.state.as_deref() {
+                _ if status.total_count == 0 => Checks::Missing,
+                Some("failure" | "error") => Checks::Failing,
+                Some("pending") => Checks::Pending,
+                _ => Checks::Passing,
+            })
+        }
+        .boxed()
+    }
+
+    fn behind_by<'a>(
+        &'a self,
+        remote: &'a Remote,
+        base: &'a str,
+        head: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        async move {
+            // the commits base has that head doesn't
+            let compare: GiteaCompare = self
+                .get(self.repo_url(remote, &format!("/compare/{head}...{base}")))
+                .await?;
+            Ok(compare.total_commits)
+        }
+        .boxed()
+    }
+}
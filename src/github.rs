@@ -0,0 +1,340 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use futures::{future::BoxFuture, FutureExt};
+use octocrab::{models::pulls::PullRequest, params, Octocrab, Page};
+use serde_json::Value;
+
+use crate::{
+    forge::{Checks, Forge, MergeMethod, Pull, Ref},
+    git::Remote,
+    network,
+    settings::Network,
+};
+
+/// github.com, through octocrab
+pub struct GitHub(Octocrab);
+
+impl GitHub {
+    pub fn new(
+        token: String,
+        timeout: Option<Duration>,
+        network: &Network,
+    ) -> anyhow::Result<GitHub> {
+        Ok(GitHub(network::github(token, timeout, network)?))
+    }
+}
+
+impl From<PullRequest> for Pull {
+    fn from(pull: PullRequest) -> Pull {
+        Pull {
+            number: pull.number,
+            title: pull.title,
+            body: pull.body,
+            author: pull.user.map(|u| u.login),
+            labels: pull
+                .labels
+                .unwrap_or_default()
+                .into_iter()
+                .map(|l| l.name)
+                .collect(),
+            html_url: pull.html_url,
+            created_at: pull.created_at,
+            updated_at: pull.updated_at,
+            head: Ref {
+                branch: pull.head.ref_field,
+                sha: pull.head.sha,
+            },
+            base: Ref {
+                branch: pull.base.ref_field,
+                sha: pull.base.sha,
+            },
+            commits: pull.commits,
+            additions: pull.additions,
+            deletions: pull.deletions,
+            merged: pull.merged_at.is_some(),
+            mergeable: pull.mergeable,
+        }
+    }
+}
+
+impl Forge for GitHub {
+    fn pulls<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<Vec<Pull>>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            self.0
+                .pulls(owner, repo)
+                .list()
+                .state(params::State::Open)
+                .per_page(100)
+                .page(1u8)
+                .send()
+                .await
+                .context(format!("could not get pulls for repo {owner}/{repo}"))
+                .map(|p: Page<PullRequest>| p.items.into_iter().map(Pull::from).collect())
+        }
+        .boxed()
+    }
+
+    fn pull<'a>(&'a self, remote: &'a Remote, number: u64) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        let Remote { owner, repo, .. } = remote;
+        async move { Ok(self.0.pulls(owner, repo).get(number).await?.into()) }.boxed()
+    }
+
+    fn default_branch<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            self.0
+                .repos(owner, repo)
+                .get()
+                .await?
+                .default_branch
+                .context("github did not say which branch is the default")
+        }
+        .boxed()
+    }
+
+    fn check_access<'a>(
+        &'a self,
+        remote: &'a Remote,
+        read_only: bool,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            let user = self
+                .0
+                ._get("/user")
+                .await
+                .context("could not check the token")?;
+            if user.status() == http::StatusCode::UNAUTHORIZED {
+                return Err(anyhow!(
+                    "github doesn't accept the token, it may have expired. run marge login for a new one"
+                ));
+            }
+            // only classic tokens have scopes, fine-grained ones show up in the permissions below
+            if let Some(scopes) = user.headers().get("x-oauth-scopes") {
+                let scopes = scopes.to_str().unwrap_or_default();
+                if !read_only && !scopes.split(',').any(|s| s.trim() == "repo") {
+                    return Err(anyhow!(
+                        "the token needs the repo scope, it only has [{scopes}]. create one with it at https://github.com/settings/tokens"
+                    ));
+                }
+            }
+            let permissions = self
+                .0
+                .repos(owner, repo)
+                .get()
+                .await
+                .context(format!(
+                    "could not get {owner}/{repo}, the token may not have access to it"
+                ))?
+                .permissions;
+            match permissions {
+                Some(p) if !p.push && !read_only => Err(anyhow!(
+                    "the token can't push to {owner}/{repo}, ask an admin for write access or try marge with --read-only"
+                )),
+                _ => Ok(()),
+            }
+        }
+        .boxed()
+    }
+
+    fn login<'a>(&'a self, _remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            let user = self
+                .0
+                .current()
+                .user()
+                .await
+                .context("could not get the user of the token")?;
+            Ok(user.login)
+        }
+        .boxed()
+    }
+
+    fn retarget<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            self.0
+                .pulls(owner, repo)
+                .update(number)
+                .base(base)
+                .send()
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn open_pull<'a>(
+        &'a self,
+        remote: &'a Remote,
+        title: String,
+        body: String,
+        head: &'a str,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            Ok(self
+                .0
+                .pulls(owner, repo)
+                .create(title, head, base)
+                .body(body)
+                .send()
+                .await?
+                .into())
+        }
+        .boxed()
+    }
+
+    fn merge<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        method: MergeMethod,
+    ) -> BoxFuture<'a, anyhow::Result<Option<String>>> {
+        let Remote { owner, repo, .. } = remote;
+        let method = match method {
+            MergeMethod::Rebase => params::pulls::MergeMethod::Rebase,
+            MergeMethod::Squash => params::pulls::MergeMethod::Squash,
+            MergeMethod::Merge => params::pulls::MergeMethod::Merge,
+        };
+        async move {
+            let merge = self
+                .0
+                .pulls(owner, repo)
+                .merge(number)
+                .method(method)
+                .send()
+                .await?;
+            Ok(merge.sha)
+        }
+        .boxed()
+    }
+
+    fn update_branch<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            self.0.pulls(owner, repo).update_branch(number).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn dispatch_workflow<'a>(
+        &'a self,
+        remote: &'a Remote,
+        workflow: &'a str,
+        branch: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let Remote { owner, repo, .. } = remote;
+        async move {
+            self.0
+                .actions()
+                .create_workflow_dispatch(owner, repo, workflow, branch)
+                .send()
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn workflow_run<'a>(
+        &'a self,
+        remote: &'a Remote,
+        workflow: &'a str,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        let repo = format!("/repos/{}/{}", remote.owner, remote.repo);
+        async move {
+            let runs = self
+                .0
+                .get::<Value, _, _>(
+                    format!("{repo}/actions/workflows/{workflow}/runs"),
+                    Some(&[("event", "workflow_dispatch"), ("head_sha", sha)]),
+                )
+                .await?;
+            // the runs come newest first
+            let Some(run) = runs["workflow_runs"].as_array().and_then(|r| r.first()) else {
+                return Ok(Checks::Missing);
+            };
+            Ok(match (run["status"].as_str(), run["conclusion"].as_str()) {
+                (Some("completed"), Some("success" | "neutral" | "skipped")) => Checks::Passing,
+                (Some("completed"), _) => Checks::Failing,
+                _ => Checks::Pending,
+            })
+        }
+        .boxed()
+    }
+
+    fn checks<'a>(
+        &'a self,
+        remote: &'a Remote,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        let repo = format!("/repos/{}/{}", remote.owner, remote.repo);
+        async move {
+            let (runs, status) = futures::try_join!(
+                self.0
+                    .get::<Value, _, ()>(format!("{repo}/commits/{sha}/check-runs"), None),
+                self.0
+                    .get::<Value, _, ()>(format!("{repo}/commits/{sha}/status"), None),
+            )?;
+            Ok(checks_state(&runs, &status))
+        }
+        .boxed()
+    }
+
+    fn behind_by<'a>(
+        &'a self,
+        remote: &'a Remote,
+        base: &'a str,
+        head: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        let repo = format!("/repos/{}/{}", remote.owner, remote.repo);
+        async move {
+            let compare = self
+                .0
+                .get::<Value, _, ()>(format!("{repo}/compare/{base}...{head}"), None)
+                .await?;
+            compare["behind_by"]
+                .as_u64()
+                .context("github did not say how far behind it is")
+        }
+        .boxed()
+    }
+}
+
+/** sum up the check runs and the commit statuses, the worst one wins */
+fn checks_state(runs: &Value, status: &Value) -> Checks {
+    let runs = runs["check_runs"].as_array().map_or(&[][..], Vec::as_slice);
+    let failed = runs.iter().any(|r| {
+        matches!(
+            r["conclusion"].as_str(),
+            Some("failure" | "timed_out" | "cancelled" | "action_required")
+        )
+    });
+    let pending = runs
+        .iter()
+        .any(|r| r["status"].as_str() != Some("completed"));
+    let has_statuses = status["total_count"].as_u64().unwrap_or(0) > 0;
+
+    match status["state"].as_str() {
+        _ if failed => Checks::Failing,
+        Some("failure" | "error") if has_statuses => Checks::Failing,
+        _ if pending => Checks::Pending,
+        Some("pending") if has_statuses => Checks::Pending,
+        _ if runs.is_empty() && !has_statuses => Checks::Missing,
+        _ => Checks::Passing,
+    }
+}
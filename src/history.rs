@@ -0,0 +1,202 @@
+use std::{io::Write, path::PathBuf, time::Instant};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{audit, git::Marge, merge_candidate::MergeCandidate, validation_log};
+
+/// a finished run, one line of the history file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    /// owner/repo
+    pub repo: String,
+    pub branch: String,
+    pub started: DateTime<Local>,
+    pub took_secs: u64,
+    /// how the run ended, see Outcome
+    pub outcome: String,
+    pub pulls: Vec<RunPull>,
+}
+
+/// how far a pull of the chain got in the run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPull {
+    pub number: u64,
+    pub title: Option<String>,
+    pub status: String,
+    pub took_secs: Option<u64>,
+    pub merged_as: Option<String>,
+}
+
+impl RunPull {
+    fn new(c: &MergeCandidate) -> RunPull {
+        RunPull {
+            number: c.pull.number,
+            title: c.pull.title.clone(),
+            status: c.status.name().to_owned(),
+            took_secs: c.took().map(|took| took.as_secs()),
+            merged_as: c.merged_as.clone(),
+        }
+    }
+}
+
+/// follows the chain through the run, to write it to the history once the run is over. the
+/// states that end a run, like aborting, don't know about the chain anymore
+pub struct Recorder {
+    started: DateTime<Local>,
+    at: Instant,
+    pulls: Vec<RunPull>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder {
+            started: Local::now(),
+            at: Instant::now(),
+            pulls: vec![],
+        }
+    }
+
+    /** remember how far the chain got, the merged pulls first */
+    pub fn update(&mut self, marge: &Marge) {
+        let Some(chain) = marge.app_state.chain() else {
+            return;
+        };
+        let mut pulls = marge.merged.iter().map(RunPull::new).collect::<Vec<_>>();
+        for c in chain {
+            if !pulls.iter().any(|p| p.number == c.pull.number) {
+                pulls.push(RunPull::new(c));
+            }
+        }
+        if !pulls.is_empty() {
+            self.pulls = pulls;
+        }
+    }
+
+    /** add the run to the history, unless it never got to a chain */
+    pub fn finish(self, marge: &Marge, outcome: &str) {
+        if self.pulls.is_empty() {
+            return;
+        }
+        let Some(id) = audit::run_id() else {
+            return;
+        };
+        let run = Run {
+            id: id.to_owned(),
+            repo: format!("{}/{}", marge.remote.owner, marge.remote.repo),
+            branch: marge.branch.clone(),
+            started: self.started,
+            took_secs: self.at.elapsed().as_secs(),
+            outcome: outcome.to_owned(),
+            pulls: self.pulls,
+        };
+        if let Err(e) = append(&run) {
+            warn!("could not add the run to the history: {e:#}");
+        }
+    }
+}
+
+/** the history file in the state dir */
+fn file() -> anyhow::Result<PathBuf> {
+    Ok(crate::git::state_dir()?.join("history.jsonl"))
+}
+
+fn append(run: &Run) -> anyhow::Result<()> {
+    let file = file()?;
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)?;
+    writeln!(f, "{}", serde_json::to_string(run)?)?;
+    Ok(())
+}
+
+/** the finished runs, oldest first. lines that can't be read are left out */
+fn load() -> anyhow::Result<Vec<Run>> {
+    let file = file()?;
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+    let contents = std::fs::read_to_string(&file)
+        .context(format!("could not read the history {}", file.display()))?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn format_secs(secs: u64) -> String {
+    format!("{}m {:02}s", secs / 60, secs % 60)
+}
+
+/** print the finished runs, or everything about one of them */
+pub fn print(run_id: Option<&str>) -> anyhow::Result<()> {
+    let runs = load()?;
+    let Some(run_id) = run_id else {
+        if runs.is_empty() {
+            println!("no finished runs yet");
+        }
+        for run in &runs {
+            println!(
+                "{}  {}  {} onto {}  {}  {} pulls, took {}",
+                run.id,
+                run.started.format("%a %Y-%m-%d %H:%M"),
+                run.repo,
+                run.branch,
+                run.outcome,
+                run.pulls.len(),
+                format_secs(run.took_secs)
+            );
+        }
+        return Ok(());
+    };
+    let run = runs
+        .iter()
+        .find(|run| run.id == run_id)
+        .ok_or_else(|| anyhow!("there's no finished run {run_id} in the history"))?;
+    println!(
+        "run {} of {} onto {}, started {}, took {}: {}\n",
+        run.id,
+        run.repo,
+        run.branch,
+        run.started.format("%a %Y-%m-%d %H:%M"),
+        format_secs(run.took_secs),
+        run.outcome
+    );
+    for pull in &run.pulls {
+        let mut line = format!(
+            "#{} {}: {}",
+            pull.number,
+            pull.title.as_deref().unwrap_or("<untitled>"),
+            pull.status
+        );
+        if let Some(secs) = pull.took_secs {
+            line += &format!(", took {}", format_secs(secs));
+        }
+        if let Some(sha) = &pull.merged_as {
+            line += &format!(", merged as {sha}");
+        }
+        println!("{line}");
+    }
+    let changes = audit::entries(&run.id)?;
+    if !changes.is_empty() {
+        println!("\nchanges to the remote:");
+        for change in changes {
+            println!("{change}");
+        }
+    }
+    let logs = validation_log::of_run(&run.id);
+    if !logs.is_empty() {
+        println!("\nvalidation output:");
+        for (number, path) in logs {
+            println!("#{number}: {}", path.display());
+        }
+    }
+    Ok(())
+}
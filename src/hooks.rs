@@ -0,0 +1,104 @@
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use log::{debug, info, warn};
+use tokio::process::Command;
+
+use crate::{command_log::Logged, merge_candidate::MergeCandidate, settings::Hooks};
+
+/// the hooks from the settings, set once at startup
+static HOOKS: OnceLock<Hooks> = OnceLock::new();
+
+pub fn init(hooks: Hooks) {
+    let _ = HOOKS.set(hooks);
+}
+
+/// the points in a candidate's way through the run where a hook can run
+#[derive(Clone, Copy, Debug)]
+pub enum Hook {
+    PreRebase,
+    PostRebase,
+    PrePush,
+    PostMerge,
+}
+
+impl Hook {
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PreRebase => "pre_rebase",
+            Hook::PostRebase => "post_rebase",
+            Hook::PrePush => "pre_push",
+            Hook::PostMerge => "post_merge",
+        }
+    }
+
+    pub fn command(self) -> Option<&'static str> {
+        let hooks = HOOKS.get()?;
+        match self {
+            Hook::PreRebase => hooks.pre_rebase.as_deref(),
+            Hook::PostRebase => hooks.post_rebase.as_deref(),
+            Hook::PrePush => hooks.pre_push.as_deref(),
+            Hook::PostMerge => hooks.post_merge.as_deref(),
+        }
+    }
+}
+
+/// environment variables for a command run on behalf of a candidate
+pub type Env = Vec<(&'static str, String)>;
+
+/** the environment describing the candidate going onto base */
+pub fn env(c: &MergeCandidate, base: &str) -> Env {
+    let pull = &c.pull;
+    let mut env = vec![
+        ("MARGE_PR_NUMBER", pull.number.to_string()),
+        ("MARGE_PR_TITLE", pull.title.clone().unwrap_or_default()),
+        ("MARGE_HEAD_REF", pull.head.branch.clone()),
+        ("MARGE_BASE_REF", base.to_owned()),
+    ];
+    if let Some(url) = &pull.html_url {
+        env.push(("MARGE_PR_URL", url.to_string()));
+    }
+    if let Some(sha) = &c.merged_as {
+        env.push(("MARGE_MERGED_AS", sha.clone()));
+    }
+    env
+}
+
+/** run the hook if there's one, failing if it doesn't exit with 0 */
+pub async fn run(hook: Hook, env: &Env) -> anyhow::Result<()> {
+    let Some(cmd) = hook.command() else {
+        return Ok(());
+    };
+    info!("running the {} hook: {cmd}", hook.name());
+    let output = Command::new("sh")
+        .args(["-c", cmd])
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .env("MARGE_HOOK", hook.name())
+        .logged_output()
+        .await?;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        debug!("{}: {line}", hook.name());
+    }
+    if !output.status.success() {
+        return Err(anyhow!(
+            "the {} hook failed with {}: {}",
+            hook.name(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/** run the hook in the background, only warning if it fails. for the hooks after a step, which
+can't take back what happened anymore */
+pub fn spawn(hook: Hook, env: Env) {
+    if hook.command().is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = run(hook, &env).await {
+            warn!("{e:#}");
+        }
+    });
+}
@@ -0,0 +1,184 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::*;
+
+use crate::events::AppEvent;
+
+/// a single line of text being typed, with a cursor, pasting and a history of what was entered
+/// before. the screens that need text from the user hand their events to it while it's focused
+#[derive(Debug, Default)]
+pub struct Input {
+    text: String,
+    /// in chars, not bytes
+    cursor: usize,
+    /// what was entered before, oldest first
+    history: Vec<String>,
+    /// the entry of the history being shown, if the user went back in it
+    browsing: Option<usize>,
+}
+
+/// what an event did to the input
+#[derive(Debug, PartialEq)]
+pub enum Edit {
+    /// the event wasn't meant for the input
+    Ignored,
+    /// the cursor moved, the text stayed the same
+    Moved,
+    Changed,
+    /// enter was pressed, with the text that was entered
+    Submitted(String),
+    /// esc was pressed
+    Cancelled,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /** replace the text, moving the cursor to its end */
+    pub fn set(&mut self, text: &str) {
+        text.clone_into(&mut self.text);
+        self.cursor = self.text.chars().count();
+        self.browsing = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set("");
+    }
+
+    /** handle a key or a paste while the input is focused */
+    pub fn handle(&mut self, event: &AppEvent) -> Edit {
+        match event {
+            AppEvent::Paste(text) => {
+                // a single line can't hold the newlines
+                let text = text.replace(['\r', '\n'], " ");
+                self.insert(&text);
+                Edit::Changed
+            }
+            AppEvent::Input(key) => self.key(key),
+            _ => Edit::Ignored,
+        }
+    }
+
+    fn key(&mut self, key: &KeyEvent) -> Edit {
+        let len = self.text.chars().count();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = len,
+            KeyCode::Char('u') if ctrl => {
+                let at = self.byte(self.cursor);
+                self.text.replace_range(..at, "");
+                self.cursor = 0;
+                return Edit::Changed;
+            }
+            KeyCode::Char('w') if ctrl => {
+                // the word before the cursor and the spaces after it
+                let before = self.text.chars().take(self.cursor).collect::<Vec<char>>();
+                let spaces = before
+                    .iter()
+                    .rev()
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+                let word = before
+                    .iter()
+                    .rev()
+                    .skip(spaces)
+                    .take_while(|c| !c.is_whitespace())
+                    .count();
+                let start = self.cursor - spaces - word;
+                let range = self.byte(start)..self.byte(self.cursor);
+                self.text.replace_range(range, "");
+                self.cursor = start;
+                return Edit::Changed;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.insert(&c.to_string());
+                return Edit::Changed;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.byte(self.cursor));
+                return Edit::Changed;
+            }
+            KeyCode::Delete if self.cursor < len => {
+                self.text.remove(self.byte(self.cursor));
+                return Edit::Changed;
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            KeyCode::Up => return self.browse(true),
+            KeyCode::Down => return self.browse(false),
+            KeyCode::Enter => {
+                let text = std::mem::take(&mut self.text);
+                if !text.is_empty() && self.history.last() != Some(&text) {
+                    self.history.push(text.clone());
+                }
+                self.cursor = 0;
+                self.browsing = None;
+                return Edit::Submitted(text);
+            }
+            KeyCode::Esc => return Edit::Cancelled,
+            _ => return Edit::Ignored,
+        }
+        Edit::Moved
+    }
+
+    /** go back or forward in the history. going past the newest entry empties the input */
+    fn browse(&mut self, back: bool) -> Edit {
+        let index = match (self.browsing, back) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => return Edit::Ignored,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) => Some(i + 1).filter(|i| *i < self.history.len()),
+        };
+        let text = index.map_or_else(String::new, |i| self.history[i].clone());
+        self.set(&text);
+        self.browsing = index;
+        Edit::Changed
+    }
+
+    fn insert(&mut self, text: &str) {
+        let at = self.byte(self.cursor);
+        self.text.insert_str(at, text);
+        self.cursor += text.chars().count();
+    }
+
+    /** the byte offset of the char at index */
+    fn byte(&self, index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    /** the text with the cursor showing if the input is focused */
+    pub fn line(&self, prefix: &str, focused: bool) -> Line<'_> {
+        if !focused {
+            return Line::from(vec![Span::raw(prefix.to_owned()), Span::raw(&self.text)]);
+        }
+        let at = self.byte(self.cursor);
+        let (before, rest) = self.text.split_at(at);
+        let cursor_len = rest.chars().next().map_or(0, char::len_utf8);
+        let (under, after) = rest.split_at(cursor_len);
+        Line::from(vec![
+            Span::raw(prefix.to_owned()),
+            Span::raw(before),
+            Span::styled(
+                if under.is_empty() { " " } else { under },
+                Style::new().add_modifier(Modifier::REVERSED),
+            ),
+            Span::raw(after),
+        ])
+    }
+}
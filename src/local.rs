@@ -0,0 +1,225 @@
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use tokio::process::Command;
+
+use crate::{
+    command_log::Logged,
+    forge::{Checks, Forge, MergeMethod, Pull, Ref},
+    git::Remote,
+};
+
+/// no forge at all: the branches given with --local stand in for pulls, numbered in the
+/// order they were given. nothing here talks to a server
+pub struct Local {
+    branches: Vec<String>,
+    /// the branch the first one is rebased onto
+    target: String,
+}
+
+impl Local {
+    pub fn new(branches: Vec<String>, target: String) -> Local {
+        Local { branches, target }
+    }
+
+    /** the branch as a pull based on the one before it */
+    async fn branch_pull(&self, index: usize) -> anyhow::Result<Pull> {
+        let branch = &self.branches[index];
+        let base = match index {
+            0 => &self.target,
+            _ => &self.branches[index - 1],
+        };
+        // subject, author and date of the tip, the closest thing to a title
+        let output = Command::new("git")
+            .args(["log", "-1", "--format=%H%n%s%n%an%n%cI", branch])
+            .logged_output()
+            .await
+            .context("could not run git log")?;
+        if !output.status.success() {
+            return Err(anyhow!("there's no branch {branch}"));
+        }
+        let log = String::from_utf8_lossy(&output.stdout);
+        let mut lines = log.lines();
+        let sha = lines.next().unwrap_or_default().to_owned();
+        let title = lines.next().map(str::to_owned);
+        let author = lines.next().map(str::to_owned);
+        let date = lines
+            .next()
+            .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+            .map(|d| d.with_timezone(&Utc));
+        Ok(Pull {
+            number: index as u64 + 1,
+            title,
+            body: None,
+            author,
+            labels: vec![],
+            html_url: None,
+            created_at: date,
+            updated_at: date,
+            head: Ref {
+                branch: branch.clone(),
+                sha,
+            },
+            base: Ref {
+                branch: base.clone(),
+                sha: String::new(),
+            },
+            commits: None,
+            additions: None,
+            deletions: None,
+            merged: false,
+            // nothing to work out, the branches are rebased onto each other by the time they merge
+            mergeable: Some(true),
+        })
+    }
+}
+
+impl Forge for Local {
+    fn pulls<'a>(&'a self, _remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<Vec<Pull>>> {
+        async move {
+            let mut pulls = vec![];
+            for index in 0..self.branches.len() {
+                pulls.push(self.branch_pull(index).await?);
+            }
+            Ok(pulls)
+        }
+        .boxed()
+    }
+
+    fn pull<'a>(&'a self, _remote: &'a Remote, number: u64) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        async move {
+            let index = usize::try_from(number)?
+                .checked_sub(1)
+                .filter(|i| *i < self.branches.len())
+                .context(format!("there's no branch #{number}"))?;
+            let mut pull = self.branch_pull(index).await?;
+            let range = format!("{}..{}", pull.base.branch, pull.head.branch);
+            let output = Command::new("git")
+                .args(["rev-list", "--count", &range])
+                .logged_output()
+                .await?;
+            pull.commits = String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+            Ok(pull)
+        }
+        .boxed()
+    }
+
+    fn default_branch<'a>(&'a self, _remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move { Ok(self.target.clone()) }.boxed()
+    }
+
+    fn check_access<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        _read_only: bool,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async { Ok(()) }.boxed()
+    }
+
+    fn login<'a>(&'a self, _remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        // there's no account, the user of the repo is the closest thing to one
+        async move {
+            let output = Command::new("git")
+                .args(["config", "user.name"])
+                .logged_output()
+                .await?;
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        }
+        .boxed()
+    }
+
+    fn retarget<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        _number: u64,
+        _base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        // the bases only exist in the chain marge keeps
+        async { Ok(()) }.boxed()
+    }
+
+    fn open_pull<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        _title: String,
+        _body: String,
+        head: &'a str,
+        _base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        async move {
+            Err(anyhow!(
+                "can't open a pull for {head} without a forge, push it and open one by hand"
+            ))
+        }
+        .boxed()
+    }
+
+    fn merge<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        number: u64,
+        _method: MergeMethod,
+    ) -> BoxFuture<'a, anyhow::Result<Option<String>>> {
+        async move { Err(anyhow!("can't merge #{number} without a forge")) }.boxed()
+    }
+
+    fn update_branch<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        number: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move { Err(anyhow!("can't update #{number} without a forge")) }.boxed()
+    }
+
+    fn dispatch_workflow<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        workflow: &'a str,
+        _branch: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            Err(anyhow!(
+                "can't dispatch the workflow {workflow} without a forge"
+            ))
+        }
+        .boxed()
+    }
+
+    fn workflow_run<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        workflow: &'a str,
+        _sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        async move {
+            Err(anyhow!(
+                "can't look up runs of the workflow {workflow} without a forge"
+            ))
+        }
+        .boxed()
+    }
+
+    fn checks<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        _sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        async { Ok(Checks::Missing) }.boxed()
+    }
+
+    fn behind_by<'a>(
+        &'a self,
+        _remote: &'a Remote,
+        base: &'a str,
+        head: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        async move {
+            let output = Command::new("git")
+                .args(["rev-list", "--count", &format!("{head}..{base}")])
+                .logged_output()
+                .await?;
+            Ok(String::from_utf8_lossy(&output.stdout).trim().parse()?)
+        }
+        .boxed()
+    }
+}
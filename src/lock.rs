@@ -0,0 +1,80 @@
+use std::{
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    process::Stdio,
+};
+
+use anyhow::{anyhow, Context};
+use log::warn;
+use tokio::process::Command;
+
+use crate::command_log::Logged;
+
+/// the lockfile in the repo's git dir with the pid of the marge working in it, so a second one
+/// doesn't rebase the branches from under the first. removed again when it's dropped, which
+/// covers quitting, failing and panicking
+pub struct RepoLock {
+    path: PathBuf,
+}
+
+/** take the lock of the repo in the current wd, taking over locks of processes that are gone */
+pub async fn acquire() -> anyhow::Result<RepoLock> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-common-dir"])
+        .logged_output()
+        .await?;
+    let path = PathBuf::from(std::str::from_utf8(&output.stdout)?.trim()).join("marge.lock");
+    loop {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                writeln!(file, "{}", std::process::id())?;
+                return Ok(RepoLock { path });
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let pid = std::fs::read_to_string(&path).unwrap_or_default();
+                let pid = pid.trim();
+                if alive(pid).await {
+                    return Err(anyhow!(
+                        "marge is already running in this repo as pid {pid}. if it isn't, remove {}",
+                        path.display()
+                    ));
+                }
+                warn!("taking over the lock of pid {pid}, which is gone");
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("could not remove {}", path.display()))?;
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("could not create {}", path.display()))
+            }
+        }
+    }
+}
+
+/** whether there's a process with the pid. an empty or garbled lock counts as gone */
+async fn alive(pid: &str) -> bool {
+    if pid.is_empty() || !pid.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    Command::new("kill")
+        .args(["-0", pid])
+        .stderr(Stdio::null())
+        .logged_status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        let ours = std::fs::read_to_string(&self.path)
+            .is_ok_and(|pid| pid.trim() == std::process::id().to_string());
+        if ours {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("could not remove {}: {e}", self.path.display());
+            }
+        }
+    }
+}
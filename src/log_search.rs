@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+
+use crate::{
+    events::AppEvent,
+    git::ActivePane,
+    input::{Edit, Input},
+};
+
+/// a `/` search over everything that was logged so far.
+///
+/// tui-logger doesn't let us look into its buffer, so the log is mirrored
+/// into a file and the search runs over that.
+pub struct LogSearch {
+    path: PathBuf,
+    query: Input,
+    editing: bool,
+    matches: Vec<String>,
+}
+
+impl LogSearch {
+    pub fn new(path: PathBuf) -> Self {
+        LogSearch {
+            path,
+            query: Input::new(),
+            editing: false,
+            matches: vec![],
+        }
+    }
+
+    /// whether there's anything to show below the log
+    pub fn is_active(&self) -> bool {
+        self.editing || !self.query.is_empty()
+    }
+
+    pub fn matches(&self) -> &[String] {
+        &self.matches
+    }
+
+    pub fn title(&self) -> Line<'_> {
+        let mut title = self.query.line("/", self.editing);
+        title
+            .spans
+            .push(Span::raw(format!(" ({} matches)", self.matches.len())));
+        title
+    }
+
+    /** handle a key meant for the search. returns true if nobody else should see it */
+    pub fn handle(&mut self, pane: &ActivePane, event: &AppEvent) -> bool {
+        if let AppEvent::Tick = event {
+            if self.is_active() {
+                self.refresh();
+            }
+            return false;
+        }
+        if self.editing {
+            match self.query.handle(event) {
+                Edit::Ignored => return matches!(event, AppEvent::Input(_)),
+                Edit::Moved => (),
+                Edit::Changed => self.refresh(),
+                Edit::Submitted(query) => {
+                    // entering takes the query out of the input, it stays as the search
+                    self.query.set(&query);
+                    self.editing = false;
+                }
+                Edit::Cancelled => self.clear(),
+            }
+            return true;
+        }
+        let AppEvent::Input(KeyEvent { code, .. }) = event else {
+            return false;
+        };
+
+        match (pane, code) {
+            (ActivePane::Log, KeyCode::Char('/')) => {
+                self.clear();
+                self.editing = true;
+                true
+            }
+            (ActivePane::Log, KeyCode::Esc) if !self.query.is_empty() => {
+                self.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /** run the search again, picking up lines logged since the last run */
+    pub fn refresh(&mut self) {
+        if self.query.is_empty() {
+            self.matches.clear();
+            return;
+        }
+        let query = self.query.text().to_ascii_lowercase();
+        let log = std::fs::read_to_string(&self.path).unwrap_or_default();
+        self.matches = log
+            .lines()
+            .filter(|l| l.to_ascii_lowercase().contains(&query))
+            .map(str::to_owned)
+            .collect();
+    }
+
+    fn clear(&mut self) {
+        self.query.clear();
+        self.editing = false;
+        self.matches.clear();
+    }
+
+    /** split a matching line so the matched parts stand out */
+    pub fn highlight<'a>(&self, line: &'a str) -> Line<'a> {
+        // ascii lowercasing keeps the byte offsets intact
+        let lower = line.to_ascii_lowercase();
+        let query = self.query.text().to_ascii_lowercase();
+        let mut spans = vec![];
+        let mut last = 0;
+        for (start, _) in lower.match_indices(&query) {
+            spans.push(Span::raw(&line[last..start]));
+            spans.push(Span::styled(
+                &line[start..start + query.len()],
+                Style::new().add_modifier(Modifier::REVERSED),
+            ));
+            last = start + query.len();
+        }
+        spans.push(Span::raw(&line[last..]));
+        Line::from(spans)
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+use crate::{git::save_token, network, settings::Settings, AppArgs};
+
+/// what github answers when asked for a device code
+#[derive(Debug, Deserialize)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// the answer to polling for the token. until the user is done, there's only an error
+#[derive(Debug, Deserialize)]
+struct TokenPoll {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+    /// sent with slow_down, the new minimum interval
+    interval: Option<u64>,
+}
+
+/** authorize marge with github's device flow: show a code for the user to enter in the browser, then poll until they did and store the token */
+pub async fn device(args: &AppArgs) -> anyhow::Result<()> {
+    let settings = Settings::load(args.config.as_deref()).await?;
+    let Some(client_id) = settings.login.client_id else {
+        return Err(anyhow!(
+            "logging in with --device needs client_id in the [login] table of the config"
+        ));
+    };
+    let client = network::http_client(&settings.network)?;
+
+    let code: DeviceCode = client
+        .post("https://github.com/login/device/code")
+        .header("Accept", "application/json")
+        .form(&[("client_id", client_id.as_str()), ("scope", "repo")])
+        .send()
+        .await
+        .context("could not ask github for a device code")?
+        .error_for_status()?
+        .json()
+        .await
+        .context("github sent no device code")?;
+    eprintln!(
+        "open {} and enter the code {}",
+        code.verification_uri, code.user_code
+    );
+
+    let mut interval = Duration::from_secs(code.interval);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(code.expires_in);
+    loop {
+        tokio::time::sleep(interval).await;
+        if tokio::time::Instant::now() > deadline {
+            return Err(anyhow!("the code expired before it was entered"));
+        }
+        let poll: TokenPoll = client
+            .post("https://github.com/login/oauth/access_token")
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", client_id.as_str()),
+                ("device_code", code.device_code.as_str()),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ])
+            .send()
+            .await
+            .context("could not poll github for the token")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("could not parse github's answer")?;
+        match (poll.access_token, poll.error.as_deref()) {
+            (Some(token), _) => {
+                save_token(&args.token, &token).await?;
+                eprintln!("logged in, saved the token to {}", args.token);
+                return Ok(());
+            }
+            (None, Some("authorization_pending")) => {}
+            (None, Some("slow_down")) => {
+                interval = poll
+                    .interval
+                    .map_or(interval + Duration::from_secs(5), Duration::from_secs);
+            }
+            (None, error) => {
+                return Err(anyhow!(
+                    "github refused the login: {}",
+                    poll.error_description
+                        .as_deref()
+                        .or(error)
+                        .unwrap_or("no reason given")
+                ))
+            }
+        }
+    }
+}
@@ -1,24 +1,64 @@
-use std::{io::Stdout, process::Termination};
+use std::{
+    io::{Stdout, Write},
+    path::PathBuf,
+    process::Termination,
+};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+mod audit;
+mod azure;
+mod blame;
+mod changelog;
+mod clipboard;
+mod command_log;
 pub mod events;
+mod forge;
 mod git;
+mod gitea;
+mod github;
+mod history;
+mod hooks;
+mod input;
+mod local;
+mod lock;
+mod log_search;
+mod login;
 pub mod merge_candidate;
-use git::{ActivePane, AppState, SortingState};
-use log::{info, LevelFilter};
+mod network;
+mod notify;
+mod observer;
+mod plain;
+mod plan;
+mod preflight;
+mod remote;
+mod remote_pick;
+mod session;
+mod settings;
+mod status;
+mod status_bar;
+mod tabs;
+mod task;
+mod theme;
+mod ui;
+mod validation_log;
+mod viewer;
+mod worktree;
+use git::{AppState, FailureKind, PaneScroll, SigningMode, Strategy};
+use log::{debug, info, LevelFilter};
 
 use crate::{
     events::{AppEvent, EventPump},
     git::Marge,
+    history::Recorder,
+    lock::RepoLock,
+    log_search::LogSearch,
+    preflight::Diagnostics,
+    settings::Settings,
+    status_bar::StatusBar,
+    tabs::Tabs,
 };
-use crossterm::event::{KeyCode, KeyEvent};
-use tui_logger::{TuiLoggerWidget, TuiWidgetEvent};
 
-use ratatui::{
-    prelude::*,
-    terminal::CompletedFrame,
-    widgets::{block::Block, Borders, Paragraph},
-};
+use ratatui::{prelude::*, terminal::CompletedFrame};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about)]
@@ -40,311 +80,755 @@ use ratatui::{
 ///
 /// if any step fails, marge will pause and notify so you can fix your stuff
 /// before telling her to continue.
+#[command(args_conflicts_with_subcommands = true)]
 pub struct AppArgs {
-    #[arg(long, short, default_value = "main")]
-    /// the branch to rebase the PR chain onto
-    branch: String,
-    #[arg(long, short, default_value = ".token")]
-    /// file to read the github API token from
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    run: RunArgs,
+    #[arg(long, short, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    /// log more, including the raw output of every command. give twice for trace logs
+    verbose: u8,
+    #[arg(long, short, global = true, action = clap::ArgAction::Count)]
+    /// log less, only warnings. give twice for errors only
+    quiet: u8,
+    #[arg(long, global = true)]
+    /// file to mirror the log into, to look at after marge exits. defaults to a new file
+    /// per run in ~/.local/state/marge
+    log_file: Option<PathBuf>,
+    #[arg(long, global = true)]
+    /// the settings file to use instead of ~/.config/marge/config.toml
+    config: Option<PathBuf>,
+    #[arg(long, short, global = true, default_value = ".token")]
+    /// file to read the API token from, unless the config has one for the remote's host
     token: String,
-    #[arg(long, short, default_value = "origin")]
-    /// name of the remote to pull the PRs from. not required to be overridden if there's only
-    /// one remote not named origin
-    remote: String,
+    #[arg(long, short, global = true)]
+    /// name of the remote to pull the PRs from. marge asks which one if there are several,
+    /// commands without a screen use origin then
+    remote: Option<String>,
+    #[arg(long, global = true)]
+    /// print what marge does line by line and read the answers to its questions from stdin,
+    /// instead of drawing a screen. for screen readers and dumb terminals. type the key marge
+    /// asks for and press enter, or name it like space or esc
+    plain: bool,
+    #[arg(long, global = true, conflicts_with = "plain")]
+    /// draw in the terminal's own screen instead of the alternate one, for tmux panes and
+    /// terminals that handle the alternate screen badly
+    no_altscreen: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// sort the PRs, then rebase, validate and merge them. what marge does without a subcommand
+    Run(RunArgs),
+    /// only sort the PRs and save the chain, to run it later with `marge run --plan`
+    Plan {
+        /// the file to save the chain to
+        file: PathBuf,
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// print the open PRs as the stacks they form, with their checks and whether they're
+    /// up to date with their base, without touching the repo
+    Status,
+    /// point the PRs a run retargeted back at the bases they had before it, for cleaning up
+    /// after an aborted run
+    Undo {
+        /// the id of the run to undo, as shown in the audit log. defaults to the last run
+        /// that retargeted anything in this repo
+        run: Option<String>,
+    },
+    /// run a chain saved with --save-plan again. with --merge-only, an earlier run already
+    /// rebased and pushed it and only merging is left
+    Resume {
+        /// the plan the earlier run saved
+        #[arg(value_name = "PLAN")]
+        file: PathBuf,
+        /// go straight to merging the chain, without rebasing, validating or pushing it again
+        #[arg(long)]
+        merge_only: bool,
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// list the finished runs of all repos, or show what one of them did
+    History {
+        /// the id of the run to show, as listed
+        run: Option<String>,
+    },
+    /// store a github API token in the --token file
+    Login {
+        /// authorize marge in the browser instead of pasting a token. needs the client id
+        /// of an oauth app in the config
+        #[arg(long)]
+        device: bool,
+    },
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct RunArgs {
+    #[arg(long, short)]
+    /// the branch to rebase the PR chain onto. the repo's default branch if not given
+    branch: Option<String>,
+    #[arg(long, short)]
+    /// pick the branch to rebase the PR chain onto from the remote's branches
+    /// before starting, with --branch preselected
+    pick_branch: bool,
+    #[arg(long)]
+    /// after merging, cherry-pick the chain onto this branch and open backport PRs
+    /// for it. can be given multiple times
+    backport: Vec<String>,
+    #[arg(long, requires = "backport")]
+    /// run the backport PRs through the same pipeline and merge them, too
+    backport_merge: bool,
+    #[arg(long, short, value_enum, default_value_t = Strategy::Rebase)]
+    /// how to put each PR onto its predecessor
+    strategy: Strategy,
+    #[arg(long)]
+    /// fold fixup! and squash! commits into their targets while rebasing each PR
+    autosquash: bool,
+    #[arg(long)]
+    /// skip the repository's git hooks when rebasing and pushing. can also be set per repo
+    /// with `git config marge.noVerify true`
+    no_verify: bool,
+    #[arg(long)]
+    /// update the submodules after rebasing each PR, before validating it. can also be set
+    /// per repo with `git config marge.submodules true`
+    submodules: bool,
+    #[arg(long)]
+    /// let git remember how conflicts were resolved and replay that when they come up again,
+    /// continuing the rebase on its own if that resolved all of them. can also be set per repo
+    /// with `git config marge.rerere true`
+    rerere: bool,
+    #[arg(long)]
+    /// don't download git LFS objects when checking out branches, only pull them right
+    /// before validating
+    no_lfs: bool,
+    #[arg(long, value_enum, default_value_t = SigningMode::Auto)]
+    /// whether git gets the terminal to prompt for a signing passphrase when creating commits
+    signing: SigningMode,
+    #[arg(long)]
+    /// ask before every destructive step: retargeting, force-pushing and merging each PR
+    confirm: bool,
+    #[arg(long, conflicts_with = "local")]
+    /// merge each PR as soon as it's pushed and its checks pass, and rebase the next one
+    /// onto the updated target branch instead of onto its predecessor
+    rolling: bool,
+    #[arg(long, conflicts_with = "rolling")]
+    /// experimental: validate each rebased PR in a git worktree of its own while the next one is
+    /// rebased, and push them in order once their validations pass
+    parallel: bool,
+    #[arg(long, value_name = "WORKFLOW", conflicts_with_all = ["local", "parallel"])]
+    /// instead of running CMD, push each rebased PR and validate it with this github actions
+    /// workflow, e.g. ci.yml. it needs a workflow_dispatch trigger
+    workflow: Option<String>,
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    /// run a failed validation up to N more times before waiting for a fix, for flaky tests
+    validate_retries: u32,
+    #[arg(long, conflicts_with_all = ["parallel", "workflow"])]
+    /// show the range-diff of each validated PR against its head before the rebase and wait
+    /// for y before force-pushing it
+    review_rebase: bool,
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["backport", "plan"])]
+    /// rebase these comma separated local branches onto each other in the given order,
+    /// validate and push them, without any pulls. nothing talks to a forge, so it works
+    /// with any git server
+    local: Vec<String>,
+    #[arg(long)]
+    /// only log the retargets, pushes and merges instead of doing them. checkouts, rebases
+    /// and validation still run locally, so marge can be tried on a repo you don't own
+    read_only: bool,
+    #[arg(long)]
+    /// show a desktop notification when marge needs you or is done. the terminal bell
+    /// rings either way, webhooks are set up in the config file
+    notify: bool,
+    #[arg(long)]
+    /// start with the chain saved in this file by --save-plan instead of sorting the PRs
+    plan: Option<PathBuf>,
+    #[arg(long)]
+    /// save the chain to this file when the run starts, to run the same stack again
+    /// later with --plan
+    save_plan: Option<PathBuf>,
+    #[arg(long)]
+    /// write a changelog of the merged PRs, grouped by label, to this file once the run is done
+    changelog: Option<PathBuf>,
+    #[arg(skip)]
+    /// set by `resume --merge-only`
+    merge_only: bool,
     #[arg(default_value = "true")]
     /// the sh command line marge should run to validate each rebased branch
     cmd: String,
 }
 
+impl AppArgs {
+    /// the subcommand to run, running the merge is the default
+    fn command(&self) -> Command {
+        match &self.command {
+            Some(Command::Run(run)) => Command::Run(run.clone()),
+            Some(Command::Plan { file, run }) => Command::Plan {
+                file: file.clone(),
+                run: run.clone(),
+            },
+            Some(Command::Resume {
+                file,
+                merge_only,
+                run,
+            }) => Command::Resume {
+                file: file.clone(),
+                merge_only: *merge_only,
+                run: run.clone(),
+            },
+            Some(Command::Status) => Command::Status,
+            Some(Command::Undo { run }) => Command::Undo { run: run.clone() },
+            Some(Command::History { run }) => Command::History { run: run.clone() },
+            Some(Command::Login { device }) => Command::Login { device: *device },
+            None => Command::Run(self.run.clone()),
+        }
+    }
+
+    /// info by default, moved up and down by -v and -q
+    fn log_level(&self) -> LevelFilter {
+        match i16::from(self.verbose) - i16::from(self.quiet) {
+            i16::MIN..=-3 => LevelFilter::Off,
+            -2 => LevelFilter::Error,
+            -1 => LevelFilter::Warn,
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            2..=i16::MAX => LevelFilter::Trace,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AppConfig {
     args: AppArgs,
-    token: String,
+    run: RunArgs,
+    /// stop once the chain is sorted and saved
+    plan_only: bool,
+    settings: Settings,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<Screen> {
-    let mut marge = Marge::try_init().await?;
-    let mut screen: Screen = Screen::try_new()?;
-    info!("running validation against {}", marge.cmd);
-    let mut event_pump = EventPump::new(tokio::time::Duration::from_millis(150));
-
-    loop {
-        marge.last_event = if let Some(e) = event_pump.next().await {
-            e
-        } else {
-            break;
-        };
-
-        marge.try_transition().await?;
-
-        if let AppEvent::Error(e) = marge.last_event {
-            info!("recvd error: {:#?}", e);
-            return Err(e);
-        }
+/// how marge exits, from the TUI or after a subcommand that doesn't need one
+enum Exit {
+    Screen(Screen, Outcome),
+    Plain,
+}
 
-        if let AppEvent::Signal = marge.last_event {
-            break;
+impl Termination for Exit {
+    fn report(self) -> std::process::ExitCode {
+        match self {
+            Exit::Screen(mut screen, outcome) => match screen.restore() {
+                Ok(()) => std::process::ExitCode::from(outcome.code()),
+                Err(e) => {
+                    eprintln!("{e:?}");
+                    std::process::ExitCode::FAILURE
+                }
+            },
+            Exit::Plain => std::process::ExitCode::SUCCESS,
         }
-
-        screen.draw(|f| draw_frame(f, &mut marge))?;
     }
-    Ok(screen)
 }
 
-fn draw_frame(t: &mut Frame, marge: &mut Marge) {
-    let size = t.size();
-
-    let main_block = Block::default().borders(Borders::NONE);
-    let main_area = main_block.inner(size);
-    t.render_widget(main_block, size);
-
-    let constraints = vec![
-        Constraint::Length(3), // title line
-        Constraint::Min(10),   // content
-    ];
-
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(constraints)
-        .split(main_area);
-
-    render_title(t, marge, chunks[0]);
-    render_content(t, marge, chunks[1]);
+/// how the run ended, for scripts running marge to tell apart
+#[derive(Clone, Copy, Debug)]
+enum Outcome {
+    /// the chain was merged, or there was nothing to do
+    Success,
+    /// the user quit before the chain was done
+    Aborted,
+    /// a candidate didn't pass the validation
+    ValidationFailed,
+    /// a git command or a request to the forge failed
+    StepFailed,
 }
 
-fn render_title(t: &mut Frame, marge: &mut Marge, rect: Rect) {
-    let title_block = Block::default().borders(Borders::ALL);
-    let title_area = title_block.inner(rect);
+impl Outcome {
+    fn code(self) -> u8 {
+        match self {
+            Outcome::Success => 0,
+            Outcome::ValidationFailed => 2,
+            Outcome::StepFailed => 3,
+            Outcome::Aborted => 130,
+        }
+    }
 
-    let title = Paragraph::new(format!(
-        "Merging {}/{} ({}) into {}",
-        marge.remote.owner, marge.remote.repo, marge.remote.name, marge.branch
-    ));
-    t.render_widget(title, title_area);
-    t.render_widget(title_block, rect);
-}
+    fn name(self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::ValidationFailed => "validation failed",
+            Outcome::StepFailed => "step failed",
+            Outcome::Aborted => "aborted",
+        }
+    }
 
-fn render_content(t: &mut Frame, marge: &mut Marge, rect: Rect) {
-    let constraints = vec![
-        Constraint::Percentage(50), // lists
-        Constraint::Percentage(50), // log
-    ];
-
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(constraints)
-        .split(rect);
-
-    if let AppEvent::Input(KeyEvent {
-        code: KeyCode::Left | KeyCode::Right,
-        ..
-    }) = marge.last_event
-    {
-        marge.active_pane = if marge.active_pane == ActivePane::List {
-            ActivePane::Log
-        } else {
-            ActivePane::List
+    /** the outcome if the run ended in the state, None if it isn't an outcome by itself */
+    fn of(state: &AppState) -> Option<Outcome> {
+        match state {
+            AppState::Done => Some(Outcome::Success),
+            AppState::WaitingForFix(_) => Some(Outcome::ValidationFailed),
+            AppState::Failed(f) if f.kind == FailureKind::Validation => {
+                Some(Outcome::ValidationFailed)
+            }
+            AppState::Failed(_) => Some(Outcome::StepFailed),
+            _ => None,
         }
     }
 
-    render_app(t, marge, chunks[0]);
-    render_log(t, marge, chunks[1]);
-}
+    /** the failure the run would end with after getting into the state, given the one before.
+    winding down after a failure keeps it, any other step means it was retried or left behind */
+    fn after(failure: Option<Outcome>, state: &AppState) -> Option<Outcome> {
+        match (Outcome::of(state), state) {
+            (Some(Outcome::Success), _) => None,
+            (Some(outcome), _) => Some(outcome),
+            (None, AppState::Aborting(_) | AppState::Aborted | AppState::UndoingRetargets(_)) => {
+                failure
+            }
+            (None, _) => None,
+        }
+    }
 
-fn render_app(t: &mut Frame, marge: &mut Marge, rect: Rect) {
-    let style = if marge.active_pane == ActivePane::List {
-        Style::new()
-    } else {
-        Style::new().fg(Color::DarkGray)
-    };
+    /** how the run ended in the state, with the failure it was in before if it's aborting after it */
+    fn ended(state: &AppState, failure: Option<Outcome>) -> Outcome {
+        Outcome::of(state).or(failure).unwrap_or(Outcome::Aborted)
+    }
+}
 
-    let lists_block = Block::default()
-        .title("App")
-        .border_style(style)
-        .style(style)
-        .borders(Borders::ALL);
-    let lists_area = lists_block.inner(rect);
-
-    let content: String = match marge.app_state.as_ref() {
-        AppState::Failed => "<failed>".to_owned(),
-        AppState::CheckingRepo(_) => "checking repo...".to_owned(),
-        AppState::WaitingForCleanRepo => "cleanup repo, then press space".to_owned(),
-        AppState::CheckingOutTargetBranch(_) => format!("checking out {}", marge.branch),
-        AppState::PullingRemote(_) => "pulling current state from remote...".to_owned(),
-        AppState::GettingPulls => "gettin pulls...".to_owned(),
-        AppState::WaitingForSort(state) => format_candidates(state),
-        AppState::UpdatingCandidate(s) => format!(
-            "retargeting pr {} onto {}",
-            s.current_checkout.pull.head.ref_field,
-            s.done
-                .last()
-                .map(|c| c.pull.head.ref_field.clone())
-                .unwrap_or(marge.branch.clone())
-        ),
-        AppState::CheckingOutCandidate(..) => "checkin out!".to_owned(),
-        AppState::RebaseCandidate(..) => "rebasing :)".to_owned(),
-        AppState::CheckingForConflicts(..) => "checkin for conflicts :D".to_owned(),
-        AppState::WaitingForResolution(..) => {
-            "resolve conflicts, then press space to rebase continue".to_owned()
+#[tokio::main]
+async fn main() -> anyhow::Result<Exit> {
+    let args = AppArgs::try_parse()?;
+    let (run, plan_only) = match args.command() {
+        Command::Status => return status::print(&args).await.map(|()| Exit::Plain),
+        Command::Undo { run } => {
+            let (forge, remote) = git::connect(&args).await?;
+            forge.check_access(&remote, false).await?;
+            audit::init(format!("{}/{}", remote.owner, remote.repo))?;
+            for outcome in git::undo_retargets(&*forge, &remote, run.as_deref()).await? {
+                println!("{outcome}");
+            }
+            return Ok(Exit::Plain);
+        }
+        Command::History { run } => return history::print(run.as_deref()).map(|()| Exit::Plain),
+        Command::Login { device: false } => {
+            return git::login(&args.token).await.map(|()| Exit::Plain)
+        }
+        Command::Login { device: true } => return login::device(&args).await.map(|()| Exit::Plain),
+        Command::Run(run) => (run, false),
+        Command::Plan { file, mut run } => {
+            run.save_plan = Some(file);
+            (run, true)
+        }
+        Command::Resume {
+            file,
+            merge_only,
+            mut run,
+        } => {
+            run.plan = Some(file);
+            run.merge_only = merge_only;
+            (run, false)
         }
-        AppState::Validating(..) => "validation".to_owned(),
-        AppState::WaitingForFix(..) => "fix validation, then press space".to_owned(),
-        AppState::PushingCandidate(..) => "pushing".to_owned(),
-        AppState::Merging(..) => "merging".to_owned(),
-        AppState::Done => "<all done>".to_owned(),
     };
-    let lists = Paragraph::new(content);
-    t.render_widget(lists, lists_area);
-    t.render_widget(lists_block, rect);
+    run_tui(args, run, plan_only)
+        .await
+        .map(|(screen, outcome)| Exit::Screen(screen, outcome))
 }
 
-fn format_candidates(state: &SortingState) -> String {
-    let chain_section = if state.merge_chain.is_empty() {
-        "<no pulls selected>".to_owned()
-    } else {
-        state
-            .merge_chain
-            .iter()
-            .map(|c| {
-                if let Some(title) = c.pull.title.clone() {
-                    format!(
-                        "Pull #{}: {}\n  {}",
-                        c.pull.number, c.pull.head.ref_field, title
-                    )
-                } else {
-                    format!("<no title on {}>", c.pull.number)
-                }
+/** make sure marge can run at all, lock the repo, let the user pick the remote if there are
+several and set marge up. if something's missing, the diagnostics say what before the error is
+handed back */
+async fn start(
+    mut args: AppArgs,
+    run: RunArgs,
+    plan_only: bool,
+    events: &mut EventPump,
+) -> anyhow::Result<(Marge, Screen, RepoLock)> {
+    let log_level = args.log_level();
+    let plain = args.plain;
+    let altscreen = !args.no_altscreen;
+    let local = !run.local.is_empty();
+    let mut diagnostics = Diagnostics::default();
+    let mut remotes = vec![];
+    diagnostics
+        .check("finding git", preflight::git_installed())
+        .await;
+    diagnostics
+        .check("finding the repo", preflight::in_repo())
+        .await;
+    let mut lock = None;
+    diagnostics
+        .check("locking the repo", async {
+            lock = Some(lock::acquire().await?);
+            Ok(())
+        })
+        .await;
+    if !local {
+        diagnostics
+            .check("finding the remotes", async {
+                remotes = git::get_remotes().await?;
+                Ok(())
             })
-            .collect::<Vec<String>>()
-            .join("\n")
+            .await;
+    }
+
+    // the screen only comes up before marge is set up if there's something to show
+    let mut screen = None;
+    if !diagnostics.failed() && !plain && args.remote.is_none() && remotes.len() > 1 {
+        let mut picking = Screen::try_new(log_level, altscreen)?;
+        match remote_pick::pick(&mut picking, events, std::mem::take(&mut remotes)).await {
+            Ok(remote) => args.remote = Some(remote.name),
+            Err(e) => {
+                picking.restore()?;
+                return Err(e);
+            }
+        }
+        screen = Some(picking);
+    }
+    let name = match (&args.remote, remotes.as_slice()) {
+        (Some(name), _) => name.clone(),
+        (None, [remote]) => remote.name.clone(),
+        (None, _) => "origin".to_owned(),
     };
+    diagnostics
+        .check("reaching the remote", preflight::reachable(&name))
+        .await;
+    let mut marge = None;
+    diagnostics
+        .check("connecting to the forge", async {
+            marge = Some(Marge::try_init(events.sender(), args, run, plan_only).await?);
+            Ok(())
+        })
+        .await;
+
+    match (marge, screen, lock) {
+        (Some(marge), Some(screen), Some(lock)) => Ok((marge, screen, lock)),
+        (Some(marge), None, Some(lock)) => {
+            let screen = if plain {
+                Screen::plain(marge.log_level)
+            } else {
+                Screen::try_new(marge.log_level, altscreen)?
+            };
+            Ok((marge, screen, lock))
+        }
+        _ if plain => Err(diagnostics.into_error()),
+        (_, screen, _) => {
+            let screen = match screen {
+                Some(screen) => Ok(screen),
+                None => Screen::try_new(log_level, altscreen),
+            };
+            // without a terminal to show them on, the error goes to the shell right away
+            let Ok(mut screen) = screen else {
+                return Err(diagnostics.into_error());
+            };
+            let e = diagnostics.show(&mut screen, events).await;
+            screen.restore()?;
+            Err(e)
+        }
+    }
+}
 
-    let unsorted_section = if state.unsorted.is_empty() {
-        "<no pulls remaining>".to_owned()
+async fn run_tui(
+    args: AppArgs,
+    run: RunArgs,
+    plan_only: bool,
+) -> anyhow::Result<(Screen, Outcome)> {
+    // a broken config is reported once marge is set up, the events go on with the defaults
+    let events = Settings::load(args.config.as_deref())
+        .await
+        .map(|settings| settings.events)
+        .unwrap_or_default();
+    let tick_rate = tokio::time::Duration::from_millis(events.tick_ms.max(1));
+    let mut event_pump = if args.plain {
+        EventPump::plain(tick_rate, events.capacity)
     } else {
-        state
-            .unsorted
-            .iter()
-            .enumerate()
-            .map(|(i, c)| {
-                let brk = if state.current_index == i {
-                    "\n>> "
-                } else {
-                    "\n "
-                };
-
-                if let Some(title) = c.pull.title.clone() {
-                    format!(
-                        "{brk}Pull #{}: {}{brk}  {title}",
-                        c.pull.number, c.pull.head.ref_field
-                    )
-                } else {
-                    format!("{}<no title on {}>", brk, c.pull.number)
-                }
-            })
-            .collect::<String>()
+        EventPump::new(tick_rate, events.capacity)
     };
-
-    format!("Merge Chain:\n{chain_section}\n\n=====\n\n Remaining Pulls:\n{unsorted_section}")
+    // held until the run is over, however it ends
+    let (mut marge, mut screen, _lock) = start(args, run, plan_only, &mut event_pump).await?;
+    match run_loop(&mut marge, &mut screen, &mut event_pump).await {
+        Ok(outcome) => Ok((screen, outcome)),
+        Err(e) => {
+            // the shell gets its terminal back before the error is printed
+            if let Err(restoring) = screen.restore() {
+                eprintln!("{restoring:?}");
+            }
+            Err(e)
+        }
+    }
 }
 
-fn render_log(t: &mut Frame, marge: &mut Marge, rect: Rect) {
-    let style = if marge.active_pane == ActivePane::Log {
-        let maybe_event = match marge.last_event {
-            AppEvent::Input(KeyEvent {
-                code: KeyCode::Up, ..
-            }) => Some(TuiWidgetEvent::PrevPageKey),
-            AppEvent::Input(KeyEvent {
-                code: KeyCode::Down,
-                ..
-            }) => Some(TuiWidgetEvent::NextPageKey),
-            AppEvent::Input(KeyEvent {
-                code: KeyCode::Char(' '),
-                ..
-            }) => Some(TuiWidgetEvent::EscapeKey),
-            // fixme remove
-            AppEvent::Input(KeyEvent {
-                code: KeyCode::Char(c),
-                ..
-            }) => {
-                info!("{}", c);
-                None
+/** drive marge with the events until the run is over, drawing it as it goes */
+async fn run_loop(
+    marge: &mut Marge,
+    screen: &mut Screen,
+    event_pump: &mut EventPump,
+) -> anyhow::Result<Outcome> {
+    if let Some(dir) = marge.log_file.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tui_logger::set_log_file(&marge.log_file.to_string_lossy())?;
+    let mut search = LogSearch::new(marge.log_file.clone());
+    let mut tabs = Tabs::new();
+    // the last thing that went wrong, if the user quits after it
+    let mut failure = None;
+    let mut recorder = Recorder::new();
+    // when the screen was last drawn
+    let mut drawn = std::time::Instant::now();
+    let mut status_bar = StatusBar::new(format!("{}/{}", marge.remote.owner, marge.remote.repo));
+    info!("logging to {}", marge.log_file.display());
+    if let Screen::Plain(_) = screen {
+        println!("logging to {}", marge.log_file.display());
+    }
+    info!("running validation against {}", marge.cmd);
+
+    loop {
+        marge.last_event = match event_pump.next().await {
+            Some(AppEvent::Foreground(foreground)) => {
+                screen.suspend()?;
+                event_pump.pause();
+                let _ = foreground.granted.send(());
+                let _ = foreground.done.await;
+                event_pump.resume();
+                screen.resume()?;
+                AppEvent::Tick
             }
-            _ => None,
+            Some(AppEvent::GitDone(op, result)) => {
+                // the state that started it picks up the result in the transition below
+                debug!("{op} finished: {result:?}");
+                AppEvent::GitDone(op, result)
+            }
+            Some(e) => e,
+            None => break,
         };
 
-        if let Some(e) = maybe_event {
-            marge.log_state.transition(&e);
+        let panes = !matches!(screen, Screen::Plain(_));
+        if panes && search.handle(&marge.active_pane, &marge.last_event) {
+            // the key went into the search, the app and the log pane don't get to see it
+            marge.last_event = AppEvent::Tick;
+        }
+        if panes
+            && marge.viewer.is_none()
+            && !marge.app_state.typing()
+            && tabs.handle(&marge.last_event, &mut marge.clipboard)
+        {
+            // switching tabs or scrolling one that isn't the chain doesn't drive the run
+            marge.last_event = AppEvent::Tick;
         }
 
-        Style::new()
-    } else {
-        let e = TuiWidgetEvent::EscapeKey;
-        marge.log_state.transition(&e);
-        Style::new().fg(Color::DarkGray)
-    };
+        let before = std::mem::discriminant(marge.app_state.as_ref());
+        marge.try_transition().await?;
+        if let AppEvent::Tick = marge.last_event {
+            marge.ticks += 1;
+        }
+        let changed = before != std::mem::discriminant(marge.app_state.as_ref());
+        if changed {
+            failure = Outcome::after(failure, marge.app_state.as_ref());
+            marge.state_since = std::time::Instant::now();
+            recorder.update(marge);
+            marge.app_scroll = PaneScroll::default();
+            if let Some(message) = marge.app_state.attention() {
+                screen.bell()?;
+                let state = marge.app_state.as_ref();
+                marge.notifier.notify(state, &marge.branch, message);
+            }
+        }
 
-    let tui_w: TuiLoggerWidget = TuiLoggerWidget::default()
-        .block(
-            Block::default()
-                .title("Logs")
-                .border_style(style)
-                .title_style(style)
-                .style(style)
-                .borders(Borders::ALL),
-        )
-        .output_separator(' ')
-        .output_timestamp(Some("%H:%M".to_string()))
-        .output_level(None)
-        .output_target(false)
-        .output_file(false)
-        .output_line(false)
-        .state(&marge.log_state);
-
-    t.render_widget(tui_w, rect);
+        if let AppEvent::Error(_) = marge.last_event {
+            let AppEvent::Error(e) = std::mem::replace(&mut marge.last_event, AppEvent::Tick)
+            else {
+                unreachable!("just matched an error");
+            };
+            info!("recvd error: {:#?}", e);
+            return Err(e);
+        }
+
+        if let AppEvent::Signal = marge.last_event {
+            break;
+        }
+
+        if let AppState::Aborted = marge.app_state.as_ref() {
+            break;
+        }
+
+        status_bar.update(&marge.last_event);
+        match screen {
+            // ticks only move the spinner and the clocks, they wait for the frame budget
+            Screen::Tui(..)
+                if changed
+                    || !matches!(marge.last_event, AppEvent::Tick)
+                    || drawn.elapsed() >= marge.frame_budget =>
+            {
+                screen.draw(|f| ui::draw_frame(f, marge, &search, &status_bar, &mut tabs))?;
+                drawn = std::time::Instant::now();
+            }
+            Screen::Tui(..) => (),
+            Screen::Plain(printer) => printer.print(marge)?,
+        }
+    }
+    let outcome = Outcome::ended(marge.app_state.as_ref(), failure);
+    recorder.update(marge);
+    recorder.finish(marge, outcome.name());
+    Ok(outcome)
 }
 
-struct Screen(Terminal<CrosstermBackend<Stdout>>);
+/// where marge shows what it does
+enum Screen {
+    /// the panes, drawn on the alternate screen unless it's false
+    Tui(Terminal<CrosstermBackend<Stdout>>, bool),
+    /// line after line on stdout, for --plain
+    Plain(plain::Printer),
+}
 
 impl Screen {
-    pub fn try_new() -> anyhow::Result<Self> {
-        tui_logger::init_logger(LevelFilter::Trace).unwrap();
-        tui_logger::set_default_level(LevelFilter::Trace);
-
+    pub fn try_new(log_level: LevelFilter, altscreen: bool) -> anyhow::Result<Self> {
+        init_logger(log_level);
         crossterm::terminal::enable_raw_mode()?;
         let mut stdout = std::io::stdout();
-        crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+        if altscreen {
+            crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+        }
+        crossterm::execute!(stdout, crossterm::event::EnableBracketedPaste)?;
 
         let backend = ratatui::backend::CrosstermBackend::new(stdout);
-        let terminal = Terminal::new(backend)?;
-        Ok(Screen(terminal))
+        let mut terminal = Terminal::new(backend)?;
+        if !altscreen {
+            // whatever the shell printed before would show through the parts marge doesn't draw
+            terminal.clear()?;
+        }
+        Ok(Screen::Tui(terminal, altscreen))
+    }
+
+    /** leave the terminal as it is, the log only goes into the log file */
+    pub fn plain(log_level: LevelFilter) -> Self {
+        init_logger(log_level);
+        Screen::Plain(plain::Printer::default())
+    }
+
+    /// give the terminal back to the shell, e.g. for an interactive child process
+    pub fn suspend(&mut self) -> anyhow::Result<()> {
+        self.leave()
+    }
+
+    pub fn resume(&mut self) -> anyhow::Result<()> {
+        let Screen::Tui(terminal, altscreen) = self else {
+            return Ok(());
+        };
+        crossterm::terminal::enable_raw_mode()?;
+        if *altscreen {
+            crossterm::execute!(
+                terminal.backend_mut(),
+                crossterm::terminal::EnterAlternateScreen
+            )?;
+        }
+        crossterm::execute!(
+            terminal.backend_mut(),
+            crossterm::event::EnableBracketedPaste
+        )?;
+        terminal.clear()?;
+        Ok(())
+    }
+
+    /** ring the terminal bell. with the panes it's queued on the writer they're drawn with, so it
+    goes out with the next frame instead of in the middle of one */
+    fn bell(&mut self) -> std::io::Result<()> {
+        match self {
+            Screen::Tui(terminal, _) => {
+                crossterm::queue!(terminal.backend_mut(), crossterm::style::Print('\x07'))
+            }
+            Screen::Plain(_) => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(b"\x07")?;
+                stdout.flush()
+            }
+        }
+    }
+
+    /** give the terminal back for good when marge exits */
+    fn restore(&mut self) -> anyhow::Result<()> {
+        self.leave()
+    }
+
+    fn leave(&mut self) -> anyhow::Result<()> {
+        let Screen::Tui(terminal, altscreen) = self else {
+            return Ok(());
+        };
+        crossterm::execute!(
+            terminal.backend_mut(),
+            crossterm::event::DisableBracketedPaste
+        )?;
+        if *altscreen {
+            crossterm::execute!(
+                terminal.backend_mut(),
+                crossterm::terminal::LeaveAlternateScreen
+            )?;
+        } else {
+            // the shell gets a clean screen back, like it does when the alternate one is left
+            terminal.clear()?;
+        }
+        crossterm::terminal::disable_raw_mode()?;
+        Ok(())
     }
 
     pub fn draw<F>(&mut self, f: F) -> Result<CompletedFrame<'_>, std::io::Error>
     where
         F: FnOnce(&mut Frame),
     {
-        self.0.draw(f)
+        match self {
+            Screen::Tui(terminal, _) => terminal.draw(f),
+            Screen::Plain(_) => Err(std::io::Error::other(
+                "there's no screen to draw with --plain",
+            )),
+        }
     }
 }
 
-impl Termination for Screen {
-    fn report(mut self) -> std::process::ExitCode {
-        use crossterm::{
-            execute,
-            terminal::{disable_raw_mode, LeaveAlternateScreen},
-        };
-        use std::process::ExitCode;
-
-        if let Err(e) = execute!(self.0.backend_mut(), LeaveAlternateScreen) {
-            eprintln!("{e:?}");
-            ExitCode::FAILURE
-        } else if let Err(e) = disable_raw_mode() {
-            eprintln!("{e:?}");
-            ExitCode::FAILURE
-        } else {
-            ExitCode::SUCCESS
+fn init_logger(log_level: LevelFilter) {
+    tui_logger::init_logger(LevelFilter::Trace).unwrap();
+    // applies to the log pane and the log file alike
+    tui_logger::set_default_level(log_level);
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use clap::CommandFactory;
+
+    use super::{AppArgs, AppState, Outcome};
+    use crate::git::Failure;
+
+    /** how a run going through the states ends */
+    fn ends(states: Vec<AppState>) -> u8 {
+        let mut failure = None;
+        for state in &states {
+            failure = Outcome::after(failure, state);
         }
+        Outcome::ended(states.last().expect("a run has states"), failure).code()
+    }
+
+    fn failed() -> AppState {
+        AppState::Failed(Failure::new("pushing", anyhow!("rejected")).with_retry(|| AppState::Done))
+    }
+
+    fn aborting() -> AppState {
+        AppState::Aborting(tokio::sync::mpsc::channel(1).1)
+    }
+
+    #[test]
+    fn aborting_after_a_failure_is_the_failure() {
+        assert_eq!(ends(vec![failed(), aborting(), AppState::Aborted]), 3);
+    }
+
+    #[test]
+    fn aborting_after_a_successful_retry_is_an_abort() {
+        let states = vec![
+            failed(),
+            AppState::GettingPulls,
+            aborting(),
+            AppState::Aborted,
+        ];
+        assert_eq!(ends(states), 130);
+    }
+
+    #[test]
+    fn args_are_consistent() {
+        // AppArgs::command is the subcommand to run, this is clap's
+        <AppArgs as CommandFactory>::command().debug_assert();
     }
 }
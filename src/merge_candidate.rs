@@ -1,16 +1,167 @@
-use octocrab::models::pulls::PullRequest;
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+use crate::forge::{MergeMethod, Pull};
+
+#[derive(Debug, Clone)]
 pub struct MergeCandidate {
-    pub pull: octocrab::models::pulls::PullRequest,
+    pub pull: Pull,
+    /// squash the pull into a single commit while rebasing it
+    pub squash: bool,
+    /// how the forge merges it
+    pub merge_method: MergeMethod,
+    /// push it right after rebasing, without running the validation
+    pub skip_validation: bool,
+    /// the branch to rebase it onto instead of the pull before it, which the rest of the chain
+    /// then goes on top of
+    pub onto: Option<String>,
+    /// the fetched copy of onto that it's rebased onto, since the local branch may be behind or
+    /// have gone its own way
+    pub onto_ref: Option<String>,
+    /// only known once it was fetched, the pull list doesn't include it
+    pub stats: Option<PullStats>,
+    /// the commit the forge merged the pull as
+    pub merged_as: Option<String>,
+    /// whether merged_as was found on the base after the chain was merged, none if it wasn't looked for
+    pub landed: Option<bool>,
+    /// how far the run got with it
+    pub status: Status,
+    /// how often its validation failed and was run again by itself since the last fix
+    pub retries: u32,
+    /// the head and base the pull had before marge touched it
+    pub original_head: String,
+    pub original_base: String,
+    /// the head it was pushed with after rebasing
+    pub new_head: Option<String>,
+    /// `git range-diff` of its commits before and after rebasing, to spot what resolving conflicts changed
+    pub range_diff: Option<String>,
+    /// when marge started and stopped working on it
+    pub started: Option<Instant>,
+    pub finished: Option<Instant>,
+}
+
+/// where a candidate is in the run
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Status {
+    #[default]
+    Pending,
+    Rebased,
+    Pushed,
+    Merged,
+    /// not merged because the run is read-only
+    Skipped,
+    Failed,
+}
+
+impl Status {
+    #[must_use] pub fn name(self) -> &'static str {
+        match self {
+            Status::Pending => "pending",
+            Status::Rebased => "rebased",
+            Status::Pushed => "pushed",
+            Status::Merged => "merged",
+            Status::Skipped => "skipped",
+            Status::Failed => "failed",
+        }
+    }
+
+    /** whether nothing more happens to the candidate in this run */
+    #[must_use] pub fn is_final(self) -> bool {
+        matches!(self, Status::Merged | Status::Skipped | Status::Failed)
+    }
+}
+
+/// what the options popup of a candidate can change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CandidateOption {
+    Squash,
+    MergeMethod,
+    SkipValidation,
+    /// cycles through the bases of the open pulls
+    Base,
+}
+
+impl CandidateOption {
+    pub const ALL: [CandidateOption; 4] = [CandidateOption::Squash, CandidateOption::MergeMethod, CandidateOption::SkipValidation, CandidateOption::Base];
+}
+
+/// the size of a pull
+#[derive(Debug, Clone, Copy)]
+pub struct PullStats {
+    pub commits: u64,
+    pub additions: u64,
+    pub deletions: u64,
 }
 
 impl MergeCandidate {
-    #[must_use] pub fn new(pull: PullRequest) -> MergeCandidate {
-        MergeCandidate { pull }
+    #[must_use] pub fn new(pull: Pull) -> MergeCandidate {
+        let original_head = pull.head.sha.clone();
+        let original_base = pull.base.branch.clone();
+        MergeCandidate { pull, squash: false, merge_method: MergeMethod::default(), skip_validation: false, onto: None, onto_ref: None, stats: None, merged_as: None, landed: None, status: Status::Pending, retries: 0, original_head, original_base, new_head: None, range_diff: None, started: None, finished: None }
+    }
+
+    /** start the clock, unless it's already running from an earlier attempt */
+    pub fn start(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    /** move it along, stopping the clock once it's done */
+    pub fn set(&mut self, status: Status) {
+        self.status = status;
+        self.finished = status.is_final().then(Instant::now);
+    }
+
+    /** how long marge has been working on it, or took once it's done */
+    #[must_use] pub fn took(&self) -> Option<Duration> {
+        let started = self.started?;
+        Some(self.finished.unwrap_or_else(Instant::now) - started)
+    }
+
+    /** flip the option, or go to the next merge method */
+    pub fn toggle(&mut self, option: CandidateOption) {
+        match option {
+            CandidateOption::Squash => self.squash = !self.squash,
+            CandidateOption::MergeMethod => self.merge_method = self.merge_method.next(),
+            CandidateOption::SkipValidation => self.skip_validation = !self.skip_validation,
+            // needs the bases to pick from, see next_base
+            CandidateOption::Base => (),
+        }
+    }
+
+    /** go to the next of the bases to rebase onto, or back to the pull before it after the last one */
+    pub fn next_base(&mut self, bases: &[String]) {
+        let next = match &self.onto {
+            None => 0,
+            Some(onto) => bases.iter().position(|b| b == onto).map_or(bases.len(), |i| i + 1),
+        };
+        self.onto = bases.get(next).cloned();
+    }
+
+    /** the option and what it's set to, for the popup */
+    #[must_use] pub fn describe(&self, option: CandidateOption) -> String {
+        let check = |on: bool| if on { "[x]" } else { "[ ]" };
+        match option {
+            CandidateOption::Squash => format!("{} squash into one commit while rebasing", check(self.squash)),
+            CandidateOption::MergeMethod => format!("merge method: {}", self.merge_method.name()),
+            CandidateOption::SkipValidation => format!("{} skip validation", check(self.skip_validation)),
+            CandidateOption::Base => format!("base: {}", self.onto.as_deref().unwrap_or("the pull before it")),
+        }
     }
 
-    #[must_use] pub fn retarget(self) -> MergeCandidate {
-        MergeCandidate { pull: self.pull, }
+    /** the options that differ from the defaults, for the lists */
+    #[must_use] pub fn flags(&self) -> String {
+        let mut flags = String::new();
+        if self.squash {
+            flags += " [squash]";
+        }
+        if self.merge_method != MergeMethod::default() {
+            flags += &format!(" [{}]", self.merge_method.name());
+        }
+        if self.skip_validation {
+            flags += " [no validation]";
+        }
+        if let Some(onto) = &self.onto {
+            flags += &format!(" [onto {onto}]");
+        }
+        flags
     }
 }
\ No newline at end of file
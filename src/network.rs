@@ -0,0 +1,189 @@
+use std::{future::Future, io::BufReader, pin::Pin, sync::Arc, task::Poll, time::Duration};
+
+use anyhow::{anyhow, Context};
+use http::{
+    header::{AUTHORIZATION, USER_AGENT},
+    HeaderValue, Uri,
+};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::{TokioExecutor, TokioIo},
+};
+use octocrab::{
+    service::middleware::{base_uri::BaseUriLayer, extra_headers::ExtraHeadersLayer},
+    AuthState, Octocrab, OctocrabBuilder,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::settings::Network;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/** the proxy from the settings, or from HTTPS_PROXY like curl and git use it */
+fn proxy(network: &Network) -> Option<String> {
+    network
+        .proxy
+        .as_ref()
+        .map(|p| p.to_string())
+        .or_else(|| std::env::var("HTTPS_PROXY").ok())
+        .or_else(|| std::env::var("https_proxy").ok())
+        .filter(|p| !p.is_empty())
+}
+
+/** a github client that gives up on requests after timeout, going through the proxy and trusting the extra CAs if there are any */
+pub fn github(
+    token: String,
+    timeout: Option<Duration>,
+    network: &Network,
+) -> anyhow::Result<Octocrab> {
+    let proxy = proxy(network);
+    if proxy.is_none() && network.ca_bundle.is_none() {
+        return Ok(Octocrab::builder()
+            .personal_token(token)
+            .set_connect_timeout(timeout)
+            .set_read_timeout(timeout)
+            .set_write_timeout(timeout)
+            .build()?);
+    }
+
+    // octocrab's own client can't do either, so it gets the same stack with our connector
+    let tls = rustls::ClientConfig::builder()
+        .with_root_certificates(root_certificates(network)?)
+        .with_no_client_auth();
+    let connector = match proxy {
+        Some(proxy) => Connector::Proxy(
+            proxy
+                .parse()
+                .context(format!("{proxy} is not a valid proxy url"))?,
+        ),
+        None => {
+            let mut http = HttpConnector::new();
+            http.enforce_http(false);
+            Connector::Direct(http)
+        }
+    };
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls)
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(connector);
+    let mut connector = hyper_timeout::TimeoutConnector::new(https);
+    connector.set_connect_timeout(timeout);
+    connector.set_read_timeout(timeout);
+    connector.set_write_timeout(timeout);
+    let client = Client::builder(TokioExecutor::new()).build::<_, String>(connector);
+
+    let headers = vec![
+        (USER_AGENT, HeaderValue::from_static("marge")),
+        (
+            AUTHORIZATION,
+            format!("Bearer {token}")
+                .parse()
+                .context("the token is not a valid header")?,
+        ),
+    ];
+    Ok(OctocrabBuilder::new_empty()
+        .with_service(client)
+        .with_layer(&BaseUriLayer::new(Uri::from_static(
+            "https://api.github.com",
+        )))
+        .with_layer(&ExtraHeadersLayer::new(Arc::new(headers)))
+        .with_auth(AuthState::None)
+        .build()?)
+}
+
+/** the system's CAs plus the ones in the bundle from the settings */
+fn root_certificates(network: &Network) -> anyhow::Result<rustls::RootCertStore> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(
+        rustls_native_certs::load_native_certs().context("could not load the system's CAs")?,
+    );
+    if let Some(path) = &network.ca_bundle {
+        let file = std::fs::File::open(path)
+            .context(format!("could not open the CA bundle {}", path.display()))?;
+        let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+            .collect::<Result<Vec<_>, _>>()
+            .context(format!("could not read the CA bundle {}", path.display()))?;
+        let (added, _) = roots.add_parsable_certificates(certs);
+        if added == 0 {
+            return Err(anyhow!("no CAs in {}", path.display()));
+        }
+    }
+    Ok(roots)
+}
+
+/// opens the tcp connections for the github client, tls goes on top
+#[derive(Clone)]
+enum Connector {
+    Direct(HttpConnector),
+    /// tunnels through an http proxy with CONNECT
+    Proxy(Uri),
+}
+
+impl tower_service::Service<Uri> for Connector {
+    type Response = TokioIo<TcpStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), BoxError>> {
+        match self {
+            Connector::Direct(http) => http.poll_ready(cx).map_err(Into::into),
+            Connector::Proxy(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        match self {
+            Connector::Direct(http) => {
+                let connecting = http.call(dst);
+                Box::pin(async move { Ok(connecting.await?) })
+            }
+            Connector::Proxy(proxy) => Box::pin(tunnel(proxy.clone(), dst)),
+        }
+    }
+}
+
+/** connect to the proxy and have it open a tunnel to dst */
+async fn tunnel(proxy: Uri, dst: Uri) -> Result<TokioIo<TcpStream>, BoxError> {
+    let proxy_host = proxy.host().ok_or("the proxy url has no host")?;
+    let mut stream = TcpStream::connect((proxy_host, proxy.port_u16().unwrap_or(80))).await?;
+
+    let host = dst.host().ok_or("the url has no host")?;
+    let target = format!("{host}:{}", dst.port_u16().unwrap_or(443));
+    stream
+        .write_all(format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n").as_bytes())
+        .await?;
+
+    // the response is only headers, everything after them belongs to the tunnel
+    let mut response = vec![];
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() > 8192 {
+            return Err("the proxy sent too long a response".into());
+        }
+        let byte = stream.read_u8().await?;
+        response.push(byte);
+    }
+    let status = String::from_utf8_lossy(&response);
+    let status = status.lines().next().unwrap_or_default();
+    if status.split(' ').nth(1) != Some("200") {
+        return Err(format!("the proxy refused to connect to {target}: {status}").into());
+    }
+    Ok(TokioIo::new(stream))
+}
+
+/** the client for the webhook and the login. reqwest finds HTTPS_PROXY itself, so only the settings are applied */
+pub fn http_client(network: &Network) -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = &network.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy.clone())?);
+    }
+    if let Some(path) = &network.ca_bundle {
+        let pem = std::fs::read(path)
+            .context(format!("could not read the CA bundle {}", path.display()))?;
+        builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+    }
+    Ok(builder.build()?)
+}
@@ -0,0 +1,69 @@
+use log::warn;
+use url::Url;
+
+use crate::git::AppState;
+
+/// tells the user, and whoever follows the webhook, when marge needs them
+pub struct Notifier {
+    /// show desktop notifications, too
+    pub desktop: bool,
+    pub webhook: Option<Url>,
+    /// owner/repo, so the webhook messages make sense out of context
+    pub repo: String,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(
+        desktop: bool,
+        webhook: Option<Url>,
+        repo: String,
+        client: reqwest::Client,
+    ) -> Notifier {
+        Notifier {
+            desktop,
+            webhook,
+            repo,
+            client,
+        }
+    }
+
+    /** show a desktop notification and post to the webhook if they're set up. the screen rings the
+    bell, it owns the terminal */
+    pub fn notify(&self, state: &AppState, branch: &str, message: &'static str) {
+        if self.desktop {
+            // talking to the notification daemon blocks
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = notify_rust::Notification::new()
+                    .appname("marge")
+                    .summary("marge")
+                    .body(message)
+                    .show()
+                {
+                    warn!("could not show a desktop notification: {e}");
+                }
+            });
+        }
+
+        if let Some(webhook) = &self.webhook {
+            let event = match state {
+                AppState::Done => "finished",
+                AppState::Failed(_) => "failed",
+                _ => "waiting",
+            };
+            let payload = serde_json::json!({
+                "text": format!("marge {event} merging into {}/{branch}: {message}", self.repo),
+                "event": event,
+                "repo": self.repo,
+                "branch": branch,
+                "message": message,
+            });
+            let request = self.client.post(webhook.clone()).json(&payload);
+            tokio::spawn(async move {
+                if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+                    warn!("could not post to the webhook: {e}");
+                }
+            });
+        }
+    }
+}
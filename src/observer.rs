@@ -0,0 +1,202 @@
+use std::{
+    process::Output,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use log::debug;
+
+use crate::{
+    command_log::Ran,
+    forge::{Checks, Forge, MergeMethod, Pull},
+    git::{AppState, Remote},
+};
+
+/// follows the run from the outside, for code that wants to know what marge does without
+/// changing the pipeline: a bot driving it, a test, something emitting events. every callback
+/// does nothing unless it's overridden
+pub trait Observer: Send + Sync {
+    /** the app went from the state named from to the state */
+    fn state_changed(&self, _from: &'static str, _to: &AppState) {}
+
+    /** a command ran, with what it printed if that was captured */
+    fn command_ran(&self, _ran: &Ran, _output: Option<&Output>) {}
+
+    /** a call to the forge came back, with the error if it failed */
+    fn api_called(&self, _call: &'static str, _took: Duration, _error: Option<&anyhow::Error>) {}
+}
+
+/// the observers, in the order they were registered
+static OBSERVERS: RwLock<Vec<Arc<dyn Observer>>> = RwLock::new(vec![]);
+
+pub fn register(observer: Arc<dyn Observer>) {
+    if let Ok(mut observers) = OBSERVERS.write() {
+        observers.push(observer);
+    }
+}
+
+fn each(f: impl Fn(&dyn Observer)) {
+    if let Ok(observers) = OBSERVERS.read() {
+        for observer in observers.iter() {
+            f(&**observer);
+        }
+    }
+}
+
+pub fn state_changed(from: &'static str, to: &AppState) {
+    each(|o| o.state_changed(from, to));
+}
+
+pub fn command_ran(ran: &Ran, output: Option<&Output>) {
+    each(|o| o.command_ran(ran, output));
+}
+
+fn api_called(call: &'static str, took: Duration, error: Option<&anyhow::Error>) {
+    each(|o| o.api_called(call, took, error));
+}
+
+/// the observer marge always has, putting everything it sees into the debug log
+pub struct DebugLog;
+
+impl Observer for DebugLog {
+    fn state_changed(&self, from: &'static str, to: &AppState) {
+        debug!("state: {from} -> {}", to.name());
+    }
+
+    fn command_ran(&self, ran: &Ran, _output: Option<&Output>) {
+        debug!(
+            "ran {} in {}ms, exited with {:?}",
+            ran.line,
+            ran.took.as_millis(),
+            ran.code
+        );
+    }
+
+    fn api_called(&self, call: &'static str, took: Duration, error: Option<&anyhow::Error>) {
+        match error {
+            Some(e) => debug!("forge {call} failed after {}ms: {e:#}", took.as_millis()),
+            None => debug!("forge {call} took {}ms", took.as_millis()),
+        }
+    }
+}
+
+/** time the call to the forge and tell the observers how it went */
+fn observe<'a, T: Send + 'a>(
+    call: &'static str,
+    future: BoxFuture<'a, anyhow::Result<T>>,
+) -> BoxFuture<'a, anyhow::Result<T>> {
+    Box::pin(async move {
+        let started = Instant::now();
+        let result = future.await;
+        api_called(call, started.elapsed(), result.as_ref().err());
+        result
+    })
+}
+
+/// a forge whose calls the observers see
+pub struct Observed(pub Arc<dyn Forge>);
+
+impl Forge for Observed {
+    fn pulls<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<Vec<Pull>>> {
+        observe("pulls", self.0.pulls(remote))
+    }
+
+    fn default_branch<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        observe("default_branch", self.0.default_branch(remote))
+    }
+
+    fn pull<'a>(&'a self, remote: &'a Remote, number: u64) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        observe("pull", self.0.pull(remote, number))
+    }
+
+    fn check_access<'a>(
+        &'a self,
+        remote: &'a Remote,
+        read_only: bool,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        observe("check_access", self.0.check_access(remote, read_only))
+    }
+
+    fn login<'a>(&'a self, remote: &'a Remote) -> BoxFuture<'a, anyhow::Result<String>> {
+        observe("login", self.0.login(remote))
+    }
+
+    fn retarget<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        observe("retarget", self.0.retarget(remote, number, base))
+    }
+
+    fn open_pull<'a>(
+        &'a self,
+        remote: &'a Remote,
+        title: String,
+        body: String,
+        head: &'a str,
+        base: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Pull>> {
+        observe(
+            "open_pull",
+            self.0.open_pull(remote, title, body, head, base),
+        )
+    }
+
+    fn merge<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+        method: MergeMethod,
+    ) -> BoxFuture<'a, anyhow::Result<Option<String>>> {
+        observe("merge", self.0.merge(remote, number, method))
+    }
+
+    fn update_branch<'a>(
+        &'a self,
+        remote: &'a Remote,
+        number: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        observe("update_branch", self.0.update_branch(remote, number))
+    }
+
+    fn dispatch_workflow<'a>(
+        &'a self,
+        remote: &'a Remote,
+        workflow: &'a str,
+        branch: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        observe(
+            "dispatch_workflow",
+            self.0.dispatch_workflow(remote, workflow, branch),
+        )
+    }
+
+    fn workflow_run<'a>(
+        &'a self,
+        remote: &'a Remote,
+        workflow: &'a str,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        observe("workflow_run", self.0.workflow_run(remote, workflow, sha))
+    }
+
+    fn checks<'a>(
+        &'a self,
+        remote: &'a Remote,
+        sha: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Checks>> {
+        observe("checks", self.0.checks(remote, sha))
+    }
+
+    fn behind_by<'a>(
+        &'a self,
+        remote: &'a Remote,
+        base: &'a str,
+        head: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        observe("behind_by", self.0.behind_by(remote, base, head))
+    }
+}
@@ -0,0 +1,29 @@
+use std::io::Write;
+
+use crate::{git::Marge, ui};
+
+/// prints what the app pane would show, one block after the other as it changes instead of
+/// redrawing a screen, for --plain. screen readers and dumb terminals can follow that
+#[derive(Default)]
+pub struct Printer {
+    /// what was printed last, the same text isn't printed again
+    last: String,
+}
+
+impl Printer {
+    pub fn print(&mut self, marge: &Marge) -> anyhow::Result<()> {
+        let text = ui::app_text(marge, usize::MAX);
+        if text == self.last {
+            return Ok(());
+        }
+        let mut out = std::io::stdout().lock();
+        writeln!(out, "\n{text}")?;
+        // the states that wait for the user get a prompt for the answer
+        if marge.app_state.attention().is_some() {
+            write!(out, "> ")?;
+        }
+        out.flush()?;
+        self.last = text;
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    forge::{MergeMethod, Pull},
+    merge_candidate::MergeCandidate,
+};
+
+/// a merge chain saved with --save-plan, so a stack that comes up again and again
+/// doesn't have to be sorted by hand every time
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    pub pulls: Vec<PlannedPull>,
+}
+
+/// one pull of the chain, with the options it was chained with
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlannedPull {
+    pub number: u64,
+    #[serde(default)]
+    pub squash: bool,
+    #[serde(default)]
+    pub merge_method: MergeMethod,
+    #[serde(default)]
+    pub skip_validation: bool,
+    #[serde(default)]
+    pub onto: Option<String>,
+}
+
+impl Plan {
+    pub fn from_chain(chain: &[MergeCandidate]) -> Plan {
+        Plan {
+            pulls: chain
+                .iter()
+                .map(|c| PlannedPull {
+                    number: c.pull.number,
+                    squash: c.squash,
+                    merge_method: c.merge_method,
+                    skip_validation: c.skip_validation,
+                    onto: c.onto.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /** the pulls numbered 1 to count in order, which is how --local numbers its branches */
+    pub fn in_order(count: usize) -> Plan {
+        Plan {
+            pulls: (1..=count as u64)
+                .map(|number| PlannedPull {
+                    number,
+                    ..PlannedPull::default()
+                })
+                .collect(),
+        }
+    }
+
+    pub async fn load(path: &Path) -> anyhow::Result<Plan> {
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .context(format!("could not read the plan {}", path.display()))?;
+        toml::from_str(&contents).context(format!("could not parse the plan {}", path.display()))
+    }
+
+    pub async fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let contents = toml::to_string_pretty(self)?;
+        tokio::fs::write(path, contents)
+            .await
+            .context(format!("could not write the plan {}", path.display()))
+    }
+
+    /** pick the planned pulls out of the open ones, in the planned order. all of them need to be open still */
+    pub fn chain(&self, pulls: &[Pull]) -> anyhow::Result<Vec<MergeCandidate>> {
+        self.pulls
+            .iter()
+            .map(|planned| {
+                let pull = pulls
+                    .iter()
+                    .find(|p| p.number == planned.number)
+                    .ok_or_else(|| anyhow!("#{} from the plan isn't open", planned.number))?;
+                Ok(MergeCandidate {
+                    squash: planned.squash,
+                    merge_method: planned.merge_method,
+                    skip_validation: planned.skip_validation,
+                    onto: planned.onto.clone(),
+                    ..MergeCandidate::new(pull.clone())
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,131 @@
+use std::{future::Future, process::Stdio, time::Duration};
+
+use anyhow::{anyhow, Context};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use tokio::process::Command;
+
+use crate::{
+    command_log::Logged,
+    events::{AppEvent, EventPump},
+    Screen,
+};
+
+/// how long the remote gets to answer before marge assumes the network is down
+const REACH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// the checks run before marge starts and how they went. if one fails, they're shown on a screen
+/// of their own instead of an error dumped on the shell
+#[derive(Default)]
+pub struct Diagnostics {
+    checks: Vec<(&'static str, anyhow::Result<()>)>,
+}
+
+impl Diagnostics {
+    /** run a check, unless an earlier one failed already */
+    pub async fn check(
+        &mut self,
+        name: &'static str,
+        check: impl Future<Output = anyhow::Result<()>>,
+    ) {
+        if !self.failed() {
+            self.checks.push((name, check.await));
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.checks.iter().any(|(_, result)| result.is_err())
+    }
+
+    /** show what passed and what failed until a key is pressed, handing back what failed */
+    pub async fn show(self, screen: &mut Screen, events: &mut EventPump) -> anyhow::Error {
+        let _ = screen.draw(|t| self.render(t));
+        loop {
+            match events.next().await {
+                Some(AppEvent::Input(_) | AppEvent::Signal) | None => break,
+                _ => (),
+            }
+        }
+        self.into_error()
+    }
+
+    /** the first check that failed */
+    pub fn into_error(self) -> anyhow::Error {
+        self.checks
+            .into_iter()
+            .find_map(|(name, result)| Some(result.err()?.context(format!("{name} failed"))))
+            .unwrap_or_else(|| anyhow!("all the checks passed"))
+    }
+
+    fn render(&self, t: &mut Frame) {
+        let lines = self
+            .checks
+            .iter()
+            .map(|(name, result)| match result {
+                Ok(()) => format!("ok    {name}"),
+                Err(e) => format!("FAIL  {name}: {e:#}"),
+            })
+            .collect::<Vec<String>>();
+        let text = format!(
+            "marge can't start:\n\n{}\n\npress any key to quit",
+            lines.join("\n")
+        );
+        let block = Block::default().title("Diagnostics").borders(Borders::ALL);
+        let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
+        t.render_widget(paragraph, t.size());
+    }
+}
+
+pub async fn git_installed() -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .arg("--version")
+        .logged_output()
+        .await
+        .context("could not run git, is it installed and on the PATH?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git --version failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub async fn in_repo() -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .logged_output()
+        .await?;
+    if !output.status.success() {
+        let dir = std::env::current_dir()?;
+        return Err(anyhow!("{} isn't inside a git repo", dir.display()));
+    }
+    Ok(())
+}
+
+/** ask the remote for its branches, which fails if the network or the credentials for it are missing */
+pub async fn reachable(remote: &str) -> anyhow::Result<()> {
+    let mut command = Command::new("git");
+    command
+        .args(["ls-remote", "--heads", remote])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(REACH_TIMEOUT, command.logged_output())
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "{remote} didn't answer within {}s, is the network up?",
+                REACH_TIMEOUT.as_secs()
+            )
+        })??;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "could not reach {remote}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::anyhow;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{
+    events::{AppEvent, EventPump},
+    git::Remote,
+    Screen,
+};
+
+/** let the user pick the remote when there are several and --remote doesn't say which, with origin preselected */
+pub async fn pick(
+    screen: &mut Screen,
+    events: &mut EventPump,
+    mut remotes: Vec<Remote>,
+) -> anyhow::Result<Remote> {
+    remotes.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut current = remotes.iter().position(|r| r.name == "origin").unwrap_or(0);
+    loop {
+        screen.draw(|t| render(t, &remotes, current))?;
+        match events.next().await {
+            Some(AppEvent::Input(KeyEvent { code, .. })) => match code {
+                KeyCode::Up | KeyCode::Char('k') => current = current.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    current = (current + 1).min(remotes.len() - 1);
+                }
+                KeyCode::Enter => return Ok(remotes.swap_remove(current)),
+                KeyCode::Esc | KeyCode::Char('q') => break,
+                _ => (),
+            },
+            Some(AppEvent::Error(e)) => return Err(e),
+            Some(AppEvent::Signal) | None => break,
+            Some(_) => (),
+        }
+    }
+    Err(anyhow!("no remote was picked, pass one with --remote"))
+}
+
+fn render(t: &mut Frame, remotes: &[Remote], current: usize) {
+    let lines = remotes
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let brk = if i == current { ">> " } else { "   " };
+            format!("{brk}{}: {}/{} on {}", r.name, r.owner, r.repo, r.host)
+        })
+        .collect::<Vec<String>>();
+    let text = format!(
+        "there are several remotes, pick the one with the PRs and press enter. esc quits\n\n{}",
+        lines.join("\n")
+    );
+    let block = Block::default()
+        .title("Pick the remote")
+        .borders(Borders::ALL);
+    t.render_widget(Paragraph::new(text).block(block), t.size());
+}
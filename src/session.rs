@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use log::{debug, info, warn};
+use tokio::process::Command;
+
+use crate::{command_log::Logged, merge_candidate::MergeCandidate, plan::Plan};
+
+/// the rest of the chain of the run, kept per repo in the state dir so the next run can offer to
+/// go on with it if this one stops halfway. it's removed again once the chain is done
+pub struct Session {
+    /// none if the repo or the state dir couldn't be found, nothing is kept then
+    path: Option<PathBuf>,
+    /// how many pulls were left when it was last saved, none until this run saved it
+    saved: Option<usize>,
+}
+
+impl Session {
+    /** the session of the repo in the current wd */
+    pub async fn open() -> Session {
+        let path = match path().await {
+            Ok(path) => Some(path),
+            Err(e) => {
+                debug!("not keeping the session: {e:#}");
+                None
+            }
+        };
+        Session { path, saved: None }
+    }
+
+    /** a session that keeps nothing */
+    #[cfg(test)]
+    pub fn none() -> Session {
+        Session {
+            path: None,
+            saved: None,
+        }
+    }
+
+    /** the chain an earlier run in this repo left unfinished */
+    pub async fn unfinished(&self) -> Option<Plan> {
+        let path = self.path.as_ref()?;
+        if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+            return None;
+        }
+        match Plan::load(path).await {
+            Ok(plan) if !plan.pulls.is_empty() => Some(plan),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("{e:#}");
+                None
+            }
+        }
+    }
+
+    /** save the pulls that are left whenever there are fewer of them, removing the session once
+    none are */
+    pub async fn update(&mut self, left: &[&MergeCandidate]) {
+        if self.saved == Some(left.len()) {
+            return;
+        }
+        // a run that never saved one, like `marge plan`, leaves the last one alone
+        if left.is_empty() && self.saved.is_none() {
+            return;
+        }
+        self.saved = Some(left.len());
+        if left.is_empty() {
+            self.discard().await;
+            return;
+        }
+        let Some(path) = &self.path else {
+            return;
+        };
+        let chain = left.iter().map(|c| (*c).clone()).collect::<Vec<_>>();
+        let result = async {
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            Plan::from_chain(&chain).save(path).await
+        }
+        .await;
+        if let Err(e) = result {
+            warn!("could not keep the session in {}: {e:#}", path.display());
+        }
+    }
+
+    /** forget the unfinished chain, the user started over */
+    pub async fn discard(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => info!("removed the session {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => warn!("could not remove the session {}: {e}", path.display()),
+        }
+    }
+}
+
+/** the session file of the repo, named after its path so every checkout gets its own */
+async fn path() -> anyhow::Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .logged_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!("could not find the top of the repo"));
+    }
+    let repo = std::str::from_utf8(&output.stdout)?.trim();
+    let name = repo
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    Ok(crate::git::state_dir()?
+        .join("sessions")
+        .join(format!("{name}.toml")))
+}
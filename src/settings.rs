@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use url::Url;
+
+use crate::theme::Colors;
+
+/// the settings that stay the same from run to run, read from
+/// ~/.config/marge/config.toml
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub notifications: Notifications,
+    pub branches: Branches,
+    pub timeouts: Timeouts,
+    pub network: Network,
+    pub login: Login,
+    pub layout: Layout,
+    pub events: Events,
+    pub sorting: Sorting,
+    pub merging: Merging,
+    pub hooks: Hooks,
+    /// the forges on other hosts than github.com, by host name
+    pub hosts: HashMap<String, Host>,
+}
+
+/// the `[notifications]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Notifications {
+    /// gets a json POST whenever the run finishes, fails or waits for the user.
+    /// the payload has a `text` field, so slack incoming webhooks work as is
+    pub webhook: Option<Url>,
+}
+
+/// the `[branches]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Branches {
+    /// patterns like `main` or `release/*` for branches marge must never delete,
+    /// reset, rebase or force-push
+    pub protected: Vec<String>,
+}
+
+/// the `[timeouts]` table
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    /// seconds a push, fetch or github request may hang before marge gives up on it
+    /// and offers a retry. 0 waits forever
+    pub remote: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts { remote: 300 }
+    }
+}
+
+/// the `[network]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Network {
+    /// an http proxy like `http://proxy.corp:3128` to reach github and the webhook
+    /// through. HTTPS_PROXY is used if it's not set
+    pub proxy: Option<Url>,
+    /// a pem file with CAs to trust on top of the system's, for proxies that
+    /// intercept tls
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// the `[login]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Login {
+    /// the client id of the github oauth app `marge login --device` authorizes.
+    /// the app needs the device flow enabled
+    pub client_id: Option<String>,
+}
+
+/// the `[layout]` table
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layout {
+    /// how much of the width the app pane gets, in percent. the log gets the rest
+    pub app_width: u16,
+    pub colors: Colors,
+    /// how many milliseconds to wait at least between drawing the screen for the spinner and
+    /// the clocks. keys and new states are drawn right away
+    pub frame_ms: u64,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            app_width: 50,
+            colors: Colors::default(),
+            frame_ms: 150,
+        }
+    }
+}
+
+/// the `[events]` table
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Events {
+    /// milliseconds between the ticks that drive the steps, the spinner and the clocks
+    pub tick_ms: u64,
+    /// how many keys and ticks can wait to be handled before reading the terminal waits, too
+    pub capacity: usize,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Events {
+            tick_ms: 150,
+            capacity: 10,
+        }
+    }
+}
+
+/// the `[sorting]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sorting {
+    /// a regex finding the stack a pull belongs to and its place in it in the pull's title,
+    /// with a `stack` and an `index` group. the stacks it finds start out in the chain
+    pub stack: Option<String>,
+}
+
+/// the `[merging]` table
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Merging {
+    /// how often a merge the forge turned away because the base moved is tried again,
+    /// after bringing the pull up to date
+    pub retries: u32,
+    /// seconds to wait before each merge, on top of waiting for the previous one to land
+    pub gap: u64,
+}
+
+impl Default for Merging {
+    fn default() -> Self {
+        Merging { retries: 3, gap: 1 }
+    }
+}
+
+/// the `[hooks]` table: sh command lines run around the steps of each candidate, with
+/// MARGE_PR_NUMBER, MARGE_HEAD_REF, MARGE_BASE_REF and friends describing it
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    /// runs before rebasing, the rebase doesn't start if it fails
+    pub pre_rebase: Option<String>,
+    pub post_rebase: Option<String>,
+    /// runs before force-pushing, the push doesn't happen if it fails
+    pub pre_push: Option<String>,
+    /// runs after the forge merged the pull, with MARGE_MERGED_AS set to the merge commit
+    pub post_merge: Option<String>,
+}
+
+/// a `[hosts."git.example.com"]` table
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Host {
+    pub forge: ForgeKind,
+    /// where the token for this host is, instead of --token
+    pub token_file: Option<String>,
+    /// the api root, if it's not at https://<host>/api/v1/
+    pub api: Option<Url>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    Gitea,
+    Forgejo,
+    Azure,
+}
+
+impl Settings {
+    /** read the settings from path, or the default location if there's none. a missing default file is fine */
+    pub async fn load(path: Option<&Path>) -> anyhow::Result<Settings> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => match default_path() {
+                Some(path) if tokio::fs::try_exists(&path).await? => path,
+                _ => return Ok(Settings::default()),
+            },
+        };
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .context(format!("could not read {}", path.display()))?;
+        toml::from_str(&contents).context(format!("could not parse {}", path.display()))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("marge").join("config.toml"))
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use crate::{
+    forge::{Checks, Forge, Pull},
+    git::{connect, Remote},
+    AppArgs,
+};
+
+/** print the open pulls as the stacks they form, with their checks and whether they're behind their base. doesn't touch the repo */
+pub async fn print(args: &AppArgs) -> anyhow::Result<()> {
+    let (forge, remote) = connect(args).await?;
+    let pulls = forge.pulls(&remote).await?;
+    let states =
+        futures::future::join_all(pulls.iter().map(|p| pull_state(&*forge, &remote, p))).await;
+
+    println!(
+        "{}/{}: {} open pulls",
+        remote.owner,
+        remote.repo,
+        pulls.len()
+    );
+    let heads = pulls
+        .iter()
+        .map(|p| p.head.branch.as_str())
+        .collect::<HashSet<&str>>();
+    // the bottom of each stack is based on a branch that isn't a pull
+    let mut bases = pulls
+        .iter()
+        .map(|p| p.base.branch.as_str())
+        .filter(|base| !heads.contains(base))
+        .collect::<Vec<&str>>();
+    bases.sort_unstable();
+    bases.dedup();
+    for base in bases {
+        println!("\n{base}");
+        print_stack(&pulls, &states, base, 1);
+    }
+    Ok(())
+}
+
+/** print the pulls based on base, each followed by the pulls based on it */
+fn print_stack(pulls: &[Pull], states: &[String], base: &str, depth: usize) {
+    for (pull, state) in pulls.iter().zip(states) {
+        if pull.base.branch != base {
+            continue;
+        }
+        let title = pull.title.as_deref().unwrap_or("<no title>");
+        println!(
+            "{}#{} {} ({title}): {state}",
+            "  ".repeat(depth),
+            pull.number,
+            pull.head.branch
+        );
+        print_stack(pulls, states, &pull.head.branch, depth + 1);
+    }
+}
+
+/** the checks of the pull's head and how far it's behind its base, as far as the forge knows */
+async fn pull_state(forge: &dyn Forge, remote: &Remote, pull: &Pull) -> String {
+    let (checks, behind) = futures::join!(
+        forge.checks(remote, &pull.head.sha),
+        forge.behind_by(remote, &pull.base.branch, &pull.head.sha),
+    );
+
+    let checks = checks.map_or("checks unknown", Checks::describe);
+    let behind = match behind {
+        Ok(0) => "up to date".to_owned(),
+        Ok(behind) => format!("{behind} commits behind {}", pull.base.branch),
+        Err(_) => "unknown if up to date".to_owned(),
+    };
+    format!("{checks}, {behind}")
+}
@@ -0,0 +1,88 @@
+use ratatui::{prelude::*, widgets::Paragraph};
+use tokio::{process::Command, sync::mpsc::Receiver};
+
+use crate::{events::AppEvent, git::AppState, task, ui::truncate};
+
+/// the line at the bottom: which repo, what's checked out, what marge is doing and what runs
+/// in the background
+pub struct StatusBar {
+    slug: String,
+    /// the checked out branch and its short sha
+    head: Option<(String, String)>,
+    rx: Option<Receiver<anyhow::Result<(String, String)>>>,
+    /// ticks since the head was last looked up
+    ticks: u32,
+}
+
+/// look the head up again every so many ticks, in case something outside of marge moved it
+const REFRESH_TICKS: u32 = 20;
+
+impl StatusBar {
+    pub fn new(slug: String) -> StatusBar {
+        StatusBar {
+            slug,
+            head: None,
+            rx: None,
+            ticks: REFRESH_TICKS,
+        }
+    }
+
+    /** look the head up again whenever a git task finished, and every now and then */
+    pub fn update(&mut self, event: &AppEvent) {
+        if let Some(rx) = &mut self.rx {
+            if let Ok(head) = rx.try_recv() {
+                self.head = head.ok();
+                self.rx = None;
+            }
+        }
+        let stale = match event {
+            AppEvent::GitDone(..) => true,
+            AppEvent::Tick => {
+                self.ticks += 1;
+                self.ticks >= REFRESH_TICKS
+            }
+            _ => false,
+        };
+        if stale && self.rx.is_none() {
+            self.ticks = 0;
+            self.rx = Some(head());
+        }
+    }
+
+    pub fn render(&self, t: &mut Frame, state: &AppState, rect: Rect) {
+        let head = self
+            .head
+            .as_ref()
+            .map_or("?".to_owned(), |(branch, sha)| format!("{branch}@{sha}"));
+        let pending = task::pending();
+        let running = if pending.is_empty() {
+            String::new()
+        } else {
+            format!(" | running: {}", pending.join(", "))
+        };
+        let line = format!(" {} | {head} | {}{running}", self.slug, state.name());
+        let bar = Paragraph::new(truncate(&line, rect.width as usize))
+            .style(Style::new().add_modifier(Modifier::REVERSED));
+        t.render_widget(bar, rect);
+    }
+}
+
+/** the checked out branch and the short sha of HEAD */
+fn head() -> Receiver<anyhow::Result<(String, String)>> {
+    // not a task channel, its GitDone would have the head looked up again right away
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    tokio::spawn(async move {
+        let result = async {
+            let rev_parse = |args: &'static [&'static str]| async move {
+                let output = Command::new("git").args(args).output().await?;
+                Ok::<_, anyhow::Error>(std::str::from_utf8(&output.stdout)?.trim().to_owned())
+            };
+            let branch = rev_parse(&["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+            let sha = rev_parse(&["rev-parse", "--short", "HEAD"]).await?;
+            Ok::<_, anyhow::Error>((branch, sha))
+        }
+        .await;
+        let _ = tx.send(result).await;
+    });
+    rx
+}
@@ -0,0 +1,139 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+
+use crate::{clipboard::Clipboard, command_log, events::AppEvent};
+
+/// the views switched between with the number keys
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tab {
+    /// the app and the log side by side, where the run is driven from
+    Chain,
+    Log,
+    /// everything about the selected pull
+    Pull,
+    /// how far each candidate got and what the run changed
+    Report,
+    /// every command marge ran, to reproduce one by hand
+    Commands,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 5] = [Tab::Chain, Tab::Log, Tab::Pull, Tab::Report, Tab::Commands];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tab::Chain => "chain",
+            Tab::Log => "log",
+            Tab::Pull => "pull",
+            Tab::Report => "report",
+            Tab::Commands => "commands",
+        }
+    }
+}
+
+/// a text that's read by scrolling through it
+#[derive(Default)]
+pub struct TextView {
+    /// the first line shown
+    pub scroll: usize,
+}
+
+impl TextView {
+    fn key(&mut self, code: KeyCode) {
+        self.scroll = match code {
+            KeyCode::Char('j') | KeyCode::Down => self.scroll + 1,
+            KeyCode::Char('k') | KeyCode::Up => self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll + 10,
+            KeyCode::PageUp => self.scroll.saturating_sub(10),
+            KeyCode::Char('g') | KeyCode::Home => 0,
+            _ => self.scroll,
+        };
+    }
+
+    /** the scroll, clamped so the last line stays on the screen */
+    pub fn offset(&mut self, lines: usize, height: usize) -> u16 {
+        self.scroll = self.scroll.min(lines.saturating_sub(height));
+        u16::try_from(self.scroll).unwrap_or(u16::MAX)
+    }
+}
+
+pub struct Tabs {
+    pub active: Tab,
+    pub pull: TextView,
+    pub report: TextView,
+    /// the command that's copied with y
+    pub command: usize,
+}
+
+impl Tabs {
+    pub fn new() -> Tabs {
+        Tabs {
+            active: Tab::Chain,
+            pull: TextView::default(),
+            report: TextView::default(),
+            command: 0,
+        }
+    }
+
+    /** switch tabs with 1 to 5, scroll the text tabs and pick a command to copy. returns true if the app shouldn't see the key */
+    pub fn handle(&mut self, event: &AppEvent, clipboard: &mut Clipboard) -> bool {
+        let AppEvent::Input(KeyEvent { code, .. }) = event else {
+            return false;
+        };
+        if let KeyCode::Char(c @ '1'..='5') = code {
+            let index = *c as usize - '1' as usize;
+            self.active = Tab::ALL[index];
+            return true;
+        }
+        match self.active {
+            // the log takes its keys in the drawing, like it does next to the app
+            Tab::Chain | Tab::Log => false,
+            Tab::Pull => {
+                self.pull.key(*code);
+                true
+            }
+            Tab::Report => {
+                self.report.key(*code);
+                true
+            }
+            Tab::Commands => {
+                let ran = command_log::ran();
+                let last = ran.len().saturating_sub(1);
+                match code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        self.command = (self.command + 1).min(last)
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        self.command = self.command.saturating_sub(1)
+                    }
+                    KeyCode::Char('g') | KeyCode::Home => self.command = 0,
+                    KeyCode::Char('G') | KeyCode::End => self.command = last,
+                    KeyCode::Char('y') => {
+                        if let Some(ran) = ran.get(self.command) {
+                            clipboard.copy("the command", ran.line.clone());
+                        }
+                    }
+                    _ => (),
+                }
+                true
+            }
+        }
+    }
+
+    /** the tab names, with the active one standing out */
+    pub fn line(&self) -> Line<'static> {
+        let spans = Tab::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| {
+                let name = format!(" {} {} ", i + 1, tab.name());
+                if *tab == self.active {
+                    Span::styled(name, Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    Span::raw(name)
+                }
+            })
+            .collect::<Vec<Span>>();
+        Line::from(spans)
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex, OnceLock,
+};
+
+use tokio::sync::mpsc::{error::TryRecvError, Receiver, Sender};
+
+use crate::events::AppEvent;
+
+/// the event pump, so finished tasks can wake the app up without waiting for the next tick
+static EVENTS: OnceLock<Sender<AppEvent>> = OnceLock::new();
+
+/// the tasks that haven't sent their result yet, by id
+static PENDING: Mutex<Vec<(u64, &'static str)>> = Mutex::new(vec![]);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+pub fn init(events: Sender<AppEvent>) {
+    let _ = EVENTS.set(events);
+}
+
+/// the sending side of a background task. the result goes to the receiver the state holds on
+/// to, and a `GitDone` event goes to the event pump so the state gets to see it right away
+pub struct Done<T> {
+    id: u64,
+    op: &'static str,
+    tx: Sender<anyhow::Result<T>>,
+}
+
+/** a channel for the result of the background task op */
+pub fn channel<T>(op: &'static str) -> (Done<T>, Receiver<anyhow::Result<T>>) {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.push((id, op));
+    }
+    (Done { id, op, tx }, rx)
+}
+
+/** the tasks still running in the background, oldest first */
+pub fn pending() -> Vec<&'static str> {
+    PENDING
+        .lock()
+        .map(|pending| pending.iter().map(|(_, op)| *op).collect())
+        .unwrap_or_default()
+}
+
+fn finish(id: u64) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.retain(|(i, _)| *i != id);
+    }
+}
+
+impl<T> Done<T> {
+    pub async fn send(&self, result: anyhow::Result<T>) {
+        finish(self.id);
+        let event = self.event(&result);
+        if self.tx.send(result).await.is_ok() {
+            if let Some(events) = EVENTS.get() {
+                let _ = events.send(event).await;
+            }
+        }
+    }
+
+    /** send from outside of an async block. the result is dropped if one was sent already */
+    pub fn try_send(&self, result: anyhow::Result<T>) {
+        finish(self.id);
+        let event = self.event(&result);
+        if self.tx.try_send(result).is_ok() {
+            if let Some(events) = EVENTS.get() {
+                let _ = events.try_send(event);
+            }
+        }
+    }
+
+    fn event(&self, result: &anyhow::Result<T>) -> AppEvent {
+        AppEvent::GitDone(
+            self.op,
+            result.as_ref().map(|_| ()).map_err(|e| format!("{e:#}")),
+        )
+    }
+}
+
+impl<T> Drop for Done<T> {
+    fn drop(&mut self) {
+        // the task may have gone away without sending anything
+        finish(self.id);
+    }
+}
+
+/** the result of a task once it's done, `Some(None)` if it went away without one */
+pub fn finished<T>(rx: &mut Receiver<anyhow::Result<T>>) -> Option<Option<anyhow::Result<T>>> {
+    match rx.try_recv() {
+        Ok(result) => Some(Some(result)),
+        Err(TryRecvError::Empty) => None,
+        Err(TryRecvError::Disconnected) => Some(None),
+    }
+}
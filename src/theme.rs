@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use crate::merge_candidate::Status;
+
+/// how many colors marge draws with, the `colors` of the `[layout]` table
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colors {
+    /// basic unless the terminal says it has more
+    #[default]
+    Auto,
+    /// the 16 ansi colors, where dark gray often comes out as black
+    Basic,
+    Full,
+}
+
+/// whether only the 16 ansi colors can be relied on, set once the config is read
+static BASIC: OnceLock<bool> = OnceLock::new();
+
+pub fn init(colors: Colors) {
+    let _ = BASIC.set(match colors {
+        Colors::Auto => !full_color_terminal(),
+        Colors::Basic => true,
+        Colors::Full => false,
+    });
+}
+
+/** whether the terminal says it shows 256 colors or more */
+fn full_color_terminal() -> bool {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    let term = std::env::var("TERM").unwrap_or_default();
+    matches!(colorterm.as_str(), "truecolor" | "24bit") || term.contains("256color")
+}
+
+fn basic() -> bool {
+    BASIC.get().copied().unwrap_or(false)
+}
+
+/** for what's in the background, like the pane without focus */
+pub fn faint() -> Color {
+    if basic() {
+        Color::Gray
+    } else {
+        Color::DarkGray
+    }
+}
+
+pub fn status(status: Status) -> Color {
+    match status {
+        // gray is the default foreground of most basic terminals
+        Status::Pending if basic() => Color::Reset,
+        Status::Pending => Color::Gray,
+        Status::Rebased => Color::Cyan,
+        Status::Pushed => Color::Blue,
+        Status::Merged => Color::Green,
+        Status::Skipped => Color::Yellow,
+        Status::Failed => Color::Red,
+    }
+}
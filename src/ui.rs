@@ -0,0 +1,1475 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    prelude::*,
+    widgets::{
+        block::Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        Wrap,
+    },
+};
+use tui_logger::{TuiLoggerSmartWidget, TuiWidgetEvent};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    audit, command_log,
+    events::AppEvent,
+    git::{
+        self, ActivePane, AppState, AutoOrder, BranchPickState, ConflictState, Failure, Marge,
+        RepoStatus, SortingState, WorkingState,
+    },
+    log_search::LogSearch,
+    merge_candidate::{CandidateOption, MergeCandidate, Status},
+    status_bar::StatusBar,
+    tabs::{Tab, Tabs, TextView},
+    theme, validation_log,
+    viewer::Viewer,
+};
+
+/** draw the whole app, on the terminal or on any other backend */
+pub fn draw_frame(
+    t: &mut Frame,
+    marge: &mut Marge,
+    search: &LogSearch,
+    status_bar: &StatusBar,
+    tabs: &mut Tabs,
+) {
+    let size = t.size();
+
+    let main_block = Block::default().borders(Borders::NONE);
+    let main_area = main_block.inner(size);
+    t.render_widget(main_block, size);
+
+    let constraints = vec![
+        Constraint::Length(3), // title line
+        Constraint::Min(10),   // content
+        Constraint::Length(1), // status bar
+    ];
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(main_area);
+
+    render_title(t, marge, tabs, chunks[0]);
+    match tabs.active {
+        Tab::Chain => render_content(t, marge, search, chunks[1]),
+        Tab::Log => {
+            marge.active_pane = ActivePane::Log;
+            render_log(t, marge, search, chunks[1]);
+        }
+        Tab::Pull => {
+            let text = marge
+                .app_state
+                .current_candidate()
+                .map_or("<no pull selected>".to_owned(), format_pull);
+            render_text(t, "Pull", &text, &mut tabs.pull, chunks[1]);
+        }
+        Tab::Report => {
+            let text = format_report(marge);
+            render_text(t, "Report", &text, &mut tabs.report, chunks[1]);
+        }
+        Tab::Commands => render_commands(t, tabs.command, chunks[1]),
+    }
+    if let Some(viewer) = &mut marge.viewer {
+        render_viewer(t, viewer, chunks[1]);
+    }
+    if marge.quitting {
+        render_quit(t, marge.app_state.name(), chunks[1]);
+    }
+    status_bar.render(t, &marge.app_state, chunks[2]);
+}
+
+/** a tab that's just text, scrolled with j and k */
+fn render_text(t: &mut Frame, title: &str, text: &str, view: &mut TextView, rect: Rect) {
+    let block = Block::default()
+        .title(format!("{title} (j k scroll)"))
+        .borders(Borders::ALL);
+    let height = block.inner(rect).height as usize;
+    let offset = view.offset(text.lines().count(), height);
+    let paragraph = Paragraph::new(text.to_owned())
+        .block(block)
+        .scroll((offset, 0));
+    t.render_widget(paragraph, rect);
+}
+
+/** the commands marge ran, two lines each, scrolled so the selected one is on the screen */
+fn render_commands(t: &mut Frame, selected: usize, rect: Rect) {
+    let ran = command_log::ran();
+    let text = if ran.is_empty() {
+        "<no commands run yet>".to_owned()
+    } else {
+        ran.iter()
+            .enumerate()
+            .map(|(i, ran)| {
+                let brk = if i == selected { ">> " } else { "   " };
+                let code = ran
+                    .code
+                    .map_or("no exit code".to_owned(), |c| format!("exit {c}"));
+                format!(
+                    "{brk}{}\n     {code} after {:.1}s in {}",
+                    ran.line,
+                    ran.took.as_secs_f32(),
+                    ran.cwd
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    let block = Block::default()
+        .title("Commands (j k select, y copy)")
+        .borders(Borders::ALL);
+    let height = block.inner(rect).height as usize;
+    let offset = (selected * 2 + 2).saturating_sub(height);
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .scroll((u16::try_from(offset).unwrap_or(u16::MAX), 0));
+    t.render_widget(paragraph, rect);
+}
+
+/** everything known about a pull */
+fn format_pull(c: &MergeCandidate) -> String {
+    let pull = &c.pull;
+    let mut lines = vec![
+        format!(
+            "#{} {}",
+            pull.number,
+            pull.title.as_deref().unwrap_or("<untitled>")
+        ),
+        String::new(),
+        format!("{} onto {}", pull.head.branch, pull.base.branch),
+        format!("by {}", pull.author.as_deref().unwrap_or("?")),
+    ];
+    if let Some(url) = &pull.html_url {
+        lines.push(url.to_string());
+    }
+    if !pull.labels.is_empty() {
+        lines.push(format!("labels: {}", pull.labels.join(", ")));
+    }
+    if let (Some(created), Some(updated)) = (pull.created_at, pull.updated_at) {
+        lines.push(format!(
+            "opened {}, updated {}",
+            created.format("%Y-%m-%d %H:%M"),
+            updated.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    lines.push(format_columns(c));
+    lines.push(format!(
+        "status: {}{}{}",
+        c.status.name(),
+        c.flags(),
+        format_took(c)
+    ));
+    lines.push(format!("head: {}", c.original_head));
+    if let Some(head) = &c.new_head {
+        lines.push(format!("pushed as: {head}"));
+    }
+    if let Some(sha) = &c.merged_as {
+        lines.push(format!("merged as: {sha}"));
+    }
+    if let Some(diff) = &c.range_diff {
+        lines.push(String::new());
+        lines.push("range-diff of the rebase:".to_owned());
+        lines.push(diff.trim_end().to_owned());
+    }
+    lines.push(String::new());
+    lines.push(pull.body.clone().unwrap_or("<no description>".to_owned()));
+    lines.join("\n")
+}
+
+/** whether the merge commit turned up on the base after the final fetch */
+fn format_landed(c: &MergeCandidate) -> String {
+    match c.landed {
+        Some(true) => format!(", on {}", c.pull.base.branch),
+        Some(false) => format!(", NOT on {}", c.pull.base.branch),
+        None => String::new(),
+    }
+}
+
+/** what's wrong with the repo and how to get it clean */
+fn format_unclean(status: &RepoStatus) -> String {
+    let mut text = match status.in_progress {
+        Some(operation) => format!(
+            "a {0} is in progress in the repo, maybe left over from a run that was killed. \
+             finish it and press space, or press a to run git {0} --abort",
+            operation.name()
+        ),
+        None if status.dirty.is_empty() => "the repo is clean now, going on...".to_owned(),
+        None => {
+            "commit or stash these, marge goes on once the repo is clean. press space to check \
+                 right away"
+                .to_owned()
+        }
+    };
+    // the two status columns are the index and the work tree
+    let column = |i: usize| {
+        move |line: &&String| line.as_bytes().get(i).is_some_and(|c| !b" ?!".contains(c))
+    };
+    let groups = [
+        (
+            "staged",
+            status.dirty.iter().filter(column(0)).collect::<Vec<_>>(),
+        ),
+        ("modified", status.dirty.iter().filter(column(1)).collect()),
+        (
+            "untracked",
+            status
+                .dirty
+                .iter()
+                .filter(|l| l.starts_with("??"))
+                .collect(),
+        ),
+    ];
+    for (name, lines) in groups {
+        if lines.is_empty() {
+            continue;
+        }
+        text += &format!("\n\n{name}:");
+        for line in lines {
+            text += &format!("\n  {}", line.get(3..).unwrap_or(line));
+        }
+    }
+    text
+}
+
+/** which run of the validation this is, once a failed one was run again */
+fn format_attempt(c: &MergeCandidate) -> String {
+    if c.retries == 0 {
+        String::new()
+    } else {
+        format!(", attempt {}", c.retries + 1)
+    }
+}
+
+/** how far each candidate got, and what the run changed so far */
+fn format_report(marge: &Marge) -> String {
+    let mut candidates = marge.merged.iter().collect::<Vec<&MergeCandidate>>();
+    match marge.app_state.as_ref() {
+        AppState::Merging(s) => candidates.extend(&s.to_merge),
+        state => {
+            if let Some(s) = state.working_state() {
+                candidates.extend(&s.done);
+                candidates.push(&s.current_checkout);
+                candidates.extend(&s.next);
+            }
+        }
+    }
+    let lines = candidates
+        .iter()
+        .map(|c| {
+            let mut line = format!(
+                "#{} {}: {}{}",
+                c.pull.number,
+                c.pull.head.branch,
+                c.status.name(),
+                format_took(c)
+            );
+            if let Some(sha) = &c.merged_as {
+                line += &format!(", merged as {sha}{}", format_landed(c));
+            }
+            line
+        })
+        .collect::<Vec<String>>();
+    let lines = if lines.is_empty() {
+        "<nothing started yet>".to_owned()
+    } else {
+        lines.join("\n")
+    };
+    lines + &format_validation_logs() + &format_audit_trail()
+}
+
+/** the viewer goes over both panes */
+fn render_viewer(t: &mut Frame, viewer: &mut Viewer, rect: Rect) {
+    let block = Block::default().title(viewer.title()).borders(Borders::ALL);
+    let height = block.inner(rect).height as usize;
+    let text = Paragraph::new(viewer.visible(height)).block(block);
+    t.render_widget(Clear, rect);
+    t.render_widget(text, rect);
+}
+
+fn render_title(t: &mut Frame, marge: &mut Marge, tabs: &Tabs, rect: Rect) {
+    let title_block = Block::default().title(tabs.line()).borders(Borders::ALL);
+    let title_area = title_block.inner(rect);
+
+    let paused = if marge.paused {
+        " [paused, press p to resume]"
+    } else {
+        ""
+    };
+    let login = marge
+        .login
+        .as_ref()
+        .map_or(String::new(), |login| format!(" as @{login}"));
+    let progress = match format_chain_progress(marge) {
+        Some(progress) => format!(" | {progress}"),
+        None => String::new(),
+    };
+    let title = format!(
+        "Merging {}/{} ({}) into {}{login}{progress} | {}{paused}",
+        marge.remote.owner,
+        marge.remote.repo,
+        marge.remote.name,
+        marge.branch,
+        marge.app_state.name()
+    );
+    let title = Paragraph::new(truncate(&title, title_area.width as usize));
+    t.render_widget(title, title_area);
+    t.render_widget(title_block, rect);
+}
+
+/** cut the text down to width columns, ending in an ellipsis if anything was cut. wide chars
+like most cjk ones take two columns */
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_owned();
+    }
+    let mut cut = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        cut.push(c);
+        used += w;
+    }
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+/** how many pulls are chained and how many of them got through, pushed or further. none before
+there's a chain */
+fn format_chain_progress(marge: &Marge) -> Option<String> {
+    if let AppState::WaitingForSort(s) = marge.app_state.as_ref() {
+        return Some(format!("{} selected", s.merge_chain.len()));
+    }
+    let chain = marge.app_state.chain()?;
+    let mut pulls = marge.merged.iter().collect::<Vec<_>>();
+    for c in chain {
+        if !pulls.iter().any(|p| p.pull.number == c.pull.number) {
+            pulls.push(c);
+        }
+    }
+    if pulls.is_empty() {
+        return None;
+    }
+    let done = pulls
+        .iter()
+        .filter(|c| !matches!(c.status, Status::Pending | Status::Rebased))
+        .count();
+    Some(format!("{done}/{} done", pulls.len()))
+}
+
+fn render_content(t: &mut Frame, marge: &mut Marge, search: &LogSearch, rect: Rect) {
+    if let AppEvent::Input(KeyEvent { code, .. }) = marge.last_event {
+        match code {
+            // they're typed into the jump to a pull
+            _ if marge.app_state.typing() => (),
+            KeyCode::Left | KeyCode::Right => {
+                marge.active_pane = if marge.active_pane == ActivePane::List {
+                    ActivePane::Log
+                } else {
+                    ActivePane::List
+                }
+            }
+            KeyCode::Char('<') => {
+                marge.app_width = marge.app_width.saturating_sub(5).max(git::MIN_WIDTH);
+            }
+            KeyCode::Char('>') => marge.app_width = (marge.app_width + 5).min(100 - git::MIN_WIDTH),
+            KeyCode::Char('z') => marge.zoomed = !marge.zoomed,
+            _ => (),
+        }
+    }
+
+    let app_width = match (marge.zoomed, &marge.active_pane) {
+        (false, _) => marge.app_width,
+        (true, ActivePane::List) => 100,
+        (true, ActivePane::Log) => 0,
+    };
+    let constraints = vec![
+        Constraint::Percentage(app_width),       // lists
+        Constraint::Percentage(100 - app_width), // log
+    ];
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(rect);
+
+    if app_width > 0 {
+        render_app(t, marge, chunks[0]);
+    }
+    if app_width < 100 {
+        render_log(t, marge, search, chunks[1]);
+    }
+}
+
+fn render_app(t: &mut Frame, marge: &mut Marge, rect: Rect) {
+    let style = if marge.active_pane == ActivePane::List {
+        Style::new()
+    } else {
+        Style::new().fg(theme::faint())
+    };
+
+    let lists_block = Block::default()
+        .title("App")
+        .border_style(style)
+        .style(style)
+        .borders(Borders::ALL);
+    let lists_area = lists_block.inner(rect);
+
+    let content = app_text(marge, lists_area.width as usize);
+    let content = if marge.app_state.is_running() {
+        format_running(marge, &content)
+    } else {
+        content
+    };
+    let content = match marge.app_state.as_ref() {
+        AppState::Done | AppState::Failed(_) => {
+            content + &format_validation_logs() + &format_audit_trail()
+        }
+        _ => content,
+    };
+    let mut text = Text::from(content);
+    let working = match marge.app_state.as_ref() {
+        AppState::Done | AppState::Failed(_) => None,
+        state => state.working_state(),
+    };
+    if let Some(s) = working {
+        text.extend(std::iter::once(Line::default()).chain(chain_graph(&marge.branch, s)));
+    }
+    let lines = text.height();
+    let scroll = &mut marge.app_scroll;
+    scroll.height = lists_area.height as usize;
+    // keep the selected pull in view when it moves
+    let selected = text.lines.iter().position(|l| {
+        l.spans
+            .first()
+            .is_some_and(|span| span.content.starts_with(">>"))
+    });
+    if let Some(line) = selected.filter(|_| selected != scroll.selected) {
+        if line < scroll.offset {
+            scroll.offset = line;
+        } else if line >= scroll.offset + scroll.height {
+            scroll.offset = line + 1 - scroll.height;
+        }
+    }
+    scroll.selected = selected;
+    scroll.offset = scroll.offset.min(lines.saturating_sub(scroll.height));
+    let offset = scroll.offset;
+    let lists = Paragraph::new(text).scroll((u16::try_from(offset).unwrap_or(u16::MAX), 0));
+    t.render_widget(lists, lists_area);
+    t.render_widget(lists_block, rect);
+    if lines > lists_area.height as usize {
+        let mut state =
+            ScrollbarState::new(lines.saturating_sub(lists_area.height as usize)).position(offset);
+        t.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            rect.inner(&Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut state,
+        );
+    }
+
+    let sorting = match marge.app_state.as_ref() {
+        AppState::WaitingForSort(s) => Some(s),
+        AppState::EditingChain(s) => Some(&s.sorting),
+        _ => None,
+    };
+    if let Some(s) = sorting {
+        render_options(t, s, lists_area);
+    }
+}
+
+/** what the app pane says about the state, without the spinner. the pull lists fit into width
+columns */
+pub fn app_text(marge: &Marge, width: usize) -> String {
+    let content: String = match marge.app_state.as_ref() {
+        AppState::Failed(f) => format_failure(f),
+        AppState::Aborting(_) => "aborting...".to_owned(),
+        AppState::Aborted => "<aborted>".to_owned(),
+        AppState::WaitingForConfirmation(c) => format!("{}, or n to stop", c.prompt),
+        AppState::LoadingChainEdit(_) => "getting the open pulls...".to_owned(),
+        AppState::RefreshingChain(_) => "looking for new pulls...".to_owned(),
+        AppState::EditingChain(s) => format!(
+            "Edit the rest of the chain, then press space to go back\n\n{}",
+            format_candidates(&s.sorting, width)
+        ),
+        AppState::OfferingSession(plan) => format!(
+            "found an unfinished chain with {} remaining PRs ({}), press y to resume it or n to \
+             start over",
+            plan.pulls.len(),
+            plan.pulls
+                .iter()
+                .map(|p| format!("#{}", p.number))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        AppState::CheckingRepo(_) => "checking repo...".to_owned(),
+        AppState::WaitingForCleanRepo(c) => format_unclean(&c.status),
+        AppState::ListingBranches(_) => "listing remote branches...".to_owned(),
+        AppState::WaitingForBranch(state) => format_branches(state),
+        AppState::CheckingOutTargetBranch(_) => format!("checking out {}", marge.branch),
+        AppState::PullingRemote(_) => "pulling current state from remote...".to_owned(),
+        AppState::GettingPulls => "gettin pulls...".to_owned(),
+        AppState::WaitingForSort(state) => format_candidates(state, width),
+        AppState::RefreshingPulls(_) => "refreshing pulls...".to_owned(),
+        AppState::StartingChain(_) => "starting...".to_owned(),
+        AppState::UndoingRetargets(_) => {
+            "pointing retargeted pulls back at their old bases...".to_owned()
+        }
+        AppState::UpdatingCandidate(s) => format!(
+            "retargeting pr {} onto {}",
+            s.current_checkout.pull.head.branch,
+            s.base(&marge.branch)
+        ),
+        AppState::CheckingOutCandidate(_, s) => {
+            format!("checking out pr {}", s.current_checkout.pull.head.branch)
+        }
+        AppState::RebaseCandidate(_, s) => format!(
+            "rebasing pr {} onto {}",
+            s.current_checkout.pull.head.branch,
+            s.base(&marge.branch)
+        ),
+        AppState::CheckingForConflicts(_, s) => format!(
+            "checking the rebase of pr {} for conflicts",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::WaitingForResolution(c, _) => format_conflicts(c),
+        AppState::ComparingRebase(_, s) => format!(
+            "comparing pr {} before and after the rebase",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::ComparingBeforePush(_, s) => format!(
+            "comparing pr {} with its old head before pushing it",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::PreparingSquash(_, s) => format!(
+            "preparing the squash message of pr {}",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::WaitingForSquashMessage(path, _) => format!(
+            "edit the squash message in {}, then press space to squash. press e to edit the rest \
+             of the chain, r to add new pulls",
+            path.display()
+        ),
+        AppState::Squashing(_, s) => format!(
+            "squashing pr {} into one commit",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::UpdatingSubmodules(_, s) => format!(
+            "updating submodules of pr {}",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::PullingLfs(_, s) => format!(
+            "pulling lfs objects for pr {}",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::Validating(_, s) => format!(
+            "validating pr {}{}",
+            s.current_checkout.pull.head.branch,
+            format_attempt(&s.current_checkout)
+        ),
+        AppState::WaitingForFix(..) => {
+            "fix validation, then press space. press e to edit the rest of the chain, r to add \
+             new pulls"
+                .to_owned()
+        }
+        AppState::PushingCandidate(_, s) => format!(
+            "force-pushing the rebased pr {}",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::PushingValidated(p) => match p.done.get(p.current) {
+            Some(c) if p.push.is_some() => format!("pushing pr {}", c.pull.head.branch),
+            Some(c) => format!(
+                "waiting for the validation of pr {}{}",
+                c.pull.head.branch,
+                format_attempt(c)
+            ),
+            None => "pushing".to_owned(),
+        },
+        AppState::CheckingOutToFix(_, s) => format!(
+            "checking out pr {} to fix its validation",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::DispatchingWorkflow(s) => format!(
+            "running the workflow on pr {}",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::WaitingForWorkflow(s, _) => format!(
+            "waiting for the workflow on pr {} to pass{}",
+            s.current_checkout.pull.head.branch,
+            format_attempt(&s.current_checkout)
+        ),
+        AppState::WaitingForChecks(s, _) => format!(
+            "waiting for the checks of pr {} to pass",
+            s.current_checkout.pull.head.branch
+        ),
+        AppState::MergingCandidate(s) => {
+            format!("merging pr {}", s.current_checkout.pull.head.branch)
+        }
+        AppState::UpdatingTarget(..) => format!("updating {} with the merge", marge.branch),
+        AppState::CherryPicking(_, s) => format!(
+            "cherry-picking pr {} onto {}",
+            s.current_checkout.pull.head.branch,
+            git::integration_branch(&marge.branch)
+        ),
+        AppState::WaitingForPickResolution(..) => {
+            "resolve conflicts, then press space to cherry-pick continue. press v to view them, e \
+             to edit the rest of the chain, r to add new pulls"
+                .to_owned()
+        }
+        AppState::PushingIntegration(..) => format!(
+            "pushing {} and opening its pull",
+            git::integration_branch(&marge.branch)
+        ),
+        AppState::Merging(m) => match m.to_merge.first() {
+            Some(c) => format!(
+                "merging pr #{} {}: {}, {} left",
+                c.pull.number,
+                c.pull.head.branch,
+                c.pull.title.as_deref().unwrap_or("<untitled>"),
+                m.to_merge.len()
+            ),
+            None => "merging".to_owned(),
+        },
+        AppState::CleaningUp => {
+            "pointing pulls left on merged branches at the target...".to_owned()
+        }
+        AppState::VerifyingMerges(_) => "making sure the merges landed...".to_owned(),
+        AppState::Backporting(_, s) => format!(
+            "cherry-picking pr {} onto {}",
+            s.current.pull.head.branch,
+            s.branch_name()
+        ),
+        AppState::WaitingForBackportResolution(..) => {
+            "resolve conflicts, then press space to cherry-pick continue. press v to view them"
+                .to_owned()
+        }
+        AppState::PushingBackport(_, s) => format!("opening backport pull for {}", s.branch_name()),
+        AppState::Done if marge.merged.is_empty() => "<all done>".to_owned(),
+        AppState::Done => format!(
+            "<all done>\n\n{}\n\npress y to copy the merged commits, c to copy the changelog",
+            marge
+                .merged
+                .iter()
+                .map(|c| match c.status {
+                    Status::Merged => format!(
+                        "#{} merged as {}{}{}",
+                        c.pull.number,
+                        c.merged_as.as_deref().unwrap_or("?"),
+                        format_landed(c),
+                        format_took(c)
+                    ),
+                    status => format!("#{} {}{}", c.pull.number, status.name(), format_took(c)),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        ),
+    };
+    match marge.app_state.working_state() {
+        Some(s) => with_context(&content, &marge.branch, s),
+        None => content,
+    }
+}
+
+/** ask before cutting off a step that's in progress */
+fn render_quit(t: &mut Frame, step: &str, rect: Rect) {
+    let text = format!(
+        "{step} is in progress.\nabort it and clean up? y/n\n(ctrl-c again quits right away)"
+    );
+    let block = Block::default().title("Quit").borders(Borders::ALL);
+    let width = 50.min(rect.width);
+    let area = Rect {
+        x: rect.x + (rect.width - width) / 2,
+        y: rect.y + 2,
+        width,
+        height: 5.min(rect.height.saturating_sub(2)),
+    };
+    t.render_widget(Clear, area);
+    t.render_widget(Paragraph::new(text).block(block), area);
+}
+
+/** the options popup of the selected pull, if it's open */
+fn render_options(t: &mut Frame, state: &SortingState, rect: Rect) {
+    let (Some(row), Some(c)) = (state.options, state.unsorted.get(state.current_index)) else {
+        return;
+    };
+    let lines = CandidateOption::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, option)| {
+            let brk = if i == row { ">> " } else { "   " };
+            format!("{brk}{}", c.describe(*option))
+        })
+        .collect::<Vec<String>>();
+
+    let block = Block::default()
+        .title(format!(
+            "Options of #{} (space to change, esc to close)",
+            c.pull.number
+        ))
+        .borders(Borders::ALL);
+    let width = (lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4)
+        .max(50)
+        .min(rect.width);
+    let area = Rect {
+        x: rect.x + (rect.width - width) / 2,
+        y: rect.y + 2,
+        width,
+        height: (lines.len() as u16 + 2).min(rect.height.saturating_sub(2)),
+    };
+    t.render_widget(Clear, area);
+    t.render_widget(Paragraph::new(lines.join("\n")).block(block), area);
+}
+
+fn format_candidates(state: &SortingState, width: usize) -> String {
+    let chain_section = if state.merge_chain.is_empty() {
+        "<no pulls selected>".to_owned()
+    } else {
+        state
+            .merge_chain
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let moving = if state.moving == Some(i) {
+                    " [moving]"
+                } else {
+                    ""
+                };
+                format_candidate(c, &format!("{}{moving}", c.flags()), width).join("\n")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    let unsorted_section = if state.unsorted.is_empty() {
+        "<no pulls remaining>".to_owned()
+    } else {
+        state
+            .unsorted
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                // every line of the selected pull starts with the marker, so it shows however
+                // the list is scrolled
+                let brk = if state.current_index == i {
+                    "\n>> "
+                } else {
+                    "\n   "
+                };
+
+                let flags = c.flags();
+                let mark = match state.marked.iter().position(|n| *n == c.pull.number) {
+                    Some(position) => format!(" [marked {}]", position + 1),
+                    None => String::new(),
+                };
+                format_candidate(c, &format!("{flags}{mark}"), width.saturating_sub(3))
+                    .iter()
+                    .map(|line| format!("{brk}{line}"))
+                    .collect::<String>()
+            })
+            .collect::<String>()
+    };
+
+    let jump = match &state.jump {
+        Some(input) => format!(
+            " jump to #{}_ (a pull number or a place in the list, enter to jump, esc to cancel)\n",
+            input.text()
+        ),
+        None => String::new(),
+    };
+
+    let auto = match state.auto {
+        Some((order, _)) => format!(
+            "chained by {}, a to chain by {} instead",
+            order.name(),
+            order.next().name()
+        ),
+        None => format!("a to chain the rest by {}", AutoOrder::Created.name()),
+    };
+
+    format!(
+        "Merge Chain ({auto}, shift+up/down to move the last pull):\n{chain_section}\n\n=====\n\n Remaining Pulls by {} (x to \
+         mark, o to reorder, O for options, # to jump to a pull, r to refresh, d/c for the \
+         diff/commits, y/Y to copy url/branch):\n{jump}\
+         {unsorted_section}",
+        state.order.name()
+    )
+}
+
+/** the lines of a pull in the lists, cut to width columns. the title gets two lines */
+fn format_candidate(c: &MergeCandidate, flags: &str, width: usize) -> Vec<String> {
+    let Some(title) = &c.pull.title else {
+        return vec![truncate(&format!("<no title on {}>", c.pull.number), width)];
+    };
+    let header = format!("Pull #{}: {}{flags}", c.pull.number, c.pull.head.branch);
+    let mut lines = vec![truncate(&header, width)];
+    for line in wrap(title, width.saturating_sub(2), 2) {
+        lines.push(format!("  {line}"));
+    }
+    lines.push(format!(
+        "  {}",
+        truncate(&format_columns(c), width.saturating_sub(2))
+    ));
+    lines
+}
+
+/** break the text into lines of at most width columns at its spaces. the last of at most max
+lines ends in an ellipsis if the rest doesn't fit */
+fn wrap(text: &str, width: usize, max: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.width() + 1 + word.width() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    if lines.len() > max {
+        let rest = lines.split_off(max.saturating_sub(1)).join(" ");
+        lines.push(rest);
+    }
+    lines.iter().map(|line| truncate(line, width)).collect()
+}
+
+/** author, age, size, labels and base of a pull. the size shows up once it was fetched */
+fn format_columns(c: &MergeCandidate) -> String {
+    let author = c.pull.author.as_deref().unwrap_or("?");
+    let age = c.pull.created_at.map_or("?".to_owned(), |created| {
+        let age = chrono::Utc::now() - created;
+        if age.num_days() > 0 {
+            format!("{}d", age.num_days())
+        } else if age.num_hours() > 0 {
+            format!("{}h", age.num_hours())
+        } else {
+            format!("{}m", age.num_minutes())
+        }
+    });
+    let size = c.stats.map_or("? commits, +?/-?".to_owned(), |s| {
+        format!("{} commits, +{}/-{}", s.commits, s.additions, s.deletions)
+    });
+    let labels = c.pull.labels.join(", ");
+    format!(
+        "@{author} | {age} | {size} | [{labels}] | onto {}",
+        c.pull.base.branch
+    )
+}
+
+/** which pull the state works on, onto what and how far along the chain it is, right below the
+first line of the state's own text */
+fn with_context(content: &str, branch: &str, s: &WorkingState) -> String {
+    let c = &s.current_checkout;
+    let (first, rest) = content.split_once('\n').unwrap_or((content, ""));
+    let rest = if rest.is_empty() {
+        String::new()
+    } else {
+        format!("\n{rest}")
+    };
+    format!(
+        "{first}\npr #{} {}: {}\nonto {}, {} of {} in the chain{rest}",
+        c.pull.number,
+        c.pull.head.branch,
+        c.pull.title.as_deref().unwrap_or("<untitled>"),
+        s.base(branch),
+        s.done.len() + 1,
+        s.done.len() + 1 + s.next.len()
+    )
+}
+
+/** put a spinner and how long the state has been running in front of its first line */
+fn format_running(marge: &Marge, content: &str) -> String {
+    const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+    let secs = marge.state_since.elapsed().as_secs();
+    let (first, rest) = content.split_once('\n').unwrap_or((content, ""));
+    let rest = if rest.is_empty() {
+        String::new()
+    } else {
+        format!("\n{rest}")
+    };
+    format!(
+        "{} {first} ({}m {:02}s){rest}",
+        SPINNER[marge.ticks % SPINNER.len()],
+        secs / 60,
+        secs % 60
+    )
+}
+
+/** the chain as a graph hanging off the target, each pull on top of the one above it and colored
+by how far it got. a pull with a base set in its options starts a new branch of the graph */
+fn chain_graph(branch: &str, s: &WorkingState) -> Vec<Line<'static>> {
+    let current = s.current_checkout.pull.number;
+    let mut lines = vec![Line::from(format!("   {branch}"))];
+    for c in s.done.iter().chain([&s.current_checkout]).chain(&s.next) {
+        if let Some(onto) = &c.onto {
+            lines.push(Line::from(format!("   {onto}")));
+        }
+        let (marker, style) = if c.pull.number == current {
+            (">> ", Style::new().add_modifier(Modifier::BOLD))
+        } else {
+            ("   ", Style::new())
+        };
+        lines.push(Line::from(vec![
+            Span::raw(marker),
+            Span::styled(
+                format!(
+                    "\u{2190} #{} {} ({}{}{})",
+                    c.pull.number,
+                    c.pull.head.branch,
+                    c.status.name(),
+                    format_rebase_changes(c),
+                    format_took(c)
+                ),
+                style.fg(theme::status(c.status)),
+            ),
+        ]));
+    }
+    lines
+}
+
+/** how many commits the rebase changed, once it was compared */
+fn format_rebase_changes(c: &MergeCandidate) -> String {
+    match c.range_diff.as_deref().map(git::changed_commits) {
+        Some(0) | None => String::new(),
+        Some(changed) => format!(", {changed} changed by the rebase"),
+    }
+}
+
+/** how long the candidate took so far, if marge started on it */
+fn format_took(c: &MergeCandidate) -> String {
+    c.took()
+        .map(|took| {
+            let secs = took.as_secs();
+            format!(", took {}m {:02}s", secs / 60, secs % 60)
+        })
+        .unwrap_or_default()
+}
+
+/** what this run changed on the remote, see the audit file for earlier runs */
+fn format_audit_trail() -> String {
+    let trail = audit::trail();
+    if trail.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n\nchanges made by run {}:\n{}",
+        audit::run_id().unwrap_or("?"),
+        trail.join("\n")
+    )
+}
+
+/** where the validation output of each pull went, to look up what failed after the run */
+fn format_validation_logs() -> String {
+    let logs = validation_log::all();
+    if logs.is_empty() {
+        return String::new();
+    }
+    let lines = logs
+        .iter()
+        .map(|(number, path)| format!("#{number}: {}", path.display()))
+        .collect::<Vec<String>>();
+    format!("\n\nvalidation output:\n{}", lines.join("\n"))
+}
+
+fn format_failure(f: &Failure) -> String {
+    let command = f
+        .command()
+        .map(|c| {
+            let stderr = c
+                .stderr
+                .lines()
+                .rev()
+                .take(10)
+                .collect::<Vec<&str>>()
+                .into_iter()
+                .rev()
+                .collect::<Vec<&str>>()
+                .join("\n  ");
+            format!(
+                "\n\ncommand:   {}\nexit code: {}\nstderr:\n  {stderr}",
+                c.command,
+                c.exit_code
+                    .map(|c| c.to_string())
+                    .unwrap_or("<killed>".to_owned())
+            )
+        })
+        .unwrap_or_default();
+
+    let keys = if f.retry.is_some() {
+        "press r to retry, u to point retargeted pulls back at their old bases, q to abort"
+    } else {
+        "press u to point retargeted pulls back at their old bases, q to abort"
+    };
+
+    format!(
+        "FAILED while {}\n\n{:#}{command}\n\nhint: {}\n\n{keys}",
+        f.step,
+        f.error,
+        f.remediation()
+    )
+}
+
+fn format_conflicts(c: &ConflictState) -> String {
+    let files = c
+        .files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let brk = if c.current_index == i { "\n>> " } else { "\n " };
+            if f.owners.is_empty() {
+                format!("{brk}{}", f.path)
+            } else {
+                format!(
+                    "{brk}{}{brk}  last touched by {}",
+                    f.path,
+                    f.owners.join(", ")
+                )
+            }
+        })
+        .collect::<String>();
+
+    format!(
+        "resolve conflicts, then press space to rebase continue. press v to view them, e to edit \
+         the rest of the chain, r to add new pulls.\n\no takes the base's side of the selected \
+         file, t the pull's side:\n{files}"
+    )
+}
+
+fn format_branches(state: &BranchPickState) -> String {
+    let branches = state
+        .branches
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if state.current_index == i {
+                format!("\n>> {b}")
+            } else {
+                format!("\n {b}")
+            }
+        })
+        .collect::<String>();
+
+    format!("Pick the branch to merge into, then press enter:\n{branches}")
+}
+
+fn render_log(t: &mut Frame, marge: &mut Marge, search: &LogSearch, rect: Rect) {
+    let style = if marge.active_pane == ActivePane::Log {
+        let maybe_event = match marge.last_event {
+            AppEvent::Input(KeyEvent { code, .. }) => match code {
+                KeyCode::Up | KeyCode::PageUp | KeyCode::Char('k') => {
+                    Some(TuiWidgetEvent::PrevPageKey)
+                }
+                KeyCode::Down | KeyCode::PageDown | KeyCode::Char('j') => {
+                    Some(TuiWidgetEvent::NextPageKey)
+                }
+                // back to following the newest lines
+                KeyCode::Char(' ' | 'G') | KeyCode::Esc | KeyCode::End => {
+                    Some(TuiWidgetEvent::EscapeKey)
+                }
+                // target selection
+                KeyCode::Char('K') => Some(TuiWidgetEvent::UpKey),
+                KeyCode::Char('J') => Some(TuiWidgetEvent::DownKey),
+                KeyCode::Char('f') => Some(TuiWidgetEvent::FocusKey),
+                KeyCode::Char('h') => Some(TuiWidgetEvent::HideKey),
+                KeyCode::Char('o') => Some(TuiWidgetEvent::SpaceKey),
+                // shown level of the selected target
+                KeyCode::Char('[') => Some(TuiWidgetEvent::LeftKey),
+                KeyCode::Char(']') => Some(TuiWidgetEvent::RightKey),
+                // captured level of the selected target
+                KeyCode::Char('-') => Some(TuiWidgetEvent::MinusKey),
+                KeyCode::Char('+') => Some(TuiWidgetEvent::PlusKey),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(e) = maybe_event {
+            marge.log_state.transition(&e);
+        }
+
+        Style::new()
+    } else {
+        let e = TuiWidgetEvent::EscapeKey;
+        marge.log_state.transition(&e);
+        Style::new().fg(theme::faint())
+    };
+
+    let rect = if search.is_active() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rect);
+        render_search(t, search, style, chunks[1]);
+        chunks[0]
+    } else {
+        rect
+    };
+
+    let tui_w: TuiLoggerSmartWidget = TuiLoggerSmartWidget::default()
+        .title_log("Logs (/ search, h targets, [ ] level, z zoom)")
+        .title_target("Targets (J K select, f focus)")
+        .border_style(style)
+        .style(style)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .output_separator(' ')
+        .output_timestamp(Some("%H:%M".to_string()))
+        .output_level(None)
+        .output_target(false)
+        .output_file(false)
+        .output_line(false)
+        .state(&marge.log_state);
+
+    t.render_widget(tui_w, rect);
+}
+
+fn render_search(t: &mut Frame, search: &LogSearch, style: Style, rect: Rect) {
+    let block = Block::default()
+        .title(search.title())
+        .border_style(style)
+        .style(style)
+        .borders(Borders::ALL);
+
+    // the newest matches are the interesting ones
+    let height = block.inner(rect).height as usize;
+    let matches = search.matches();
+    let lines = matches[matches.len().saturating_sub(height)..]
+        .iter()
+        .map(|l| search.highlight(l))
+        .collect::<Vec<Line>>();
+
+    let results = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    t.render_widget(results, rect);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use anyhow::anyhow;
+    use ratatui::backend::TestBackend;
+    use tokio::sync::mpsc::Receiver;
+
+    use super::*;
+    use crate::git::tests::{candidate, confirmation, conflicts, marge};
+    use crate::git::{
+        BackportState, ChainEditState, CleanState, Conflict, MergingState, Operation, ParallelPush,
+    };
+    use crate::{plan::Plan, settings::Merging};
+
+    /** draw marge in the state onto a screen of 100x30 and read it back line by line. the app
+    pane is zoomed, the log changes with every run */
+    fn draw(state: AppState, merged: Vec<MergeCandidate>) -> Vec<String> {
+        let mut marge = marge(state);
+        marge.merged = merged;
+        marge.zoomed = true;
+        let search = LogSearch::new(std::path::PathBuf::new());
+        let status_bar = StatusBar::new("o/r".to_owned());
+        let mut tabs = Tabs::new();
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal
+            .draw(|f| draw_frame(f, &mut marge, &search, &status_bar, &mut tabs))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+            .collect()
+    }
+
+    /** a receiver nothing is ever sent on. not a task channel, which would show up as running in
+    the status bar of every other test */
+    fn waiting<T>() -> Receiver<anyhow::Result<T>> {
+        tokio::sync::mpsc::channel(1).1
+    }
+
+    /** the middle of a chain of three, with #1 done and #3 up next */
+    fn working() -> WorkingState {
+        let mut done = candidate(1, "first");
+        done.set(Status::Pushed);
+        WorkingState {
+            current_checkout: candidate(2, "second"),
+            next: vec![candidate(3, "third")],
+            done: vec![done],
+        }
+    }
+
+    fn backport() -> BackportState {
+        BackportState {
+            target: "release".to_owned(),
+            current: candidate(1, "first"),
+            next: vec![candidate(2, "second")],
+            done: vec![],
+            opened: vec![],
+        }
+    }
+
+    fn sorting() -> SortingState {
+        SortingState::new(
+            vec![candidate(2, "second"), candidate(3, "third")],
+            vec![candidate(1, "first")],
+        )
+    }
+
+    /** every state marge can be in, by name */
+    fn every_state() -> Vec<(&'static str, AppState)> {
+        let dirty = RepoStatus {
+            in_progress: Some(Operation::Rebase),
+            dirty: vec![" M src/main.rs".to_owned()],
+        };
+        let conflicts = conflicts(vec![Conflict {
+            path: "src/main.rs".to_owned(),
+            owners: vec!["someone".to_owned()],
+        }]);
+        let chain = working()
+            .done
+            .into_iter()
+            .chain([working().current_checkout]);
+        let failure = || Failure::new("pushing", anyhow!("the push was rejected"));
+        vec![
+            (
+                "offering_session",
+                AppState::OfferingSession(Plan::in_order(2)),
+            ),
+            ("checking_repo", AppState::CheckingRepo(waiting())),
+            (
+                "waiting_for_clean_repo",
+                AppState::WaitingForCleanRepo(CleanState {
+                    status: dirty,
+                    refresh: None,
+                    at: Instant::now(),
+                }),
+            ),
+            ("listing_branches", AppState::ListingBranches(waiting())),
+            (
+                "waiting_for_branch",
+                AppState::WaitingForBranch(BranchPickState {
+                    branches: vec!["main".to_owned(), "release".to_owned()],
+                    current_index: 1,
+                }),
+            ),
+            (
+                "checking_out_target_branch",
+                AppState::CheckingOutTargetBranch(waiting()),
+            ),
+            ("pulling_remote", AppState::PullingRemote(waiting())),
+            ("getting_pulls", AppState::GettingPulls),
+            ("waiting_for_sort", AppState::WaitingForSort(sorting())),
+            ("refreshing_pulls", AppState::RefreshingPulls(sorting())),
+            (
+                "starting_chain",
+                AppState::StartingChain(chain.clone().collect()),
+            ),
+            ("undoing_retargets", AppState::UndoingRetargets(failure())),
+            ("updating_candidate", AppState::UpdatingCandidate(working())),
+            (
+                "checking_out_candidate",
+                AppState::CheckingOutCandidate(waiting(), working()),
+            ),
+            (
+                "rebase_candidate",
+                AppState::RebaseCandidate(waiting(), working()),
+            ),
+            (
+                "checking_for_conflicts",
+                AppState::CheckingForConflicts(waiting(), working()),
+            ),
+            (
+                "waiting_for_resolution",
+                AppState::WaitingForResolution(conflicts, working()),
+            ),
+            (
+                "comparing_rebase",
+                AppState::ComparingRebase(waiting(), working()),
+            ),
+            (
+                "comparing_before_push",
+                AppState::ComparingBeforePush(waiting(), working()),
+            ),
+            (
+                "preparing_squash",
+                AppState::PreparingSquash(waiting(), working()),
+            ),
+            (
+                "waiting_for_squash_message",
+                AppState::WaitingForSquashMessage("SQUASH_MSG".into(), working()),
+            ),
+            ("squashing", AppState::Squashing(waiting(), working())),
+            (
+                "updating_submodules",
+                AppState::UpdatingSubmodules(waiting(), working()),
+            ),
+            ("pulling_lfs", AppState::PullingLfs(waiting(), working())),
+            ("validating", AppState::Validating(waiting(), working())),
+            ("waiting_for_fix", AppState::WaitingForFix(working())),
+            (
+                "pushing_candidate",
+                AppState::PushingCandidate(waiting(), working()),
+            ),
+            (
+                "pushing_validated",
+                AppState::PushingValidated(ParallelPush {
+                    done: chain.clone().collect(),
+                    current: 1,
+                    validation: Some(waiting()),
+                    push: None,
+                }),
+            ),
+            (
+                "checking_out_to_fix",
+                AppState::CheckingOutToFix(waiting(), working()),
+            ),
+            (
+                "dispatching_workflow",
+                AppState::DispatchingWorkflow(working()),
+            ),
+            (
+                "waiting_for_workflow",
+                AppState::WaitingForWorkflow(working(), Instant::now()),
+            ),
+            (
+                "waiting_for_checks",
+                AppState::WaitingForChecks(working(), Instant::now()),
+            ),
+            ("merging_candidate", AppState::MergingCandidate(working())),
+            (
+                "updating_target",
+                AppState::UpdatingTarget(waiting(), working()),
+            ),
+            (
+                "cherry_picking",
+                AppState::CherryPicking(waiting(), working()),
+            ),
+            (
+                "waiting_for_pick_resolution",
+                AppState::WaitingForPickResolution(working()),
+            ),
+            (
+                "pushing_integration",
+                AppState::PushingIntegration(waiting(), working()),
+            ),
+            (
+                "merging",
+                AppState::Merging(MergingState::new(chain.collect(), Merging::default())),
+            ),
+            ("cleaning_up", AppState::CleaningUp),
+            ("verifying_merges", AppState::VerifyingMerges(waiting())),
+            ("backporting", AppState::Backporting(waiting(), backport())),
+            (
+                "waiting_for_backport_resolution",
+                AppState::WaitingForBackportResolution(backport()),
+            ),
+            (
+                "pushing_backport",
+                AppState::PushingBackport(waiting(), backport()),
+            ),
+            ("done", AppState::Done),
+            (
+                "failed",
+                AppState::Failed(failure().with_retry(|| AppState::Done)),
+            ),
+            ("aborting", AppState::Aborting(waiting())),
+            ("aborted", AppState::Aborted),
+            (
+                "waiting_for_confirmation",
+                confirmation("press y to merge #1"),
+            ),
+            (
+                "loading_chain_edit",
+                AppState::LoadingChainEdit(Box::new(AppState::WaitingForFix(working()))),
+            ),
+            (
+                "refreshing_chain",
+                AppState::RefreshingChain(Box::new(AppState::WaitingForFix(working()))),
+            ),
+            (
+                "editing_chain",
+                AppState::EditingChain(ChainEditState {
+                    sorting: sorting(),
+                    waiting: Box::new(AppState::WaitingForFix(working())),
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn every_state_draws_as_before() {
+        for (name, state) in every_state() {
+            insta::assert_snapshot!(name, draw(state, vec![]).join("\n"));
+        }
+    }
+
+    fn shows(screen: &[String], text: &str) -> bool {
+        screen.iter().any(|line| line.contains(text))
+    }
+
+    #[test]
+    fn sorting_shows_both_lists_and_the_selection() {
+        let state = SortingState::new(
+            vec![candidate(2, "second"), candidate(3, "third")],
+            vec![candidate(1, "first")],
+        );
+        let screen = draw(AppState::WaitingForSort(state), vec![]);
+        assert!(screen[1].contains("Merging o/r (origin) into main | 1 selected"));
+        assert!(shows(&screen, "Pull #1: pr-1"));
+        assert!(shows(&screen, ">> Pull #2: pr-2"));
+        assert!(shows(&screen, "   Pull #3: pr-3"));
+        assert!(!shows(&screen, "<no pulls selected>"));
+    }
+
+    #[test]
+    fn branch_picking_marks_the_selected_branch() {
+        let state = BranchPickState {
+            branches: vec!["main".to_owned(), "release".to_owned()],
+            current_index: 1,
+        };
+        let screen = draw(AppState::WaitingForBranch(state), vec![]);
+        assert!(shows(&screen, "Pick the branch to merge into"));
+        assert!(shows(&screen, ">> release"));
+        assert!(!shows(&screen, ">> main"));
+    }
+
+    #[test]
+    fn confirmation_shows_the_prompt() {
+        let screen = draw(confirmation("press y to merge #1"), vec![]);
+        assert!(shows(&screen, "press y to merge #1, or n to stop"));
+        assert!(screen[29].contains("waiting for confirmation"));
+    }
+
+    #[test]
+    fn failures_offer_a_retry_only_if_there_is_one() {
+        let failure = Failure::new("pushing", anyhow!("the push was rejected"));
+        let screen = draw(AppState::Failed(failure), vec![]);
+        assert!(shows(&screen, "FAILED while pushing"));
+        assert!(shows(&screen, "the push was rejected"));
+        assert!(shows(&screen, "press u to point retargeted pulls"));
+        assert!(!shows(&screen, "press r to retry"));
+
+        let failure =
+            Failure::new("pushing", anyhow!("the push was rejected")).with_retry(|| AppState::Done);
+        let screen = draw(AppState::Failed(failure), vec![]);
+        assert!(shows(&screen, "FAILED while pushing"));
+        assert!(shows(&screen, "press r to retry"));
+    }
+
+    #[test]
+    fn done_lists_the_merged_pulls() {
+        assert!(shows(&draw(AppState::Done, vec![]), "<all done>"));
+
+        let mut merged = candidate(1, "first");
+        merged.set(Status::Merged);
+        merged.merged_as = Some("abc123".to_owned());
+        let screen = draw(AppState::Done, vec![merged]);
+        assert!(shows(&screen, "<all done>"));
+        assert!(shows(&screen, "#1 merged as abc123"));
+        assert!(shows(&screen, "press y to copy the merged commits"));
+    }
+}
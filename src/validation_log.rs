@@ -0,0 +1,74 @@
+use std::{io::Write, path::PathBuf, process::Output};
+
+use log::warn;
+
+use crate::audit;
+
+/** where the validation output of the pull goes in this run. every attempt is appended to it */
+pub fn path(number: u64) -> Option<PathBuf> {
+    Some(dir()?.join(format!("{number}.log")))
+}
+
+/** the dir with this run's validation output, next to the audit file */
+fn dir() -> Option<PathBuf> {
+    run_dir(audit::run_id()?)
+}
+
+fn run_dir(run_id: &str) -> Option<PathBuf> {
+    Some(
+        crate::git::state_dir()
+            .ok()?
+            .join("validations")
+            .join(run_id),
+    )
+}
+
+/** keep the output of a validation of the pull, only warning if that fails since the
+validation itself went through */
+pub fn write(number: u64, cmd: &str, output: &Output) {
+    let Some(path) = path(number) else {
+        return;
+    };
+    let result = (|| {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%z");
+        writeln!(file, "=== {timestamp} {cmd}")?;
+        file.write_all(&output.stdout)?;
+        writeln!(file, "--- stderr")?;
+        file.write_all(&output.stderr)?;
+        writeln!(file, "=== {}\n", output.status)
+    })();
+    if let Err(e) = result {
+        warn!(
+            "could not keep the validation output in {}: {e}",
+            path.display()
+        );
+    }
+}
+
+/** the pulls validated in this run and where their output is, by number */
+pub fn all() -> Vec<(u64, PathBuf)> {
+    audit::run_id().map(of_run).unwrap_or_default()
+}
+
+/** the pulls validated in the run and where their output is, by number */
+pub fn of_run(run_id: &str) -> Vec<(u64, PathBuf)> {
+    let Some(entries) = run_dir(run_id).and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return vec![];
+    };
+    let mut logs = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let number = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((number, path))
+        })
+        .collect::<Vec<(u64, PathBuf)>>();
+    logs.sort();
+    logs
+}
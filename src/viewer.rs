@@ -0,0 +1,183 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use tokio::sync::mpsc::Receiver;
+
+use crate::{events::AppEvent, theme};
+
+/// a read-only text shown over the panes, like the diff of a pull, until it's closed with q
+pub struct Viewer {
+    title: String,
+    highlight: Highlight,
+    /// the text as it's being loaded in the background
+    rx: Option<Receiver<anyhow::Result<String>>>,
+    lines: Vec<(String, Style)>,
+    /// the first line shown
+    scroll: usize,
+    /// how many lines fit on the screen, as of the last draw
+    height: usize,
+}
+
+impl Viewer {
+    pub fn new(
+        title: String,
+        highlight: Highlight,
+        rx: Receiver<anyhow::Result<String>>,
+    ) -> Viewer {
+        Viewer {
+            title,
+            highlight,
+            rx: Some(rx),
+            lines: vec![("loading...".to_owned(), Style::new())],
+            scroll: 0,
+            height: 1,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        format!(
+            "{} ({}/{}, j k scroll, space page, {}q close)",
+            self.title,
+            (self.scroll + 1).min(self.lines.len()),
+            self.lines.len(),
+            match self.highlight {
+                Highlight::Diff | Highlight::Conflicts => "n next hunk, ",
+                Highlight::Commits => "",
+            }
+        )
+    }
+
+    /** handle a key while the viewer is open. returns false once it was closed */
+    pub fn handle(&mut self, event: &AppEvent) -> bool {
+        if let Some(rx) = &mut self.rx {
+            if let Ok(text) = rx.try_recv() {
+                self.lines = match text {
+                    Ok(text) if text.is_empty() => {
+                        vec![("<nothing to show>".to_owned(), Style::new())]
+                    }
+                    Ok(text) => self.highlight.lines(&text),
+                    Err(e) => vec![(format!("{e:#}"), Style::new())],
+                };
+                self.rx = None;
+            }
+        }
+        let AppEvent::Input(KeyEvent { code, .. }) = event else {
+            return true;
+        };
+        let last = self.lines.len().saturating_sub(1);
+        self.scroll = match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('j') | KeyCode::Down => self.scroll + 1,
+            KeyCode::Char('k') | KeyCode::Up => self.scroll.saturating_sub(1),
+            KeyCode::Char(' ') | KeyCode::PageDown => self.scroll + self.height,
+            KeyCode::PageUp => self.scroll.saturating_sub(self.height),
+            KeyCode::Char('g') | KeyCode::Home => 0,
+            KeyCode::Char('G') | KeyCode::End => last,
+            // the next line starting a hunk, wrapping around at the end
+            KeyCode::Char('n') => {
+                let starts = |i: &usize| self.highlight.starts_hunk(&self.lines[*i].0);
+                (self.scroll + 1..self.lines.len())
+                    .find(starts)
+                    .or_else(|| (0..self.scroll).find(starts))
+                    .unwrap_or(self.scroll)
+            }
+            _ => self.scroll,
+        }
+        .min(last);
+        true
+    }
+
+    /** the lines that fit into height, colored by what they are */
+    pub fn visible(&mut self, height: usize) -> Vec<Line<'_>> {
+        self.height = height.max(1);
+        self.lines
+            .iter()
+            .skip(self.scroll)
+            .take(self.height)
+            .map(|(l, style)| Line::styled(l.as_str(), *style))
+            .collect()
+    }
+}
+
+/// what the text is, which decides how its lines are colored
+#[derive(Clone, Copy)]
+pub enum Highlight {
+    /// a unified diff, colored like git colors it
+    Diff,
+    /// one commit per line, with the ones that still need squashing standing out
+    Commits,
+    /// files with conflict markers, one side green and the other one red
+    Conflicts,
+}
+
+impl Highlight {
+    /** split text into its lines and their styles */
+    fn lines(self, text: &str) -> Vec<(String, Style)> {
+        // which side of a conflict the line is on, only the conflicts keep track
+        let mut side = Style::new();
+        text.lines()
+            .map(|line| {
+                let style = match self {
+                    Highlight::Diff => diff_style(line),
+                    Highlight::Commits => commit_style(line),
+                    Highlight::Conflicts => {
+                        let marker = Style::new().add_modifier(Modifier::BOLD);
+                        if line.starts_with("<<<<<<<") {
+                            side = Style::new().fg(Color::Green);
+                            marker
+                        } else if line.starts_with("|||||||") {
+                            side = Style::new().fg(theme::faint());
+                            marker
+                        } else if line.starts_with("=======") && side != Style::new() {
+                            side = Style::new().fg(Color::Red);
+                            marker
+                        } else if line.starts_with(">>>>>>>") {
+                            side = Style::new();
+                            marker
+                        } else if line.starts_with("==> ") {
+                            Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+                        } else {
+                            side
+                        }
+                    }
+                };
+                (line.to_owned(), style)
+            })
+            .collect()
+    }
+
+    /** whether n stops at the line */
+    fn starts_hunk(self, line: &str) -> bool {
+        match self {
+            Highlight::Diff => line.starts_with("@@"),
+            Highlight::Commits => false,
+            Highlight::Conflicts => line.starts_with("<<<<<<<"),
+        }
+    }
+}
+
+fn commit_style(line: &str) -> Style {
+    let subject = line.split_once(": ").map_or("", |(_, s)| s);
+    if ["fixup! ", "squash! ", "amend! "]
+        .iter()
+        .any(|p| subject.starts_with(p))
+    {
+        Style::new().fg(Color::Yellow)
+    } else {
+        Style::new()
+    }
+}
+
+fn diff_style(line: &str) -> Style {
+    let header = ["diff ", "index ", "+++", "---"];
+    if header.iter().any(|h| line.starts_with(h)) {
+        Style::new().add_modifier(Modifier::BOLD)
+    } else if line.starts_with("@@") {
+        Style::new().fg(Color::Cyan)
+    } else if line.starts_with('+') {
+        Style::new().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::new().fg(Color::Red)
+    } else {
+        Style::new()
+    }
+}
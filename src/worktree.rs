@@ -0,0 +1,71 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Context};
+use log::{info, warn};
+use tokio::{process::Command, sync::mpsc::Receiver};
+
+use crate::command_log::Logged;
+
+/// the validations running in worktrees with --parallel, by the number of the pull they
+/// validate. they outlive the states the candidate goes through while the next ones are rebased
+static RUNNING: Mutex<Vec<(u64, Receiver<anyhow::Result<bool>>)>> = Mutex::new(vec![]);
+
+pub fn track(number: u64, rx: Receiver<anyhow::Result<bool>>) {
+    if let Ok(mut running) = RUNNING.lock() {
+        running.retain(|(n, _)| *n != number);
+        running.push((number, rx));
+    }
+}
+
+/** the validation of the pull, handed over to the state waiting for it */
+pub fn take(number: u64) -> Option<Receiver<anyhow::Result<bool>>> {
+    let mut running = RUNNING.lock().ok()?;
+    let index = running.iter().position(|(n, _)| *n == number)?;
+    Some(running.swap_remove(index).1)
+}
+
+/** check head out into a fresh worktree, without touching the checkout marge works in */
+pub async fn add(head: &str, number: u64, skip_lfs: bool) -> anyhow::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("marge-{}-{number}", std::process::id()));
+    info!("checking {head} out into {}", dir.display());
+    let mut command = Command::new("git");
+    command
+        .args(["worktree", "add", "--force", "--detach"])
+        .arg(&dir)
+        .arg(head);
+    if skip_lfs {
+        command.env("GIT_LFS_SKIP_SMUDGE", "1");
+    }
+    let output = command
+        .logged_output()
+        .await
+        .context("could not add a worktree")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git worktree add failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(dir)
+}
+
+/** remove the worktree again, only warning if that fails since the validation is done by then */
+pub async fn remove(dir: &Path) {
+    let output = Command::new("git")
+        .args(["worktree", "remove", "--force"])
+        .arg(dir)
+        .logged_output()
+        .await;
+    match output {
+        Ok(o) if o.status.success() => (),
+        Ok(o) => warn!(
+            "could not remove the worktree {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&o.stderr).trim()
+        ),
+        Err(e) => warn!("could not remove the worktree {}: {e}", dir.display()),
+    }
+}